@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::{self, BufRead, Read, Seek, Write};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::chip8::octo::Metadata;
+
+/// Files that come along with ROMs in collections but are not ROMs.
+const NOT_ROMS: &[&str] = &["json", "txt", "md", "nfo", "png", "gif", "jpg", "html"];
+
+/// A ROM extracted from a ZIP archive, with its Octo metadata when the
+/// archive has a `<stem>.json` next to it.
+pub struct Extracted {
+    pub name: String,
+    pub rom: Vec<u8>,
+    pub metadata: Option<Metadata>,
+}
+
+pub fn is_zip(filename: &str) -> bool {
+    Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Whether `name` could be a ROM rather than one of the files that come
+/// along with them.
+pub fn is_rom_name(name: &str) -> bool {
+    !Path::new(name).extension().is_some_and(|ext| {
+        NOT_ROMS
+            .iter()
+            .any(|not_rom| ext.eq_ignore_ascii_case(not_rom))
+    })
+}
+
+/// Extract the ROM in the archive at `filename`, asking which one to play
+/// when there are several.
+pub fn extract(filename: &str) -> io::Result<Extracted> {
+    let mut archive = ZipArchive::new(File::open(filename)?)?;
+    let roms = roms(&mut archive);
+    let name = match roms.as_slice() {
+        [] => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "archive contains no ROM",
+            ))
+        }
+        [name] => name.clone(),
+        _ => pick(&roms).ok_or_else(|| io::Error::from(io::ErrorKind::Interrupted))?,
+    };
+    read_entry(&mut archive, &name)
+}
+
+/// The names of the entries that look like ROMs, in archive order.
+fn roms<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<String> {
+    let mut roms = Vec::new();
+    for i in 0..archive.len() {
+        let entry = match archive.by_index_raw(i) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if entry.is_file() && is_rom_name(entry.name()) {
+            roms.push(entry.name().to_string());
+        }
+    }
+    roms
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> io::Result<Extracted> {
+    let mut rom = Vec::new();
+    archive.by_name(name)?.read_to_end(&mut rom)?;
+
+    let sidecar = Path::new(name).with_extension("json");
+    let metadata = match archive.by_name(&sidecar.to_string_lossy()) {
+        Ok(mut entry) => {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            Metadata::from_json(&json)
+                .map_err(|e| eprintln!("Ignoring metadata for `{name}`: {e}"))
+                .ok()
+        }
+        Err(_) => None,
+    };
+
+    Ok(Extracted {
+        name: name.to_string(),
+        rom,
+        metadata,
+    })
+}
+
+fn pick(roms: &[String]) -> Option<String> {
+    println!("The archive contains several ROMs, pick one:");
+    for (i, name) in roms.iter().enumerate() {
+        println!("  {}) {name}", i + 1);
+    }
+
+    loop {
+        print!("> ");
+        io::stdout().flush().ok()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        let choice = line.trim();
+        let rom = match choice.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| roms.get(i)),
+            Err(_) => roms.iter().find(|name| *name == choice),
+        };
+        match rom {
+            Some(rom) => return Some(rom.clone()),
+            None => println!("No ROM `{choice}`"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn extract_bytes(bytes: Vec<u8>) -> io::Result<Extracted> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        let name = roms(&mut archive).into_iter().next().unwrap();
+        read_entry(&mut archive, &name)
+    }
+
+    #[test]
+    fn test_extract_with_metadata() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_directory("games/", FileOptions::default()).unwrap();
+        zip.start_file("games/readme.txt", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"Have fun").unwrap();
+        zip.start_file("games/pong.ch8", FileOptions::default())
+            .unwrap();
+        zip.write_all(&[0x12, 0x00]).unwrap();
+        zip.start_file("games/pong.json", FileOptions::default())
+            .unwrap();
+        zip.write_all(br#"{"title": "Pong"}"#).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let extracted = extract_bytes(bytes).unwrap();
+        assert_eq!(extracted.name, "games/pong.ch8");
+        assert_eq!(extracted.rom, [0x12, 0x00]);
+        assert_eq!(extracted.metadata.unwrap().title.as_deref(), Some("Pong"));
+    }
+}
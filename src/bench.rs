@@ -0,0 +1,189 @@
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use argh::FromArgs;
+
+use crate::chip8::profile;
+use crate::chip8::{Chip8Error, CHIP8, STEPS_PER_FRAME};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "bench")]
+/// Run a ROM without a window as fast as it goes and report how fast the
+/// interpreter is
+pub struct BenchArgs {
+    #[argh(positional)]
+    /// the ROM to run, e.g. one written by `genbench`
+    pub filename: String,
+
+    #[argh(option, default = "1_000_000")]
+    /// how many instructions to run
+    cycles: u64,
+}
+
+/// The time spent on one kind of opcode.
+#[derive(Debug, Clone, PartialEq)]
+struct Timing {
+    /// The pattern, like `8XY4`.
+    kind: String,
+    runs: u64,
+    time: Duration,
+}
+
+impl Timing {
+    /// Nanoseconds per run, on average.
+    fn mean(&self) -> f64 {
+        self.time.as_nanos() as f64 / self.runs.max(1) as f64
+    }
+}
+
+/// What a benchmark measured.
+#[derive(Debug)]
+struct Report {
+    /// Instructions run, fewer than asked for if the program stopped.
+    cycles: u64,
+    /// The time to run them, without timing each one.
+    elapsed: Duration,
+    /// Per kind of opcode, the time spent most first.
+    timings: Vec<Timing>,
+    /// Why the program stopped early, if it did.
+    stopped: Option<String>,
+}
+
+impl Report {
+    fn per_second(&self) -> f64 {
+        self.cycles as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions: {}", self.cycles)?;
+        writeln!(f, "Time:         {:.3}s", self.elapsed.as_secs_f64())?;
+        writeln!(f, "Speed:        {:.0} instructions/s", self.per_second())?;
+        if let Some(reason) = &self.stopped {
+            writeln!(f, "Stopped:      {}", reason)?;
+        }
+        let total: Duration = self.timings.iter().map(|t| t.time).sum();
+        write!(f, "Opcodes:         runs    ns/op   time")?;
+        for timing in &self.timings {
+            write!(
+                f,
+                "\n  {:<6} {:>10} {:>8.1} {:>5.1}%",
+                timing.kind,
+                timing.runs,
+                timing.mean(),
+                timing.time.as_nanos() as f64 * 100.0 / total.as_nanos().max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl BenchArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let mut chip8 = CHIP8::headless();
+        chip8.load(&self.filename)?;
+        let report = bench(&mut chip8, self.cycles);
+        println!("{}", report);
+        Ok(())
+    }
+}
+
+/// Run up to `cycles` instructions twice from power on: once flat out, for
+/// the speed, then timing every instruction, for the time per opcode. Timers
+/// tick every `STEPS_PER_FRAME` instructions, as in a trace.
+fn bench(chip8: &mut CHIP8, cycles: u64) -> Report {
+    let start = Instant::now();
+    let (ran, stopped) = run(chip8, cycles, |chip8| chip8.step().map(|_| ()));
+    let elapsed = start.elapsed();
+
+    chip8.reset();
+    let mut by_opcode = vec![(0, Duration::ZERO); 1 << 16];
+    run(chip8, ran, |chip8| {
+        let opcode = chip8.current_opcode().unwrap_or_default();
+        let start = Instant::now();
+        chip8.step()?;
+        let entry = &mut by_opcode[opcode as usize];
+        *entry = (entry.0 + 1, entry.1 + start.elapsed());
+        Ok(())
+    });
+
+    let mut timings: Vec<Timing> = Vec::new();
+    for (opcode, &(runs, time)) in by_opcode.iter().enumerate() {
+        if runs == 0 {
+            continue;
+        }
+        let kind = profile::kind(opcode as u16);
+        match timings.iter_mut().find(|t| t.kind == kind) {
+            Some(timing) => {
+                timing.runs += runs;
+                timing.time += time;
+            }
+            None => timings.push(Timing { kind, runs, time }),
+        }
+    }
+    timings.sort_by(|a, b| b.time.cmp(&a.time).then(a.kind.cmp(&b.kind)));
+
+    Report {
+        cycles: ran,
+        elapsed,
+        timings,
+        stopped,
+    }
+}
+
+/// Run `step` up to `cycles` times, giving how many ran and why it stopped
+/// short, if it did.
+fn run(
+    chip8: &mut CHIP8,
+    cycles: u64,
+    mut step: impl FnMut(&mut CHIP8) -> Result<(), Chip8Error>,
+) -> (u64, Option<String>) {
+    for cycle in 0..cycles {
+        if chip8.has_halted() {
+            return (cycle, Some("PC ran off the end of RAM".to_string()));
+        }
+        if let Err(e) = step(chip8) {
+            return (cycle, Some(e.to_string()));
+        }
+        if (cycle + 1) % STEPS_PER_FRAME as u64 == 0 {
+            chip8.tick_timers();
+        }
+    }
+    (cycles, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x01, // LD V0, 0x01
+                0x70, 0x01, // ADD V0, 0x01
+                0x12, 0x02, // JP 0x202
+            ])
+            .unwrap();
+        let report = bench(&mut chip8, 101);
+        assert_eq!(report.cycles, 101);
+        assert_eq!(report.stopped, None);
+        let mut runs: Vec<_> = (report.timings.iter())
+            .map(|t| (t.kind.as_str(), t.runs))
+            .collect();
+        runs.sort();
+        assert_eq!(runs, [("1NNN", 50), ("6XNN", 1), ("7XNN", 50)]);
+
+        let mut chip8 = CHIP8::headless();
+        chip8.load_bytes(&[0xE0, 0x00]).unwrap();
+        let report = bench(&mut chip8, 10);
+        assert_eq!(report.cycles, 0);
+        assert_eq!(
+            report.stopped.as_deref(),
+            Some("unknown opcode E000 at 0x200")
+        );
+    }
+}
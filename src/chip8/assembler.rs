@@ -0,0 +1,823 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+/// Why Octo source could not be assembled, and on which line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+type AsmResult<T> = Result<T, AsmError>;
+
+/// Assemble the Octo program `source` into a ROM to load at 0x200.
+///
+/// Octo's statements, labels, `:const`, `:alias`, `:org`, `:byte`, `:call`,
+/// `:unpack` and `:next` are understood, as are `if ... then`, `if ...
+/// begin ... else ... end` and `loop ... while ... again`. Execution starts
+/// at the `main` label; `:macro`, `:calc` and `:stringmode` are not
+/// supported.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::new(source);
+    while asm.pos < asm.tokens.len() {
+        asm.statement()?;
+    }
+    asm.finish()
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+/// How a reference to a label is filled in once the label is defined.
+#[derive(Debug, Clone, Copy)]
+enum Fixup {
+    /// The low 12 bits of the opcode at the address.
+    Addr,
+    /// The 16 bit word at the address.
+    Long,
+    /// The byte at the address: the given high nibble, then the top 4 bits
+    /// of the 12 bit label, as `:unpack` loads into v0.
+    High(u8),
+    /// The byte at the address: the label's high byte.
+    HighByte,
+    /// The byte at the address: the label's low byte.
+    Low,
+}
+
+/// A block that is still open, and the line it was opened on.
+enum Block {
+    /// `if ... begin`, with the jump past its body to fill in.
+    If { jump: usize, line: usize },
+    /// `else`, with the jump past its body to fill in.
+    Else { jump: usize, line: usize },
+    /// `loop`, with the jumps out of it `while` made.
+    Loop {
+        start: usize,
+        exits: Vec<usize>,
+        line: usize,
+    },
+}
+
+/// A test compiled to the instructions that skip the next one: `skip_unless`
+/// when it is false, for `then`, and `skip_if` when it is true, for `begin`
+/// and `while`. Comparisons first compute into vF with `setup`.
+struct Condition {
+    setup: Vec<u16>,
+    skip_unless: u16,
+    skip_if: u16,
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    rom: Vec<u8>,
+    here: usize,
+    /// Whether the ROM starts with a jump to `main`, which it does unless
+    /// `main` comes first.
+    jump_to_main: bool,
+    labels: HashMap<&'a str, u16>,
+    consts: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    pending: Vec<(usize, Fixup, Token<'a>)>,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(n, line)| {
+                let code = line.split('#').next().unwrap_or_default();
+                code.split_whitespace()
+                    .map(move |text| Token { text, line: n + 1 })
+            })
+            .collect();
+        Assembler {
+            tokens,
+            pos: 0,
+            rom: vec![0x10, 0x00],
+            here: 0x202,
+            jump_to_main: true,
+            labels: HashMap::new(),
+            consts: HashMap::new(),
+            aliases: HashMap::new(),
+            pending: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos.saturating_sub(1)) {
+            Some(token) => token.line,
+            None => 1,
+        }
+    }
+
+    fn error<T>(&self, line: usize, message: String) -> AsmResult<T> {
+        Err(AsmError { line, message })
+    }
+
+    fn next(&mut self) -> AsmResult<Token<'a>> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(*token)
+            }
+            None => self.error(self.line(), "unexpected end of the program".into()),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn expect(&mut self, text: &str) -> AsmResult<()> {
+        let token = self.next()?;
+        if token.text != text {
+            return self.error(
+                token.line,
+                format!("expected `{}`, got `{}`", text, token.text),
+            );
+        }
+        Ok(())
+    }
+
+    fn register_named(&self, text: &str) -> Option<u8> {
+        if let Some(&reg) = self.aliases.get(text) {
+            return Some(reg);
+        }
+        match text.as_bytes() {
+            [b'v' | b'V', digit] => (*digit as char).to_digit(16).map(|reg| reg as u8),
+            _ => None,
+        }
+    }
+
+    fn register(&mut self) -> AsmResult<u16> {
+        let token = self.next()?;
+        match self.register_named(token.text) {
+            Some(reg) => Ok(reg.into()),
+            None => self.error(
+                token.line,
+                format!("expected a register, got `{}`", token.text),
+            ),
+        }
+    }
+
+    /// The value of a number or constant.
+    fn constant(&self, text: &str) -> Option<i64> {
+        if let Some(&value) = self.consts.get(text) {
+            return Some(value);
+        }
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i64::from_str_radix(binary, 2).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    fn value(&mut self) -> AsmResult<i64> {
+        let token = self.next()?;
+        match self.constant(token.text) {
+            Some(value) => Ok(value),
+            None => self.error(
+                token.line,
+                format!("expected a number, got `{}`", token.text),
+            ),
+        }
+    }
+
+    fn byte(&mut self) -> AsmResult<u16> {
+        match self.value()? {
+            value @ -128..=255 => Ok((value & 0xFF) as u16),
+            value => self.error(self.line(), format!("{} does not fit in a byte", value)),
+        }
+    }
+
+    fn nibble(&mut self) -> AsmResult<u16> {
+        match self.value()? {
+            value @ 0..=15 => Ok(value as u16),
+            value => self.error(self.line(), format!("{} does not fit in a nibble", value)),
+        }
+    }
+
+    fn name(&mut self) -> AsmResult<Token<'a>> {
+        let token = self.next()?;
+        if token.text.starts_with(':')
+            || self.constant(token.text).is_some()
+            || self.register_named(token.text).is_some()
+        {
+            return self.error(
+                token.line,
+                format!("`{}` can't be used as a name", token.text),
+            );
+        }
+        Ok(token)
+    }
+
+    fn define(&mut self, name: Token<'a>, addr: usize) -> AsmResult<()> {
+        if name.text == "main" && addr == 0x202 && self.jump_to_main {
+            // Execution can start at main without the jump to it.
+            self.jump_to_main = false;
+            self.rom.clear();
+            self.here = 0x200;
+            return self.define(name, 0x200);
+        }
+        if self.labels.contains_key(name.text) || self.consts.contains_key(name.text) {
+            return self.error(name.line, format!("`{}` is already defined", name.text));
+        }
+        self.labels.insert(name.text, addr as u16);
+        Ok(())
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> AsmResult<()> {
+        if !(0x200..0x10000).contains(&self.here) {
+            return self.error(
+                self.line(),
+                format!("0x{:X} is outside the program", self.here),
+            );
+        }
+        let at = self.here - 0x200;
+        if self.rom.len() <= at {
+            self.rom.resize(at + 1, 0);
+        }
+        self.rom[at] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> AsmResult<()> {
+        let [hi, lo] = opcode.to_be_bytes();
+        self.emit_byte(hi)?;
+        self.emit_byte(lo)
+    }
+
+    /// Fill in the reference at `at` to `addr`.
+    fn fix(&mut self, at: usize, fixup: Fixup, addr: u16, line: usize) -> AsmResult<()> {
+        let at = at - 0x200;
+        let [hi, lo] = addr.to_be_bytes();
+        match fixup {
+            Fixup::Addr | Fixup::High(_) if addr > 0xFFF => {
+                return self.error(
+                    line,
+                    format!("0x{:X} is past the 12 bit address space", addr),
+                );
+            }
+            Fixup::Addr => {
+                self.rom[at] |= hi;
+                self.rom[at + 1] = lo;
+            }
+            Fixup::Long => self.rom[at..at + 2].copy_from_slice(&[hi, lo]),
+            Fixup::High(nibble) => self.rom[at] = nibble << 4 | hi,
+            Fixup::HighByte => self.rom[at] = hi,
+            Fixup::Low => self.rom[at] = lo,
+        }
+        Ok(())
+    }
+
+    /// Fill in the reference at `at` to the address `token` names, now or
+    /// once its label is defined.
+    fn refer(&mut self, token: Token<'a>, at: usize, fixup: Fixup) -> AsmResult<()> {
+        let addr = match (self.constant(token.text), self.labels.get(token.text)) {
+            (Some(addr @ 0..=0xFFFF), _) => addr as u16,
+            (Some(addr), _) => {
+                return self.error(token.line, format!("{} is not an address", addr));
+            }
+            (None, Some(&addr)) => addr,
+            (None, None) => {
+                self.pending.push((at, fixup, token));
+                return Ok(());
+            }
+        };
+        self.fix(at, fixup, addr, token.line)
+    }
+
+    /// Emit `opcode` with the address that follows as its low 12 bits.
+    fn emit_addr(&mut self, opcode: u16) -> AsmResult<()> {
+        let token = self.next()?;
+        let at = self.here;
+        self.emit(opcode)?;
+        self.refer(token, at, Fixup::Addr)
+    }
+
+    /// Point the jump at `at` to `target`.
+    fn patch_jump(&mut self, at: usize, target: usize, line: usize) -> AsmResult<()> {
+        self.fix(at, Fixup::Addr, target as u16, line)
+    }
+
+    fn statement(&mut self) -> AsmResult<()> {
+        let token = self.next()?;
+        match token.text {
+            ":" => {
+                let name = self.name()?;
+                self.define(name, self.here)
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                if self.labels.contains_key(name.text) {
+                    return self.error(name.line, format!("`{}` is already defined", name.text));
+                }
+                self.consts.insert(name.text, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.register()?;
+                self.aliases.insert(name.text, reg as u8);
+                Ok(())
+            }
+            ":org" => match self.value()? {
+                addr @ 0x200..=0xFFFF => {
+                    self.here = addr as usize;
+                    Ok(())
+                }
+                addr => self.error(token.line, format!("can't assemble at 0x{:X}", addr)),
+            },
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte as u8)
+            }
+            ":call" => self.emit_addr(0x2000),
+            ":unpack" => {
+                let high = match self.peek() {
+                    Some("long") => {
+                        self.pos += 1;
+                        Fixup::HighByte
+                    }
+                    _ => Fixup::High(self.nibble()? as u8),
+                };
+                let label = self.next()?;
+                let at = self.here;
+                self.emit(0x6000)?;
+                self.emit(0x6100)?;
+                self.refer(label, at + 1, high)?;
+                self.refer(label, at + 3, Fixup::Low)
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define(name, self.here + 1)
+            }
+            // Only for Octo's debugger.
+            ":breakpoint" => self.next().map(drop),
+            ":monitor" => self.next().and_then(|_| self.next()).map(drop),
+            directive if directive.starts_with(':') => {
+                self.error(token.line, format!("`{}` is not supported", directive))
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "scroll-down" => {
+                let rows = self.nibble()?;
+                self.emit(0x00C0 | rows)
+            }
+            "scroll-up" => {
+                let rows = self.nibble()?;
+                self.emit(0x00D0 | rows)
+            }
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "jump" => self.emit_addr(0x1000),
+            "jump0" => self.emit_addr(0xB000),
+            "sprite" => {
+                let (x, y, n) = (self.register()?, self.register()?, self.nibble()?);
+                self.emit(0xD000 | x << 8 | y << 4 | n)
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let range = self.peek() == Some("-");
+                match (token.text, range) {
+                    ("save", false) => self.emit(0xF055 | x << 8),
+                    ("load", false) => self.emit(0xF065 | x << 8),
+                    (text, _) => {
+                        self.pos += 1;
+                        let y = self.register()?;
+                        let op = if text == "save" { 0x2 } else { 0x3 };
+                        self.emit(0x5000 | x << 8 | y << 4 | op)
+                    }
+                }
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(0xF075 | x << 8)
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(0xF085 | x << 8)
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(0xF033 | x << 8)
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let op = match token.text {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit(op | x << 8)
+            }
+            "plane" => {
+                let plane = self.nibble()?;
+                self.emit(0xF001 | plane << 8)
+            }
+            "audio" => self.emit(0xF002),
+            "i" => self.index(),
+            "if" => self.conditional(),
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    let at = self.here;
+                    self.emit(0x1000)?;
+                    self.patch_jump(jump, self.here, token.line)?;
+                    self.blocks.push(Block::Else {
+                        jump: at,
+                        line: token.line,
+                    });
+                    Ok(())
+                }
+                _ => self.error(token.line, "`else` without `if ... begin`".into()),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch_jump(jump, self.here, token.line)
+                }
+                _ => self.error(token.line, "`end` without `if ... begin`".into()),
+            },
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    exits: Vec::new(),
+                    line: token.line,
+                });
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+                for opcode in condition.setup {
+                    self.emit(opcode)?;
+                }
+                self.emit(condition.skip_if)?;
+                let at = self.here;
+                self.emit(0x1000)?;
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    _ => None,
+                }) {
+                    Some(exits) => {
+                        exits.push(at);
+                        Ok(())
+                    }
+                    None => self.error(token.line, "`while` outside a loop".into()),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits, .. }) => {
+                    self.emit(0x1000)?;
+                    self.patch_jump(self.here - 2, start, token.line)?;
+                    for exit in exits {
+                        self.patch_jump(exit, self.here, token.line)?;
+                    }
+                    Ok(())
+                }
+                _ => self.error(token.line, "`again` without `loop`".into()),
+            },
+            text => match self.register_named(text) {
+                Some(x) => self.assignment(x.into()),
+                // A number puts a byte in the program, and a label calls it.
+                None if self.constant(text).is_some() => {
+                    self.pos -= 1;
+                    let byte = self.byte()?;
+                    self.emit_byte(byte as u8)
+                }
+                None => {
+                    let at = self.here;
+                    self.emit(0x2000)?;
+                    self.refer(token, at, Fixup::Addr)
+                }
+            },
+        }
+    }
+
+    /// `i := addr`, `i := long addr`, `i := hex vx`, `i := bighex vx` or
+    /// `i += vx`.
+    fn index(&mut self) -> AsmResult<()> {
+        let op = self.next()?;
+        match (op.text, self.peek()) {
+            ("+=", _) => {
+                let x = self.register()?;
+                self.emit(0xF01E | x << 8)
+            }
+            (":=", Some("hex")) => {
+                self.pos += 1;
+                let x = self.register()?;
+                self.emit(0xF029 | x << 8)
+            }
+            (":=", Some("bighex")) => {
+                self.pos += 1;
+                let x = self.register()?;
+                self.emit(0xF030 | x << 8)
+            }
+            (":=", Some("long")) => {
+                self.pos += 1;
+                let token = self.next()?;
+                self.emit(0xF000)?;
+                let at = self.here;
+                self.emit(0x0000)?;
+                self.refer(token, at, Fixup::Long)
+            }
+            (":=", _) => self.emit_addr(0xA000),
+            (text, _) => self.error(op.line, format!("expected `:=` or `+=`, got `{}`", text)),
+        }
+    }
+
+    /// A statement that starts with the register `x`.
+    fn assignment(&mut self, x: u16) -> AsmResult<()> {
+        let op = self.next()?;
+        let register = self.peek().and_then(|text| self.register_named(text));
+        let alu =
+            |n: u16| -> Option<u16> { register.map(|y| 0x8000 | x << 8 | u16::from(y) << 4 | n) };
+        let opcode = match (op.text, self.peek()) {
+            (":=", Some("random")) => {
+                self.pos += 1;
+                let mask = self.byte()?;
+                return self.emit(0xC000 | x << 8 | mask);
+            }
+            (":=", Some("delay")) => Some(0xF007 | x << 8),
+            (":=", Some("key")) => Some(0xF00A | x << 8),
+            (":=", _) => alu(0x0),
+            ("+=", _) => alu(0x4),
+            ("-=", _) => alu(0x5),
+            ("=-", _) => alu(0x7),
+            ("|=", _) => alu(0x1),
+            ("&=", _) => alu(0x2),
+            ("^=", _) => alu(0x3),
+            (">>=", _) => alu(0x6),
+            ("<<=", _) => alu(0xE),
+            (text, _) => {
+                return self.error(op.line, format!("expected an operator, got `{}`", text));
+            }
+        };
+        if let Some(opcode) = opcode {
+            self.pos += 1;
+            return self.emit(opcode);
+        }
+        let byte = match op.text {
+            ":=" | "+=" => self.byte()?,
+            "-=" => (self.byte()?.wrapping_neg()) & 0xFF,
+            text => {
+                return self.error(op.line, format!("`{}` needs a register", text));
+            }
+        };
+        let base = if op.text == ":=" { 0x6000 } else { 0x7000 };
+        self.emit(base | x << 8 | byte)
+    }
+
+    fn condition(&mut self) -> AsmResult<Condition> {
+        let x = self.register()?;
+        let op = self.next()?;
+        let simple = |skip_unless, skip_if| Condition {
+            setup: Vec::new(),
+            skip_unless,
+            skip_if,
+        };
+        if op.text == "key" || op.text == "-key" {
+            let (pressed, released) = (0xE09E | x << 8, 0xE0A1 | x << 8);
+            return Ok(match op.text {
+                "key" => simple(released, pressed),
+                _ => simple(pressed, released),
+            });
+        }
+        let rhs = self.next()?;
+        let y = self.register_named(rhs.text).map(u16::from);
+        let byte = match y {
+            Some(_) => 0,
+            None => {
+                self.pos -= 1;
+                self.byte()?
+            }
+        };
+        let (equal, unequal) = match y {
+            Some(y) => (0x5000 | x << 8 | y << 4, 0x9000 | x << 8 | y << 4),
+            None => (0x3000 | x << 8 | byte, 0x4000 | x << 8 | byte),
+        };
+        // The other comparisons subtract in vF, whose flag then says which
+        // side is bigger.
+        let load_vf = match y {
+            Some(y) => 0x8F00 | y << 4,
+            None => 0x6F00 | byte,
+        };
+        let (vf_zero, vf_set) = (0x3F00, 0x4F00);
+        let compare = |subtract: u16, when_zero: bool| Condition {
+            setup: vec![load_vf, subtract | x << 4],
+            skip_unless: if when_zero { vf_set } else { vf_zero },
+            skip_if: if when_zero { vf_zero } else { vf_set },
+        };
+        Ok(match op.text {
+            "==" => simple(unequal, equal),
+            "!=" => simple(equal, unequal),
+            // vF := rhs; vF -= x leaves vF 1 when rhs >= x.
+            ">" => compare(0x8F05, true),
+            "<=" => compare(0x8F05, false),
+            // vF := rhs; vF =- x leaves vF 1 when x >= rhs.
+            "<" => compare(0x8F07, true),
+            ">=" => compare(0x8F07, false),
+            text => return self.error(op.line, format!("expected a comparison, got `{}`", text)),
+        })
+    }
+
+    /// `if ... then statement` or `if ... begin`.
+    fn conditional(&mut self) -> AsmResult<()> {
+        let condition = self.condition()?;
+        for &opcode in &condition.setup {
+            self.emit(opcode)?;
+        }
+        let token = self.next()?;
+        match token.text {
+            "then" => self.emit(condition.skip_unless),
+            "begin" => {
+                self.emit(condition.skip_if)?;
+                let at = self.here;
+                self.emit(0x1000)?;
+                self.blocks.push(Block::If {
+                    jump: at,
+                    line: token.line,
+                });
+                Ok(())
+            }
+            text => self.error(
+                token.line,
+                format!("expected `then` or `begin`, got `{}`", text),
+            ),
+        }
+    }
+
+    fn finish(mut self) -> AsmResult<Vec<u8>> {
+        if let Some(block) = self.blocks.pop() {
+            return match block {
+                Block::If { line, .. } | Block::Else { line, .. } => {
+                    self.error(line, "`if ... begin` without `end`".into())
+                }
+                Block::Loop { line, .. } => self.error(line, "`loop` without `again`".into()),
+            };
+        }
+        for (at, fixup, token) in mem::take(&mut self.pending) {
+            match self.labels.get(token.text) {
+                Some(&addr) => self.fix(at, fixup, addr, token.line)?,
+                None => return self.error(token.line, format!("`{}` is not defined", token.text)),
+            }
+        }
+        if self.jump_to_main {
+            match self.labels.get("main") {
+                Some(&main) => self.fix(0x200, Fixup::Addr, main, 1)?,
+                None => return self.error(1, "the program has no `main` label".into()),
+            }
+        }
+        Ok(self.rom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::CHIP8;
+
+    fn words(rom: &[u8]) -> Vec<u16> {
+        rom.chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble() {
+        let rom = assemble(
+            "
+            : main
+                v0 := 5         # a comment
+                v1 += -1
+                i := long data
+                if v0 != 5 then clear
+                draw
+            : draw
+                sprite v0 v1 5
+                ;
+            : data 0xF0 0b1001 :byte 255
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            words(&rom[..18]),
+            [0x6005, 0x71FF, 0xF000, 0x0212, 0x3005, 0x00E0, 0x220E, 0xD015, 0x00EE]
+        );
+        assert_eq!(rom[18..], [0xF0, 0x09, 0xFF]);
+
+        // A main that isn't first is jumped to.
+        let rom = assemble(": sub ; : main sub").unwrap();
+        assert_eq!(words(&rom), [0x1204, 0x00EE, 0x2202]);
+    }
+
+    #[test]
+    fn test_blocks_and_comparisons() {
+        let rom = assemble(
+            "
+            :alias count v3
+            :const LIMIT 10
+            : main
+                loop
+                    count += 1
+                    while count < LIMIT
+                    if count key begin
+                        v2 := 1
+                    else
+                        v2 := 2
+                    end
+                again
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            words(&rom),
+            [
+                0x7301, // count += 1
+                0x6F0A, 0x8F37, 0x3F00, 0x1216, // while count < LIMIT
+                0xE39E, 0x1212, // if count key begin
+                0x6201, 0x1214, // v2 := 1, else
+                0x6202, // v2 := 2
+                0x1200, // again
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source| assemble(source).unwrap_err().to_string();
+        assert_eq!(
+            error(": main\n  jump nowhere"),
+            "line 2: `nowhere` is not defined"
+        );
+        assert_eq!(
+            error(": main v0 := 256"),
+            "line 1: 256 does not fit in a byte"
+        );
+        assert_eq!(error(": main loop"), "line 1: `loop` without `again`");
+        assert_eq!(error("v0 := 1"), "line 1: the program has no `main` label");
+        assert_eq!(error(": main :macro"), "line 1: `:macro` is not supported");
+    }
+
+    #[test]
+    fn test_run_assembled() {
+        let rom = assemble(
+            "
+            : main
+                v0 := 0
+                v1 := 0
+                loop
+                    v0 += 3
+                    v1 += 1
+                    if v0 >= 20 then jump done
+                again
+            : done
+                :unpack 0xA counter
+                i := counter
+                save v1
+            : halt
+                jump halt
+            : counter 0 0
+            ",
+        )
+        .unwrap();
+        let mut chip8 = CHIP8::headless();
+        chip8.load_bytes(&rom).unwrap();
+        chip8.run_frames(2).unwrap();
+        let state = chip8.state();
+        // v0 reaches 21 after 7 rounds, then is overwritten by :unpack.
+        assert_eq!(state.pc, 0x200 + rom.len() - 4);
+        let counter = 0x200 + rom.len() - 2;
+        assert_eq!(state.vx[0], 0xA0 | (counter >> 8) as u8);
+        assert_eq!(state.vx[1], counter as u8);
+        assert_eq!(state.ram[counter..counter + 2], [state.vx[0], state.vx[1]]);
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    Arc,
+};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BuildStreamError, Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use crate::chip8::registers::Registers;
+use crate::chip8::tone::{Pattern, Tone};
+
+const TIMER_HZ: f32 = 60.0;
+
+/// How long to wait for the audio callback's next tick before deciding the
+/// output has stalled and going on without it.
+const TICK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// What the audio callback is told to play next.
+enum Change {
+    Tone(Tone),
+    /// Play this XO-CHIP pattern instead of the tone, or the tone again on
+    /// `None`.
+    Pattern(Option<Pattern>),
+}
+
+pub struct Audio {
+    _stream: Stream,
+    changes: Sender<Change>,
+    /// A message per timer tick, when the callback ticks the timers.
+    ticks: Option<Receiver<()>>,
+}
+
+impl fmt::Debug for Audio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Audio").finish_non_exhaustive()
+    }
+}
+
+impl Audio {
+    /// Open the default output device and drive the delay and sound timers
+    /// from its callback: every `sample_rate / 60` samples both timers tick,
+    /// and `wait_for_tick` returns so the frame loop can follow. The buzzer is
+    /// generated in the same callback, so the tone starts and stops on the
+    /// exact sample the sound timer changes.
+    pub fn sync_to_audio(dt: Arc<AtomicU8>, st: Arc<AtomicU8>) -> Result<Self, String> {
+        Audio::open(Some(dt), st)
+    }
+
+    /// Open the default output device and sound the buzzer for as long as
+    /// `st` is nonzero, leaving the ticking to the CPU loop, which ticks the
+    /// timers after each 60th of a second's worth of instructions.
+    pub fn buzzer(st: Arc<AtomicU8>) -> Result<Self, String> {
+        Audio::open(None, st)
+    }
+
+    /// Sound the buzzer as `tone` from the next buffer on.
+    pub fn set_tone(&self, tone: Tone) {
+        let _ = self.changes.send(Change::Tone(tone));
+    }
+
+    /// Play `pattern` instead of the tone, at the tone's volume, or the tone
+    /// again on `None`.
+    pub fn set_pattern(&self, pattern: Option<Pattern>) {
+        let _ = self.changes.send(Change::Pattern(pattern));
+    }
+
+    /// Wait until the callback has ticked the timers since the last call,
+    /// returning at once if it already has; ticks missed meanwhile are
+    /// dropped rather than caught up in a burst. Gives how many were, or
+    /// `None` when the timers are not ticked here, as for a plain `buzzer`.
+    pub fn wait_for_tick(&self) -> Option<u64> {
+        let ticks = self.ticks.as_ref()?;
+        match ticks.try_iter().count() as u64 {
+            0 => match ticks.recv_timeout(TICK_TIMEOUT) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => Some(0),
+                Err(RecvTimeoutError::Disconnected) => None,
+            },
+            ticked => Some(ticked - 1),
+        }
+    }
+
+    fn open(dt: Option<Arc<AtomicU8>>, st: Arc<AtomicU8>) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device available")?;
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+
+        let (changes, rx) = mpsc::channel();
+        let (clock, ticks) = match dt {
+            Some(dt) => {
+                let (tx, ticks) = mpsc::channel();
+                (Some(Clock { dt, ticks: tx }), Some(ticks))
+            }
+            None => (None, None),
+        };
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), clock, st, rx),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), clock, st, rx),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), clock, st, rx),
+            format => return Err(format!("unsupported sample format {format}")),
+        }
+        .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(Audio {
+            _stream: stream,
+            changes,
+            ticks,
+        })
+    }
+}
+
+/// The delay timer the callback ticks along with the sound timer, and where
+/// it tells the frame loop it did.
+struct Clock {
+    dt: Arc<AtomicU8>,
+    ticks: Sender<()>,
+}
+
+fn build_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    clock: Option<Clock>,
+    st: Arc<AtomicU8>,
+    changes: Receiver<Change>,
+) -> Result<Stream, BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let sample_rate = config.sample_rate.0 as f32;
+    let channels = config.channels as usize;
+    let samples_per_tick = sample_rate / TIMER_HZ;
+    let mut until_tick = samples_per_tick;
+    let mut phase = 0.0;
+    let mut tone = Tone::default();
+    let mut pattern: Option<Pattern> = None;
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for change in changes.try_iter() {
+                match change {
+                    Change::Tone(next) => tone = next,
+                    Change::Pattern(next) => pattern = next,
+                }
+            }
+            for frame in data.chunks_mut(channels) {
+                if let Some(clock) = &clock {
+                    until_tick -= 1.0;
+                    if until_tick <= 0.0 {
+                        until_tick += samples_per_tick;
+                        Registers::tick(&clock.dt);
+                        Registers::tick(&st);
+                        let _ = clock.ticks.send(());
+                    }
+                }
+
+                let value = if st.load(Ordering::Relaxed) != 0 {
+                    let (value, hz) = match &pattern {
+                        Some(pattern) => {
+                            (pattern.sample(phase, tone.volume), pattern.rate() / 128.0)
+                        }
+                        None => (tone.sample(phase), tone.hz),
+                    };
+                    phase = (phase + hz / sample_rate) % 1.0;
+                    value
+                } else {
+                    phase = 0.0;
+                    0.0
+                };
+
+                let sample = T::from_sample(value);
+                for s in frame.iter_mut() {
+                    *s = sample;
+                }
+            }
+        },
+        |e| eprintln!("Audio stream error: {e}"),
+        None,
+    )
+}
@@ -0,0 +1,92 @@
+use crate::chip8::framebuffer::FrameBuffer;
+use crate::chip8::frontend::{Frontend, KeypadState};
+
+/// A frontend for an HTML canvas: the screen as RGBA bytes ready for
+/// `ImageData`, with keys and the buzzer set and read by the page.
+#[derive(Debug, Default)]
+pub struct CanvasFrontend {
+    rgba: Vec<u8>,
+    width: usize,
+    height: usize,
+    keys: KeypadState,
+    beeping: bool,
+}
+
+impl CanvasFrontend {
+    /// The screen, four bytes per pixel, `width` by `height`.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Press or release keypad key `key`.
+    pub fn set_key(&mut self, key: u8, down: bool) {
+        self.keys = if down {
+            self.keys.with(key)
+        } else {
+            self.keys.without(key)
+        };
+    }
+
+    /// Whether the buzzer should be sounding.
+    pub fn is_beeping(&self) -> bool {
+        self.beeping
+    }
+}
+
+impl Frontend for CanvasFrontend {
+    fn draw(&mut self, fb: &FrameBuffer) {
+        let (width, height) = fb.resolution();
+        self.width = width;
+        self.height = height;
+        self.rgba.clear();
+        for pixel in fb.pixels() {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            self.rgba.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+
+    fn keys(&self) -> KeypadState {
+        self.keys
+    }
+
+    fn beep(&mut self, on: bool) {
+        self.beeping = on;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::framebuffer::{HEIGHT, WIDTH};
+    use crate::chip8::registers::Registers;
+    use crate::chip8::CHIP8;
+
+    #[test]
+    fn test_canvas() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), CanvasFrontend::default());
+        chip8
+            .load_bytes(&[
+                0xE1, 0xA1, // SKNP V1
+                0xD0, 0x01, // DRW V0, V0, 1, the font's top row of 0
+                0x12, 0x00, // JP 0x200
+            ])
+            .unwrap();
+        chip8.step_frame().unwrap();
+        assert_eq!(chip8.frontend().width(), 0);
+
+        chip8.frontend_mut().set_key(0x0, true);
+        chip8.step_frame().unwrap();
+        let canvas = chip8.frontend();
+        assert_eq!((canvas.width(), canvas.height()), (WIDTH, HEIGHT));
+        assert_eq!(&canvas.rgba()[..16], &[0xFF; 16]);
+        assert_eq!(&canvas.rgba()[16..20], &[0, 0, 0, 0xFF]);
+    }
+}
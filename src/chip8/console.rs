@@ -0,0 +1,86 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::chip8::frontend::Frontend;
+use crate::chip8::opcodes::get_vy;
+use crate::chip8::CHIP8;
+
+/// Give ROMs a printf-style channel to the host by reserving two machine
+/// code calls, which real interpreters would jump into the ROM for:
+///
+/// * `0FX0` writes VX to `out` as a character, e.g. `0x0A` for a new line.
+/// * `0FX1` writes VX to `out` as two hex digits.
+///
+/// X is the third nibble of these opcodes, where others keep Y, so it is read
+/// with `get_vy`; the second nibble is always F.
+pub fn install<F: Frontend, W: Write + 'static>(chip8: &mut CHIP8<F>, out: W) {
+    let out = Rc::new(RefCell::new(out));
+    let hex = out.clone();
+    chip8.register_opcode("0FX0".parse().unwrap(), move |chip8, opcode| {
+        let value = chip8.registers().Vx[*get_vy(opcode) as usize];
+        let mut out = out.borrow_mut();
+        let _ = out.write_all(&[value]).and_then(|()| out.flush());
+    });
+    chip8.register_opcode("0FX1".parse().unwrap(), move |chip8, opcode| {
+        let value = chip8.registers().Vx[*get_vy(opcode) as usize];
+        let mut out = hex.borrow_mut();
+        let _ = write!(out, "{:02X}", value).and_then(|()| out.flush());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_console() {
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8 = CHIP8::headless();
+        install(&mut chip8, Shared(written.clone()));
+        chip8
+            .load_bytes(&[
+                0x63, 0x48, // LD V3, 'H'
+                0x0F, 0x30, // print V3 as a character
+                0x6A, 0x2F, // LD VA, 0x2F
+                0x0F, 0xA1, // print VA in hex
+            ])
+            .unwrap();
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(*written.borrow(), b"H2F");
+    }
+
+    #[test]
+    fn test_console_reads_x_not_f() {
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8 = CHIP8::headless();
+        install(&mut chip8, Shared(written.clone()));
+        chip8
+            .load_bytes(&[
+                0x6F, 0x21, // LD VF, '!'
+                0x65, 0x6F, // LD V5, 'o'
+                0x0F, 0x50, // print V5, not VF, as a character
+                0x0F, 0x51, // print V5 in hex
+            ])
+            .unwrap();
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(*written.borrow(), b"o6F");
+    }
+}
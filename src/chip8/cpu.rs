@@ -1,100 +1,1669 @@
-use crate::chip8::display::Display;
+use crate::chip8::assembler;
+#[cfg(feature = "audio")]
+use crate::chip8::audio::Audio;
+#[cfg(feature = "window")]
+use crate::chip8::debugger::{self, Address, Breakpoint, Command, MEMORY_ROWS};
+#[cfg(feature = "window")]
+use crate::chip8::display::{Display, SoundIndicator};
+use crate::chip8::download;
+use crate::chip8::error::{Chip8Error, RomTooLarge};
+use crate::chip8::extension::{Handler, OpcodePattern};
+use crate::chip8::font::{FontStyle, BIG_FONT_START, BIG_GLYPHS, BIG_GLYPH_BYTES, GLYPH_BYTES};
+use crate::chip8::framebuffer::{FrameBuffer, DEFAULT_BG, DEFAULT_FG};
+#[cfg(feature = "window")]
+use crate::chip8::frametime::FrameTimes;
+#[cfg(not(feature = "window"))]
+use crate::chip8::frontend::NullFrontend;
+use crate::chip8::frontend::{Frontend, KeypadState};
+#[cfg(feature = "gamepad")]
+use crate::chip8::gamepad::Gamepad;
+use crate::chip8::gif::GifRecorder;
+use crate::chip8::idle::Idle;
+#[cfg(feature = "window")]
+use crate::chip8::keymap::Keymap;
+#[cfg(feature = "window")]
+use crate::chip8::latency::InputLatency;
+use crate::chip8::memory::{Memory, Region, Violation};
+#[cfg(feature = "window")]
+use crate::chip8::menu::{Action, Menu, MENU_KEY};
+use crate::chip8::movie::{Input, Movie};
+use crate::chip8::octo::{self, Metadata};
 use crate::chip8::opcodes::*;
+#[cfg(feature = "window")]
+use crate::chip8::overlay;
+use crate::chip8::profile::Profile;
+use crate::chip8::quirks::Quirks;
 use crate::chip8::registers::Registers;
+#[cfg(feature = "window")]
+use crate::chip8::remap::{Remap, REMAP_KEY};
+use crate::chip8::screenshot;
+#[cfg(feature = "scripting")]
+use crate::chip8::script::{Callback, Script};
+#[cfg(feature = "window")]
+use crate::chip8::shm::SharedFramebuffer;
+use crate::chip8::state::{Chip8State, Hook};
+use crate::chip8::stats::Stats;
+#[cfg(feature = "audio")]
+use crate::chip8::tone::Tone;
+use crate::chip8::tone::{Pattern, DEFAULT_PITCH};
+use crate::chip8::tracelog::Tracer;
 use either::Either;
-use rand::random;
-use std::io;
-use std::{fs::File, io::Read};
-
-const SPRITE_BYTE_LENGTH: usize = 5;
-const SPRITES: [u8; SPRITE_BYTE_LENGTH * 16] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xf0, 0x80, 0xF0, 0xF0,
-    0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
-    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
-    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, 0xF0, 0x80, 0x80, 0x80,
-    0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+#[cfg(feature = "window")]
+use minifb::Key;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+use std::io::{self, LineWriter, Write as _};
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+#[cfg(feature = "gamepad")]
+use std::sync::atomic::AtomicU8;
+#[cfg(feature = "window")]
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+#[cfg(feature = "window")]
+use std::thread;
+#[cfg(feature = "window")]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fs, fs::File, io::Read};
+
+/// Opens the ROM a filename names for the pause menu's Open ROM, with
+/// whatever else loading it involves, giving whether it did.
+#[cfg(feature = "window")]
+type RomOpener = Box<dyn FnMut(&mut CHIP8, &str) -> bool>;
+
+/// How many nested CALLs are allowed before the stack overflows. The COSMAC
+/// VIP interpreter had room for 12, most later ones for 16.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StackLimit {
+    Depth(usize),
+    Unlimited,
+}
+
+impl Default for StackLimit {
+    fn default() -> Self {
+        StackLimit::Depth(16)
+    }
+}
+
+impl fmt::Display for StackLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackLimit::Depth(depth) => write!(f, "{}", depth),
+            StackLimit::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+impl FromStr for StackLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unlimited" => Ok(StackLimit::Unlimited),
+            _ => s
+                .parse()
+                .map(StackLimit::Depth)
+                .map_err(|_| format!("expected a depth or `unlimited`, got `{}`", s)),
+        }
+    }
+}
+
+/// What happens when PC runs off the end of RAM, leaving no whole opcode to
+/// fetch.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PcOverflow {
+    /// The program ends, as if it had exited.
+    #[default]
+    Stop,
+    /// The program fails with an error.
+    Error,
+    /// PC carries on from the start of RAM.
+    Wrap,
+}
+
+impl fmt::Display for PcOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PcOverflow::Stop => write!(f, "stop"),
+            PcOverflow::Error => write!(f, "error"),
+            PcOverflow::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
+impl FromStr for PcOverflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stop" => Ok(PcOverflow::Stop),
+            "error" => Ok(PcOverflow::Error),
+            "wrap" => Ok(PcOverflow::Wrap),
+            _ => Err(format!("expected `stop`, `error` or `wrap`, got `{}`", s)),
+        }
+    }
+}
+
+/// What happens on an opcode that neither the interpreter nor an extension
+/// knows, such as data PC has strayed into.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OnUnknown {
+    /// The program fails with an error.
+    #[default]
+    Halt,
+    /// The opcode is passed over as if it did nothing.
+    Skip,
+    /// The opcode is passed over, and reported the first time it is met at
+    /// each address.
+    Warn,
+}
+
+impl fmt::Display for OnUnknown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnUnknown::Halt => write!(f, "halt"),
+            OnUnknown::Skip => write!(f, "skip"),
+            OnUnknown::Warn => write!(f, "warn"),
+        }
+    }
+}
+
+impl FromStr for OnUnknown {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halt" => Ok(OnUnknown::Halt),
+            "skip" => Ok(OnUnknown::Skip),
+            "warn" => Ok(OnUnknown::Warn),
+            _ => Err(format!("expected `halt`, `skip` or `warn`, got `{}`", s)),
+        }
+    }
+}
+
+/// What happens once the program is stuck in a jump to itself, as many test
+/// ROMs end.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OnHalt {
+    /// The program spins there, as on the original machines.
+    #[default]
+    Spin,
+    /// The run ends.
+    Exit,
+    /// The run ends and the screen is printed as text.
+    Print,
+    /// The window shows that the program halted over the screen, until it
+    /// is reset.
+    Overlay,
+}
+
+impl fmt::Display for OnHalt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnHalt::Spin => write!(f, "spin"),
+            OnHalt::Exit => write!(f, "exit"),
+            OnHalt::Print => write!(f, "print"),
+            OnHalt::Overlay => write!(f, "overlay"),
+        }
+    }
+}
+
+impl FromStr for OnHalt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spin" => Ok(OnHalt::Spin),
+            "exit" => Ok(OnHalt::Exit),
+            "print" => Ok(OnHalt::Print),
+            "overlay" => Ok(OnHalt::Overlay),
+            _ => Err(format!(
+                "expected `spin`, `exit`, `print` or `overlay`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// Instructions per frame at the default speed: 600 instructions a second
+/// against the timers' 60Hz.
+pub const STEPS_PER_FRAME: usize = 10;
+
+/// RAM in bytes, the 4K of the original machines.
+pub const DEFAULT_RAM_SIZE: usize = 0x1000;
+
+/// The most RAM that can be had, the 64K of XO-CHIP.
+pub const MAX_RAM_SIZE: usize = 0x10000;
+
+/// The least RAM that can be had: the interpreter's 512 bytes, which hold
+/// the fonts, and room for one opcode after them.
+pub const MIN_RAM_SIZE: usize = 0x202;
+
+/// The default speed, in instructions a second.
+pub const DEFAULT_CPU_HZ: u32 = STEPS_PER_FRAME as u32 * 60;
+
+/// How long a frame lasts in the window, a 60th of a second.
+#[cfg(feature = "window")]
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// How far behind its schedule the window's frame loop may fall before
+/// giving up on catching up.
+#[cfg(feature = "window")]
+const MAX_LAG: Duration = Duration::from_millis(50);
+
+/// Pauses and resumes the program, unless the keymap gives it to the keypad.
+#[cfg(feature = "window")]
+const PAUSE_KEY: Key = Key::P;
+
+/// Make the window bigger and smaller, unless the keymap gives them to the
+/// keypad.
+#[cfg(feature = "window")]
+const SCALE_KEYS: [(Key, isize); 4] = [
+    (Key::Equal, 1),
+    (Key::NumPadPlus, 1),
+    (Key::Minus, -1),
+    (Key::NumPadMinus, -1),
 ];
 
-pub struct CHIP8 {
-    stack: Vec<u16>,
-    ram: [u8; 0xFFF],
-    reg: Registers,
-    display: Display,
-}
+/// What decides the next step, apart from the timers and keys: PC, the V
+/// registers, I, the stack depth and top, and the delay timer.
+type MachineState = (usize, [u8; 16], u16, usize, Option<u16>, u8);
+
+pub struct CHIP8<
+    #[cfg(feature = "window")] F: Frontend = Display,
+    #[cfg(not(feature = "window"))] F: Frontend,
+> {
+    stack: Vec<u16>,
+    ram: Memory,
+    reg: Registers,
+    fb: FrameBuffer,
+    frontend: F,
+    rom: Vec<u8>,
+    #[cfg(feature = "window")]
+    keys_held: Vec<Key>,
+    #[cfg(feature = "window")]
+    menu: Option<Menu>,
+    #[cfg(feature = "window")]
+    remap: Option<Remap>,
+    #[cfg(feature = "window")]
+    debugging: bool,
+    #[cfg(feature = "window")]
+    paused: bool,
+    /// Whether to pause while the window is in the background, and whether
+    /// it is.
+    #[cfg(feature = "window")]
+    pause_unfocused: bool,
+    #[cfg(feature = "window")]
+    unfocused: bool,
+    /// Whether the window shows that the program halted.
+    #[cfg(feature = "window")]
+    halt_shown: bool,
+    /// Where the debugger's `memory` carries on from.
+    #[cfg(feature = "window")]
+    memory_view: Option<usize>,
+    #[cfg(feature = "window")]
+    breakpoints: Vec<Breakpoint>,
+    /// The PC the debugger was left at, whose breakpoints are passed over
+    /// once so the run can carry on.
+    #[cfg(feature = "window")]
+    resumed_at: Option<usize>,
+    #[cfg(feature = "window")]
+    saved_state: Option<Chip8State>,
+    #[cfg(feature = "window")]
+    state_path: Option<PathBuf>,
+    #[cfg(feature = "window")]
+    keymap_path: Option<PathBuf>,
+    #[cfg(feature = "window")]
+    rom_opener: Option<RomOpener>,
+    /// The filename typed at the terminal for Open ROM, once it has been.
+    #[cfg(feature = "window")]
+    rom_prompt: Option<Receiver<String>>,
+    /// SCHIP's RPL user flags, which `LD R, Vx` saves to and `LD Vx, R`
+    /// restores from. They survive a reset.
+    flags: [u8; 16],
+    flags_path: Option<PathBuf>,
+    /// The XO-CHIP audio pattern buffer, once the program loads it.
+    pattern: Option<[u8; 16]>,
+    pitch: u8,
+    stack_limit: StackLimit,
+    pc_overflow: PcOverflow,
+    on_unknown: OnUnknown,
+    on_halt: OnHalt,
+    /// Where `OnUnknown::Warn` has reported an unknown opcode.
+    unknown_seen: HashSet<usize>,
+    quirks: Quirks,
+    /// The key `LD Vx, K` saw pressed and is waiting to be released.
+    key_pressed: Option<u8>,
+    cpu_hz: u32,
+    ticks: u64,
+    frame_steps: u64,
+    #[cfg(feature = "window")]
+    next_frame: Option<Instant>,
+    font: FontStyle,
+    max_stack_depth: usize,
+    idle: Idle<MachineState>,
+    extensions: Vec<(OpcodePattern, Handler<F>)>,
+    pre_hook: Option<Hook>,
+    post_hook: Option<Hook>,
+    stats: Arc<Stats>,
+    input: Input,
+    /// Controllers read alongside the frontend's keys.
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<Gamepad>,
+    /// What RND draws from, started over from `seed` on reset.
+    rng: StdRng,
+    seed: u64,
+    gif: Option<GifRecorder>,
+    tracer: Option<Tracer>,
+    profile: Option<Profile>,
+    /// Where every frame's `screen_hash` goes, one per line.
+    hash_log: Option<LineWriter<File>>,
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+    #[cfg(feature = "audio")]
+    audio: Option<Audio>,
+}
+
+#[cfg(not(feature = "window"))]
+impl CHIP8<NullFrontend> {
+    /// A CHIP8 that shows nothing and sees no keys, there being no window in
+    /// builds without the `window` feature. Its timers tick after each 60th
+    /// of a second's worth of instructions, so runs are repeatable.
+    pub fn headless() -> Self {
+        CHIP8::with_frontend(Registers::new(), NullFrontend)
+    }
+
+    /// Run for `frames` frames, or until the program halts.
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), Chip8Error> {
+        for _ in 0..frames {
+            if self.is_done() {
+                break;
+            }
+            self.step_frame()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "window")]
+impl CHIP8 {
+    /// A CHIP8 with a window, and a buzzer on the audio output in builds with
+    /// the `audio` feature. Not `Default`, as opening a window should be
+    /// asked for; see `headless` for a machine without one.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let reg = Registers::new();
+        #[cfg(feature = "audio")]
+        let audio = Audio::buzzer(reg.timer_handles().1)
+            .map_err(|e| eprintln!("Could not open audio output, the buzzer is silent: {e}"))
+            .ok();
+        CHIP8 {
+            #[cfg(feature = "audio")]
+            audio,
+            ..CHIP8::with_registers(reg)
+        }
+    }
+
+    /// A CHIP8 whose frames, and with them its delay and sound timers, are
+    /// paced by the audio output callback instead of the system clock.
+    #[cfg(feature = "audio")]
+    pub fn synced_to_audio() -> Result<Self, String> {
+        let mut chip8 = CHIP8::with_registers(Registers::with_external_clock());
+        let (dt, st) = chip8.reg.timer_handles();
+        chip8.audio = Some(Audio::sync_to_audio(dt, st)?);
+        Ok(chip8)
+    }
+
+    /// A CHIP8 without a window. Like every CHIP8 not synced to audio, its
+    /// timers tick after each 60th of a second's worth of instructions, so
+    /// runs are repeatable.
+    pub fn headless() -> Self {
+        CHIP8::with_frontend(Registers::new(), Display::headless())
+    }
+
+    fn with_registers(reg: Registers) -> Self {
+        let stats = Arc::new(Stats::default());
+        let display = Display::init(stats.clone());
+        CHIP8 {
+            stats,
+            ..CHIP8::with_frontend(reg, display)
+        }
+    }
+
+    /// Run until the window is closed, the program halts or an instruction
+    /// fails.
+    pub fn run(&mut self) -> Result<(), Chip8Error> {
+        while self.is_running() {
+            self.run_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Run for `frames` frames, or until the window is closed.
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), Chip8Error> {
+        for _ in 0..frames {
+            if !self.is_running() {
+                break;
+            }
+            self.run_frame()?;
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.frontend.is_window_open() && !self.is_done()
+    }
+
+    /// Run one frame: handle the keys pressed since the last, run a 60th of
+    /// a second's worth of instructions at `cpu_hz`, tick the timers once and
+    /// present the screen, then wait until the next frame is due. Without a
+    /// window, the frame runs at once.
+    fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        if self.frontend.is_headless() {
+            return self.step_frame();
+        }
+
+        self.poll_rom_prompt();
+        let debugged = self.debugging;
+        let running = self.handle_keys();
+        if running {
+            self.step_until_tick()?;
+        }
+        self.frontend.beep(self.reg.get_st() > 0 && running);
+        self.frontend.present();
+        let dropped = self.wait_for_frame();
+        // Time spent at the debugger's prompt is not the loop falling behind.
+        if !debugged && !self.debugging {
+            self.stats.count_dropped_frames(dropped);
+        }
+        Ok(())
+    }
+
+    /// Wait for the next frame: when synced to audio, until the audio
+    /// callback next ticks the timers, so every tick gets a frame's worth of
+    /// instructions however the sound card's clock drifts; otherwise `pace`.
+    /// Gives how many frames were dropped for falling behind.
+    fn wait_for_frame(&mut self) -> u64 {
+        #[cfg(feature = "audio")]
+        if let Some(missed) = self.audio.as_ref().and_then(Audio::wait_for_tick) {
+            return missed;
+        }
+        self.pace()
+    }
+
+    /// Act on the hotkeys pressed since the last frame, and give whether the
+    /// program runs this frame: not while the menu, remapping or the debugger
+    /// has it, or while it is paused, by hand or for being in the background.
+    fn handle_keys(&mut self) -> bool {
+        let keys = self.frontend.poll_keys();
+        let pressed: Vec<Key> = keys
+            .iter()
+            .filter(|key| !self.keys_held.contains(key))
+            .copied()
+            .collect();
+        self.keys_held = keys;
+
+        let unfocused = self.pause_unfocused && !self.frontend.is_focused();
+        if unfocused != self.unfocused {
+            self.unfocused = unfocused;
+            self.show_status();
+        }
+        if unfocused {
+            return false;
+        }
+
+        if self.menu.is_some() || pressed.contains(&MENU_KEY) {
+            // The menu and remapping take over the overlay while open.
+            self.halt_shown = false;
+            self.drive_menu(&pressed);
+            return false;
+        }
+        if self.remap.is_some() || pressed.contains(&REMAP_KEY) {
+            self.halt_shown = false;
+            self.drive_remap(&pressed);
+            return false;
+        }
+        if pressed.contains(&Key::Backspace) {
+            self.reset();
+            self.toast("Reset");
+        }
+        if pressed.contains(&PAUSE_KEY) && self.frontend.keymap().keypad_key(PAUSE_KEY).is_none() {
+            self.paused = !self.paused;
+            self.toast(if self.paused { "Paused" } else { "Resumed" });
+            self.show_status();
+        }
+        let zoom: isize = SCALE_KEYS
+            .iter()
+            .filter(|(key, _)| {
+                pressed.contains(key) && self.frontend.keymap().keypad_key(*key).is_none()
+            })
+            .map(|(_, step)| step)
+            .sum();
+        if zoom != 0 {
+            let scale = self.frontend.scale().saturating_add_signed(zoom);
+            self.frontend.set_scale(scale);
+            self.toast(&format!("Scale {}x", self.frontend.scale()));
+        }
+        if pressed.contains(&Key::F5) {
+            self.quick_save();
+        }
+        if pressed.contains(&Key::F7) {
+            self.quick_load();
+        }
+        if pressed.contains(&Key::F10) {
+            self.toggle_gif();
+        }
+        if pressed.contains(&Key::F12) {
+            self.quick_screenshot();
+        }
+        if pressed.contains(&Key::F9) && !self.debugging {
+            self.set_debugging(true);
+        }
+        if self.debugging {
+            self.debug_prompt();
+            return false;
+        }
+        if self.on_halt == OnHalt::Overlay {
+            let stuck = self.is_stuck();
+            if stuck != self.halt_shown {
+                self.halt_shown = stuck;
+                self.show_halted();
+            }
+            if stuck {
+                return false;
+            }
+        }
+        !self.paused
+    }
+
+    /// Show that the program halted over the screen it ended on, or stop.
+    fn show_halted(&self) {
+        let overlay = self.halt_shown.then(|| {
+            let mut frame: Vec<u32> = self.fb.pixels().collect();
+            let (fg, bg) = self.fb.colors();
+            overlay::draw_toast(&mut frame, "PROGRAM HALTED", fg, bg);
+            frame
+        });
+        self.frontend.set_overlay(overlay);
+    }
+
+    /// Run instructions until the timers tick, ending the frame. A
+    /// breakpoint hands over to the debugger part way. A program spinning in
+    /// place can't change before the tick, so the rest of its frame is
+    /// skipped rather than spun through.
+    fn step_until_tick(&mut self) -> Result<(), Chip8Error> {
+        while !self.has_halted() {
+            if self.at_breakpoint() {
+                self.set_debugging(true);
+                return Ok(());
+            }
+            if self.step_timed()? {
+                return Ok(());
+            }
+            if self.idle.is_spinning() {
+                self.idle.reset();
+                self.frame_steps = 0;
+                self.end_frame();
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sleep until the next frame is due, 60 a second. After a pause, such
+    /// as the debugger or a slow frame, the schedule starts over rather than
+    /// catching up in a burst, and the frames it was behind by are dropped:
+    /// gives how many.
+    fn pace(&mut self) -> u64 {
+        let now = Instant::now();
+        let (due, dropped) = match self.next_frame {
+            Some(due) if due + MAX_LAG > now => (due, 0),
+            Some(due) => (now, ((now - due).as_nanos() / FRAME.as_nanos()) as u64),
+            None => (now, 0),
+        };
+        if due > now {
+            thread::sleep(due - now);
+        }
+        self.next_frame = Some(due + FRAME);
+        dropped
+    }
+
+    /// Open the pause menu, or hand it the keys pressed while it is open.
+    /// The program and its timers stay paused until the menu closes.
+    fn drive_menu(&mut self, pressed: &[Key]) {
+        let mut menu = match self.menu.take() {
+            Some(menu) => menu,
+            None => {
+                let menu = Menu::new(self.font);
+                self.show_menu(&menu);
+                self.menu = Some(menu);
+                return;
+            }
+        };
+
+        for &key in pressed {
+            match menu.press(key) {
+                None => {}
+                Some(Action::SetFontStyle(font)) => {
+                    self.set_font_style(font);
+                    self.toast(&format!("Font {font}"));
+                }
+                Some(action) => {
+                    self.frontend.set_overlay(None);
+                    match action {
+                        Action::Reset => {
+                            self.reset();
+                            self.toast("Reset");
+                        }
+                        Action::OpenRom => self.prompt_for_rom(),
+                        Action::Quit => self.frontend.close(),
+                        _ => {}
+                    }
+                    return;
+                }
+            }
+        }
+        self.show_menu(&menu);
+        self.menu = Some(menu);
+    }
+
+    /// Start asking for a key for each keypad key, or hand the keys pressed
+    /// to the asking. The program stays paused until every keypad key has
+    /// one, or F2 gives up.
+    fn drive_remap(&mut self, pressed: &[Key]) {
+        let mut remap = match self.remap.take() {
+            Some(remap) => remap,
+            None => {
+                let remap = Remap::new();
+                self.show_remap(&remap);
+                self.remap = Some(remap);
+                return;
+            }
+        };
+
+        for &key in pressed {
+            if key == REMAP_KEY {
+                self.frontend.set_overlay(None);
+                self.toast("Keys unchanged");
+                return;
+            }
+            if let Some(keymap) = remap.press(key) {
+                self.frontend.set_overlay(None);
+                self.set_keymap(keymap);
+                self.save_keymap(keymap);
+                return;
+            }
+        }
+        self.show_remap(&remap);
+        self.remap = Some(remap);
+    }
+
+    fn show_remap(&self, remap: &Remap) {
+        let (fg, bg) = self.fb.colors();
+        self.frontend.set_overlay(Some(remap.render(fg, bg)));
+    }
+
+    /// Keep a keymap made with F2 in the ROM's settings, if it has any.
+    fn save_keymap(&self, keymap: Keymap) {
+        if let Some(path) = &self.keymap_path {
+            match keymap.save_setting(path) {
+                Ok(()) => return self.toast("Keys saved"),
+                Err(e) => eprintln!("Could not write keymap to `{}`: {e}", path.display()),
+            }
+        }
+        self.toast("Keys remapped");
+    }
+
+    fn show_menu(&self, menu: &Menu) {
+        let (fg, bg) = self.fb.colors();
+        self.frontend.set_overlay(Some(menu.render(fg, bg)));
+    }
+
+    /// Flash `text` over the bottom of the screen, in the screen's colors.
+    fn toast(&self, text: &str) {
+        let (fg, bg) = self.fb.colors();
+        self.frontend.toast(text, fg, bg);
+    }
+
+    /// Pause the program and take commands from the terminal
+    /// instead, one instruction at a time if need be. The debugger is left
+    /// with `continue` and entered again with F9.
+    pub fn set_debugging(&mut self, debugging: bool) {
+        self.debugging = debugging;
+        self.resumed_at = (!debugging).then_some(self.reg.PC);
+        if debugging {
+            println!("Paused, type `help` for commands");
+            println!("{}", self.describe_next());
+        }
+    }
+
+    /// Read one debugger command from the terminal and carry it out.
+    fn debug_prompt(&mut self) {
+        print!("(chip8) ");
+        let mut line = String::new();
+        if io::stdout().flush().is_err() || io::stdin().read_line(&mut line).is_err() {
+            return;
+        }
+        if line.is_empty() {
+            // The terminal is closed, so nobody is left to step.
+            self.set_debugging(false);
+            return;
+        }
+
+        match line.parse() {
+            Ok(Command::Step(steps)) => {
+                for step in 0..steps {
+                    if self.has_halted() {
+                        break;
+                    }
+                    if step > 0 && self.at_breakpoint() {
+                        println!("Stopped at a breakpoint");
+                        break;
+                    }
+                    if let Err(e) = self.step_timed() {
+                        println!("{e}");
+                        break;
+                    }
+                }
+                println!("{}", self.describe_next());
+            }
+            Ok(Command::Continue) => self.set_debugging(false),
+            Ok(Command::Break(breakpoint)) => {
+                self.breakpoints.push(breakpoint);
+                println!("Breakpoint {} {}", self.breakpoints.len(), breakpoint);
+            }
+            Ok(Command::Breakpoints) => {
+                if self.breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+                for (n, breakpoint) in self.breakpoints.iter().enumerate() {
+                    println!("{:>3}  {}", n + 1, breakpoint);
+                }
+            }
+            Ok(Command::Delete(None)) => self.breakpoints.clear(),
+            Ok(Command::Delete(Some(n))) => {
+                if n >= 1 && n <= self.breakpoints.len() {
+                    self.breakpoints.remove(n - 1);
+                } else {
+                    println!("No breakpoint {n}");
+                }
+            }
+            Ok(Command::Registers) => println!(
+                "PC = 0x{:03X}, I = 0x{:03X}, DT = {}, ST = {}
+V = {:02X?}",
+                self.reg.PC,
+                self.reg.I,
+                self.reg.get_dt(),
+                self.reg.get_st(),
+                self.reg.Vx
+            ),
+            Ok(Command::Stack) => print!("{}", self.stack_trace()),
+            Ok(Command::List) => print!("{}", self.disassemble_around(self.reg.PC)),
+            Ok(Command::Memory(at)) => self.show_memory(at),
+            Ok(Command::Quit) => self.frontend.close(),
+            Ok(Command::Help) => println!("{}", debugger::HELP),
+            Err(e) => println!("{e}"),
+        }
+    }
+
+    /// Whether a breakpoint stops the instruction at PC, unless the run just
+    /// carried on from it.
+    fn at_breakpoint(&mut self) -> bool {
+        if self.resumed_at.take() == Some(self.reg.PC) || self.breakpoints.is_empty() {
+            return false;
+        }
+        let (pc, opcode) = match self.current_opcode() {
+            Some(opcode) => (self.reg.PC, opcode),
+            None => return false,
+        };
+        self.breakpoints
+            .iter()
+            .any(|breakpoint| breakpoint.hits(pc, opcode))
+    }
+
+    /// Show a page of RAM from `at`, or the page after the last one shown,
+    /// starting at I the first time.
+    fn show_memory(&mut self, at: Option<Address>) {
+        let start = match at {
+            Some(Address::At(addr)) => addr,
+            Some(Address::Pc) => self.reg.PC,
+            Some(Address::I) => self.reg.I as usize,
+            None => self.memory_view.unwrap_or(self.reg.I as usize),
+        };
+        if start >= self.ram.len() {
+            println!("0x{:X} is past the end of RAM", start);
+            return;
+        }
+        let (pc, i) = (self.reg.PC, self.reg.I as usize);
+        print!("{}", debugger::hexdump(&self.ram.to_vec(), start, pc, i));
+        let next = start - start % 16 + 16 * MEMORY_ROWS;
+        self.memory_view = Some(if next < self.ram.len() { next } else { 0 });
+    }
+
+    /// The instruction at PC, as the debugger shows it.
+    fn describe_next(&self) -> String {
+        match self.current_opcode() {
+            Some(opcode) => {
+                let text = decode(opcode).map_or("???".to_string(), |i| i.to_string());
+                format!("0x{:03X}  {:04X}  {}", self.reg.PC, opcode, text)
+            }
+            None => format!("0x{:03X}  past the end of RAM", self.reg.PC),
+        }
+    }
+
+    /// The settings file F2 writes a new keymap to.
+    pub fn set_keymap_path(&mut self, path: PathBuf) {
+        self.keymap_path = Some(path);
+    }
+
+    /// Open ROMs chosen from the pause menu with `open`, rather than just
+    /// loading the file.
+    pub fn set_rom_opener(&mut self, open: impl FnMut(&mut CHIP8, &str) -> bool + 'static) {
+        self.rom_opener = Some(Box::new(open));
+    }
+
+    /// Where F5 also writes the machine state, and F7 reads it from when
+    /// nothing was saved since the emulator started.
+    pub fn set_state_path(&mut self, path: PathBuf) {
+        self.state_path = Some(path);
+    }
+
+    fn quick_save(&mut self) {
+        let state = self.state();
+        if let Some(path) = &self.state_path {
+            if let Err(e) = state.write(path) {
+                eprintln!("Could not write state to `{}`: {e}", path.display());
+            }
+        }
+        self.saved_state = Some(state);
+        self.toast("State saved");
+    }
+
+    /// Start recording a GIF named after the time, or save the one being
+    /// recorded.
+    fn toggle_gif(&mut self) {
+        if self.is_recording_gif() {
+            match self.stop_gif() {
+                Ok(()) => self.toast("GIF saved"),
+                Err(e) => eprintln!("Could not save GIF: {e}"),
+            }
+            return;
+        }
+        let path = capture_path("gif");
+        match self.start_gif(&path) {
+            Ok(()) => self.toast("Recording GIF"),
+            Err(e) => eprintln!("Could not create `{}`: {e}", path.display()),
+        }
+    }
+
+    /// Save the screen as a PNG named after the time.
+    fn quick_screenshot(&self) {
+        let path = capture_path("png");
+        match self.screenshot(&path) {
+            Ok(()) => self.toast("Screenshot saved"),
+            Err(e) => eprintln!("Could not save screenshot `{}`: {e}", path.display()),
+        }
+    }
+
+    fn quick_load(&mut self) {
+        if self.saved_state.is_none() {
+            if let Some(path) = self.state_path.as_deref().filter(|path| path.exists()) {
+                match Chip8State::read(path, self.ram.len()) {
+                    Ok(state) => self.saved_state = Some(state),
+                    Err(e) => eprintln!("Could not read state from `{}`: {e}", path.display()),
+                }
+            }
+        }
+        match self.saved_state.take() {
+            Some(state) => {
+                self.load_state(&state);
+                self.saved_state = Some(state);
+                self.toast("State loaded");
+            }
+            None => self.toast("No saved state"),
+        }
+    }
+
+    /// Ask on the terminal for a ROM to play instead of the current one.
+    /// The answer is read in the background, so the window carries on until
+    /// `poll_rom_prompt` gets it.
+    fn prompt_for_rom(&mut self) {
+        if self.rom_prompt.is_none() {
+            print!("ROM to open: ");
+            let _ = io::stdout().flush();
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                let mut line = String::new();
+                if io::stdin().read_line(&mut line).is_ok() {
+                    let _ = tx.send(line);
+                }
+            });
+            self.rom_prompt = Some(rx);
+        }
+        self.toast("Type the ROM to open in the terminal");
+    }
+
+    /// Open the ROM typed at the terminal, once it has been.
+    fn poll_rom_prompt(&mut self) {
+        let line = match self.rom_prompt.as_ref().map(Receiver::try_recv) {
+            None | Some(Err(TryRecvError::Empty)) => return,
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.rom_prompt = None;
+                return;
+            }
+            Some(Ok(line)) => line,
+        };
+        self.rom_prompt = None;
+
+        let filename = line.trim();
+        if filename.is_empty() {
+            return;
+        }
+        let name = Path::new(filename)
+            .file_name()
+            .map_or(filename.into(), |name| name.to_string_lossy());
+        let opened = match self.rom_opener.take() {
+            Some(mut open) => {
+                let opened = open(self, filename);
+                self.rom_opener = Some(open);
+                opened
+            }
+            None => match self.load(filename) {
+                Ok(()) => {
+                    self.frontend.set_title(&name);
+                    true
+                }
+                Err(e) => {
+                    eprintln!("Could not open file `{filename}`: {e}");
+                    false
+                }
+            },
+        };
+        if opened {
+            self.reset();
+            self.toast(&format!("Loaded {name}"));
+        }
+    }
+
+    /// Pause the program and its timers while the window is in the
+    /// background, so games don't run on unwatched, and resume when it comes
+    /// back.
+    pub fn set_pause_unfocused(&mut self, pause: bool) {
+        self.pause_unfocused = pause;
+    }
+
+    /// Draw each pixel of the 64x32 screen `scale` screen pixels wide,
+    /// reopening the window at its new size.
+    pub fn set_scale(&mut self, scale: usize) {
+        self.frontend.set_scale(scale);
+    }
+
+    /// Show the frames and instructions a second in the corner of the
+    /// window, e.g. to tune `cpu_hz`, or stop.
+    pub fn show_speed(&self, show: bool) {
+        self.frontend.show_speed(show);
+    }
+
+    /// Show in the window when the buzzer sounds, for playing muted, or stop
+    /// on `None`.
+    pub fn show_sound(&self, indicator: Option<SoundIndicator>) {
+        self.frontend.show_sound(indicator);
+    }
+
+    /// Let pixels that go out fade over `frames` frames in the window, as on
+    /// a CRT, to hide the flicker of sprites redrawn every frame. 0, the
+    /// default, turns them off at once.
+    pub fn set_fade(&self, frames: u8) {
+        self.frontend.set_fade(frames);
+    }
+
+    /// Read the keypad from the keys in `keymap` instead of 1234/QWER/ASDF/ZXCV.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.frontend.set_keymap(keymap);
+    }
+
+    /// Key press to program latency measured so far.
+    pub fn input_latency(&self) -> Arc<InputLatency> {
+        self.frontend.input_latency()
+    }
+
+    /// Interval and presentation time of the frames presented so far.
+    pub fn frame_times(&self) -> Arc<FrameTimes> {
+        self.frontend.frame_times()
+    }
+
+    /// Export the display as the shared-memory region `name`, returning the
+    /// path capture tools should map.
+    pub fn export_framebuffer(&mut self, name: &str) -> io::Result<PathBuf> {
+        let export = SharedFramebuffer::create(name)?;
+        let path = export.path().to_path_buf();
+        self.frontend.set_export(export);
+        Ok(path)
+    }
+}
+
+impl<F: Frontend> CHIP8<F> {
+    /// A CHIP8 drawing to, and reading keys from, `frontend`. With
+    /// `Registers::with_external_clock` the timers only move when ticked from
+    /// elsewhere, such as `tick_timers`.
+    pub fn with_frontend(reg: Registers, frontend: F) -> Self {
+        let seed = rand::random();
+        CHIP8 {
+            stack: Vec::with_capacity(16),
+            ram: Self::blank_ram(FontStyle::default()),
+            reg,
+            fb: FrameBuffer::default(),
+            frontend,
+            rom: Vec::new(),
+            #[cfg(feature = "window")]
+            keys_held: Vec::new(),
+            #[cfg(feature = "window")]
+            menu: None,
+            #[cfg(feature = "window")]
+            remap: None,
+            #[cfg(feature = "window")]
+            debugging: false,
+            #[cfg(feature = "window")]
+            paused: false,
+            #[cfg(feature = "window")]
+            pause_unfocused: false,
+            #[cfg(feature = "window")]
+            unfocused: false,
+            #[cfg(feature = "window")]
+            halt_shown: false,
+            #[cfg(feature = "window")]
+            memory_view: None,
+            #[cfg(feature = "window")]
+            breakpoints: Vec::new(),
+            #[cfg(feature = "window")]
+            resumed_at: None,
+            #[cfg(feature = "window")]
+            saved_state: None,
+            #[cfg(feature = "window")]
+            state_path: None,
+            #[cfg(feature = "window")]
+            keymap_path: None,
+            #[cfg(feature = "window")]
+            rom_opener: None,
+            #[cfg(feature = "window")]
+            rom_prompt: None,
+            flags: [0; 16],
+            flags_path: None,
+            pattern: None,
+            pitch: DEFAULT_PITCH,
+            stack_limit: StackLimit::default(),
+            pc_overflow: PcOverflow::default(),
+            on_unknown: OnUnknown::default(),
+            on_halt: OnHalt::default(),
+            unknown_seen: HashSet::new(),
+            quirks: Quirks::default(),
+            key_pressed: None,
+            cpu_hz: DEFAULT_CPU_HZ,
+            ticks: 0,
+            frame_steps: 0,
+            #[cfg(feature = "window")]
+            next_frame: None,
+            font: FontStyle::default(),
+            max_stack_depth: 0,
+            idle: Idle::new(),
+            extensions: Vec::new(),
+            pre_hook: None,
+            post_hook: None,
+            stats: Arc::new(Stats::default()),
+            input: Input::Live,
+            #[cfg(feature = "gamepad")]
+            gamepad: None,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            gif: None,
+            tracer: None,
+            profile: None,
+            hash_log: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+        }
+    }
+
+    fn blank_ram(font: FontStyle) -> Memory {
+        let mut ram = Memory::new(DEFAULT_RAM_SIZE);
+        ram.load(0, font.glyphs());
+        ram.load(BIG_FONT_START, &BIG_GLYPHS);
+        ram
+    }
+
+    /// Counters that keep updating while the emulator runs.
+    pub fn stats(&self) -> Arc<Stats> {
+        self.stats.clone()
+    }
+
+    /// Instructions executed so far.
+    pub fn instructions(&self) -> u64 {
+        self.stats.instructions()
+    }
+
+    /// Frames presented by the window, or run without one.
+    pub fn frames(&self) -> u64 {
+        self.stats.frames()
+    }
+
+    /// Sprites drawn with DRW.
+    pub fn draw_calls(&self) -> u64 {
+        self.stats.draw_calls()
+    }
+
+    /// Sprites drawn that erased at least one pixel, setting VF.
+    pub fn collisions(&self) -> u64 {
+        self.stats.collisions()
+    }
+
+    /// `LD Vx, K` instructions that received their key.
+    pub fn key_waits(&self) -> u64 {
+        self.stats.key_waits()
+    }
+
+    pub fn frontend(&self) -> &F {
+        &self.frontend
+    }
+
+    pub fn frontend_mut(&mut self) -> &mut F {
+        &mut self.frontend
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.reg
+    }
+
+    /// Sound the buzzer as `tone` instead of a 440Hz square wave.
+    #[cfg(feature = "audio")]
+    pub fn set_tone(&self, tone: Tone) {
+        if let Some(audio) = &self.audio {
+            audio.set_tone(tone);
+        }
+    }
+
+    /// The XO-CHIP audio pattern the buzzer plays, if the program has loaded
+    /// one, for frontends making their own sound.
+    pub fn audio_pattern(&self) -> Option<Pattern> {
+        self.pattern.map(|bits| Pattern {
+            bits,
+            pitch: self.pitch,
+        })
+    }
+
+    /// Hand the audio pattern to the audio output after the program changes
+    /// it.
+    fn play_pattern(&self) {
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &self.audio {
+            audio.set_pattern(self.audio_pattern());
+        }
+    }
 
-impl CHIP8 {
-    pub fn new() -> Self {
-        let mut ram = [0; 0xFFF];
-        ram[..80].clone_from_slice(&SPRITES);
-        CHIP8 {
-            stack: Vec::with_capacity(16),
-            ram: ram,
-            reg: Registers::new(),
-            display: Display::init(),
+    /// Read the keypad from `gamepad`'s controllers too.
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepad(&mut self, gamepad: Gamepad) {
+        self.gamepad = Some(gamepad);
+    }
+
+    /// The live sound timer, for following the buzzer from another thread.
+    #[cfg(feature = "gamepad")]
+    pub fn sound_timer(&self) -> Arc<AtomicU8> {
+        self.reg.timer_handles().1
+    }
+
+    /// Tick the delay and sound timers once. Only needed for a `headless`
+    /// CHIP8, whose timers have no clock of their own.
+    pub fn tick_timers(&self) {
+        self.reg.tick_timers();
+    }
+
+    /// The opcode PC points at, or `None` once PC has run off the end of RAM.
+    pub fn current_opcode(&self) -> Option<u16> {
+        let mut lo = self.reg.PC + 1;
+        if self.pc_overflow == PcOverflow::Wrap && lo == self.ram.len() {
+            lo = 0;
         }
+        let hi = self.ram.get(self.reg.PC)?;
+        let lo = self.ram.get(lo)?;
+        Some(u16::from_be_bytes([hi, lo]))
     }
 
-    fn decode_instruction(bytes: u16) -> Instruction {
-        match get_first(bytes) {
-            0x0 => {
-                if bytes == 0x00E0 {
-                    return Instruction::CLS;
-                } else if bytes == 0x00EE {
-                    return Instruction::RET;
-                }
-                return Instruction::SYS(get_addr(bytes));
-            }
-            0x1 => Instruction::JP(get_addr(bytes)),
-            0x2 => Instruction::CALL(get_addr(bytes)),
-            0x3 => Instruction::SE(get_vx(bytes), Either::Right(get_byte(bytes))),
-            0x4 => Instruction::SNE(get_vx(bytes), Either::Right(get_byte(bytes))),
-            0x5 => Instruction::SE(get_vx(bytes), Either::Left(get_vy(bytes))),
-            0x6 => Instruction::LD(get_vx(bytes), Either::Right(get_byte(bytes))),
-            0x7 => Instruction::ADD(get_vx(bytes), Either::Right(get_byte(bytes))),
-            0x8 => match get_nibble(bytes) {
-                0x0 => Instruction::LD(get_vx(bytes), Either::Left(get_vy(bytes))),
-                0x1 => Instruction::OR(get_vx(bytes), get_vy(bytes)),
-                0x2 => Instruction::AND(get_vx(bytes), get_vy(bytes)),
-                0x3 => Instruction::XOR(get_vx(bytes), get_vy(bytes)),
-                0x4 => Instruction::ADD(get_vx(bytes), Either::Left(get_vy(bytes))),
-                0x5 => Instruction::SUB(get_vx(bytes), get_vy(bytes)),
-                0x6 => Instruction::SHR(get_vx(bytes)),
-                0x7 => Instruction::SUBN(get_vx(bytes), get_vy(bytes)),
-                0xE => Instruction::SHL(get_vx(bytes)),
-                _ => {
-                    panic!("Unrecognized OP Code 0x{:X}", bytes)
-                }
-            },
-            0x9 => Instruction::SNE(get_vx(bytes), Either::Left(get_vy(bytes))),
-            0xA => Instruction::LD_I(get_addr(bytes)),
-            0xB => Instruction::JP_V0(get_addr(bytes)),
-            0xC => Instruction::RND(get_vx(bytes), get_byte(bytes)),
-            0xD => Instruction::DRW(get_vx(bytes), get_vy(bytes), get_nibble(bytes)),
-            0xE => match bytes.to_be_bytes()[1] {
-                0x9E => Instruction::SKP(get_vx(bytes)),
-                0xA1 => Instruction::SKNP(get_vx(bytes)),
-                _ => {
-                    panic!("Unrecognized OP Code 0x{:X}", bytes)
-                }
-            },
-            0xF => match bytes.to_be_bytes()[1] {
-                0x07 => Instruction::LD_Vx_DT(get_vx(bytes)),
-                0x0A => Instruction::LD_Vx_K(get_vx(bytes)),
-                0x15 => Instruction::LD_DT_Vx(get_vx(bytes)),
-                0x18 => Instruction::LD_ST_Vx(get_vx(bytes)),
-                0x1E => Instruction::ADD_I(get_vx(bytes)),
-                0x29 => Instruction::LD_F(get_vx(bytes)),
-                0x33 => Instruction::LD_B(get_vx(bytes)),
-                0x55 => Instruction::LD_I_Vx(get_vx(bytes)),
-                0x65 => Instruction::LD_Vx_I(get_vx(bytes)),
-                _ => {
-                    panic!("Unrecognized OP Code 0x{:X}", bytes)
+    /// Run `hz` instructions a second, `DEFAULT_CPU_HZ` unless set.
+    pub fn set_cpu_hz(&mut self, hz: u32) {
+        self.cpu_hz = hz.max(1);
+        self.show_status();
+    }
+
+    /// Tell the frontend whether the game is paused and how fast it runs
+    /// against the default speed, when that is not 1x.
+    fn show_status(&self) {
+        let mut status = Vec::new();
+        #[cfg(feature = "window")]
+        if self.paused || self.unfocused {
+            status.push("Paused".to_string());
+        }
+        if self.cpu_hz != DEFAULT_CPU_HZ {
+            let speed = f64::from(self.cpu_hz) / f64::from(DEFAULT_CPU_HZ);
+            status.push(format!("{}x", (speed * 10.0).round() / 10.0));
+        }
+        self.frontend.set_status(&status.join(" - "));
+    }
+
+    pub fn set_stack_limit(&mut self, limit: StackLimit) {
+        self.stack_limit = limit;
+    }
+
+    /// The deepest the calls have nested since the last reset, to size
+    /// `set_stack_limit` by.
+    pub fn max_stack_depth(&self) -> usize {
+        self.max_stack_depth
+    }
+
+    /// Keep the RPL user flags in the file at `path`, reading them from it
+    /// now if it exists, so high scores and settings outlast the run.
+    pub fn set_flags_path(&mut self, path: PathBuf) {
+        match fs::read(&path) {
+            Ok(flags) => {
+                let len = flags.len().min(self.flags.len());
+                self.flags[..len].copy_from_slice(&flags[..len]);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Could not read flags from `{}`: {e}", path.display()),
+        }
+        self.flags_path = Some(path);
+    }
+
+    /// What to do on an opcode nobody knows: fail, as by default, or skip it,
+    /// quietly or with a warning.
+    pub fn set_on_unknown(&mut self, on_unknown: OnUnknown) {
+        self.on_unknown = on_unknown;
+    }
+
+    /// What to do once the program is stuck in a jump to itself: spin there,
+    /// as by default, end the run, or show that it halted in the window.
+    pub fn set_on_halt(&mut self, on_halt: OnHalt) {
+        self.on_halt = on_halt;
+    }
+
+    pub fn on_halt(&self) -> OnHalt {
+        self.on_halt
+    }
+
+    /// What to do when PC runs off the end of RAM: stop, as by default, fail
+    /// with an error or wrap around to the start.
+    pub fn set_pc_overflow(&mut self, overflow: PcOverflow) {
+        self.pc_overflow = overflow;
+    }
+
+    /// Call `hook` before each instruction the interpreter runs, with the
+    /// state it runs in, e.g. for tracing or coverage. Opcodes run by
+    /// `register_opcode` handlers are not seen.
+    pub fn set_hook(&mut self, hook: impl FnMut(&Chip8State, &Instruction) + 'static) {
+        self.pre_hook = Some(Box::new(hook));
+    }
+
+    /// Call `hook` after each instruction the interpreter runs successfully,
+    /// with the state it left behind.
+    pub fn set_post_hook(&mut self, hook: impl FnMut(&Chip8State, &Instruction) + 'static) {
+        self.post_hook = Some(Box::new(hook));
+    }
+
+    /// Run `handler` for opcodes matching `pattern` that the interpreter does
+    /// not implement itself: unknown ones and 0NNN machine code calls. The
+    /// first registered match wins.
+    pub fn register_opcode<H>(&mut self, pattern: OpcodePattern, handler: H)
+    where
+        H: FnMut(&mut CHIP8<F>, u16) + 'static,
+    {
+        self.extensions.push((pattern, Box::new(handler)));
+    }
+
+    /// Hand `opcode` to its registered handler, if it has one.
+    fn run_extension(&mut self, opcode: u16) -> bool {
+        if !matches!(decode(opcode), Err(_) | Ok(Instruction::SYS(_))) {
+            return false;
+        }
+        let mut extensions = mem::take(&mut self.extensions);
+        let handler = extensions
+            .iter_mut()
+            .find(|(pattern, _)| pattern.matches(opcode));
+        let handled = match handler {
+            Some((_, handler)) => {
+                self.reg.PC += 2;
+                handler(self, opcode);
+                true
+            }
+            None => false,
+        };
+        // Handlers may have registered more of their own.
+        extensions.append(&mut self.extensions);
+        self.extensions = extensions;
+        handled
+    }
+
+    /// Behave as the interpreters with `quirks` did, from the next
+    /// instruction on.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.fb.set_wrap(quirks.wrap);
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Switch the hex digit glyphs, in RAM right away and on every reset.
+    pub fn set_font_style(&mut self, font: FontStyle) {
+        self.font = font;
+        self.ram.load(0, font.glyphs());
+    }
+
+    /// Give the machine `size` bytes of RAM, from `MIN_RAM_SIZE` up to
+    /// `MAX_RAM_SIZE`, and reset it. Call this before loading a ROM, which
+    /// must fit.
+    pub fn set_ram_size(&mut self, size: usize) {
+        self.ram.resize(size.clamp(MIN_RAM_SIZE, MAX_RAM_SIZE));
+        self.reset();
+    }
+
+    /// Return to power-on state with the loaded ROM back in RAM: registers,
+    /// stack, timers and display are cleared and RAM is re-seeded from the
+    /// ROM, undoing anything the program wrote to it.
+    pub fn reset(&mut self) {
+        self.ram.clear();
+        self.ram.load(0, self.font.glyphs());
+        self.ram.load(BIG_FONT_START, &BIG_GLYPHS);
+        self.ram.load(0x200, &self.rom);
+        self.stack.clear();
+        self.max_stack_depth = 0;
+        self.key_pressed = None;
+        self.idle.reset();
+        self.frame_steps = 0;
+        self.reg.reset();
+        self.pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.play_pattern();
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.fb.set_hires(false);
+        self.frontend.draw(&self.fb);
+    }
+
+    /// Draw RND's random numbers from `seed`, so that runs given the same
+    /// keys at the same times repeat exactly. Without one, a random seed is
+    /// picked at startup.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// The seed RND's random numbers are drawn from since power on.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The colors of lit and unlit pixels.
+    pub fn colors(&self) -> (u32, u32) {
+        self.fb.colors()
+    }
+
+    /// Draw lit pixels in `fg` and unlit ones in `bg`, recoloring the screen.
+    pub fn set_colors(&mut self, fg: u32, bg: u32) {
+        self.fb.set_colors(fg, bg);
+        self.frontend.draw(&self.fb);
+    }
+
+    /// Apply the title, speed, quirks and colors from a ROM's Octo metadata.
+    pub fn apply_metadata(&mut self, meta: &Metadata) {
+        if let Some(name) = meta.display_name() {
+            self.frontend.set_title(&name);
+        }
+
+        let options = &meta.options;
+        if let Some(tickrate) = options.tickrate {
+            self.set_cpu_hz(tickrate.saturating_mul(60));
+        }
+        let mut quirks = self.quirks;
+        if let Some(clip) = options.clip_quirks {
+            quirks.wrap = !clip;
+        }
+        if let Some(shift) = options.shift_quirks {
+            quirks.shift_vy = !shift;
+        }
+        if let Some(load_store) = options.load_store_quirks {
+            quirks.increment_i = !load_store;
+        }
+        self.set_quirks(quirks);
+        let fg = options.fill_color.as_deref().and_then(octo::parse_color);
+        let bg = options
+            .background_color
+            .as_deref()
+            .and_then(octo::parse_color);
+        if fg.is_some() || bg.is_some() {
+            self.set_colors(fg.unwrap_or(DEFAULT_FG), bg.unwrap_or(DEFAULT_BG));
+        }
+    }
+
+    /// A copy of the machine as the program sees it, for `load_state` to
+    /// return to.
+    pub fn state(&self) -> Chip8State {
+        Chip8State {
+            ram: self.ram.to_vec(),
+            stack: self.stack.clone(),
+            pc: self.reg.PC,
+            sp: self.reg.SP,
+            i: self.reg.I,
+            vx: self.reg.Vx,
+            dt: self.reg.get_dt(),
+            st: self.reg.get_st(),
+            display: self.fb.lit().to_vec(),
+        }
+    }
+
+    /// Start over from power on, recording the keypad into a movie that
+    /// `replay` repeats exactly.
+    pub fn record(&mut self) {
+        self.input = Input::Recording {
+            movie: Movie::new(self.seed, self.cpu_hz),
+            keys: Default::default(),
+        };
+        self.reset();
+    }
+
+    /// The movie `record` is making, so far.
+    pub fn recording(&self) -> Option<&Movie> {
+        match &self.input {
+            Input::Recording { movie, .. } => Some(movie),
+            _ => None,
+        }
+    }
+
+    /// Start over from power on, playing `movie` back on the keypad. Once it
+    /// runs out, the frontend's keys count again.
+    pub fn replay(&mut self, movie: Movie) {
+        self.set_seed(movie.seed);
+        self.set_cpu_hz(movie.cpu_hz);
+        self.input = Input::Replaying {
+            movie,
+            frame: 0,
+            next: 0,
+            keys: Default::default(),
+        };
+        self.reset();
+    }
+
+    /// Write every instruction run from now on to `out`, with the registers
+    /// and memory it changed, in the format of the `trace` command.
+    pub fn set_trace(&mut self, out: impl io::Write + 'static) {
+        self.tracer = Some(Tracer::new(Box::new(out), &self.reg, &self.ram));
+    }
+
+    /// Count how often each address and each kind of opcode runs from now
+    /// on, starting from zero.
+    pub fn start_profile(&mut self) {
+        self.profile = Some(Profile::new(self.ram.len()));
+    }
+
+    /// The counts since `start_profile`, if profiling.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Run `script` alongside the program from now on, in place of any
+    /// script before it.
+    #[cfg(feature = "scripting")]
+    pub fn set_script(&mut self, script: Script) {
+        self.script = Some(script);
+    }
+
+    /// Give the script its `callback`, applying whatever it changed. A
+    /// script that fails is reported and stopped.
+    #[cfg(feature = "scripting")]
+    fn run_script(&mut self, callback: Callback) {
+        let mut script = match self.script.take() {
+            Some(script) if script.handles(callback) => script,
+            script => {
+                self.script = script;
+                return;
+            }
+        };
+        let before = self.state();
+        let mut state = before.clone();
+        match script.call(callback, &mut state) {
+            Ok(()) => {
+                if state != before {
+                    self.load_state(&state);
                 }
-            },
-            _ => {
-                unreachable!()
+                self.script = Some(script);
             }
+            Err(e) => eprintln!("The script failed and was stopped: {e}"),
+        }
+    }
+
+    /// Record the screen to an animated GIF at `path` until `stop_gif`, one
+    /// frame every 60th of a second the program runs.
+    pub fn start_gif(&mut self, path: &Path) -> io::Result<()> {
+        self.stop_gif()?;
+        self.gif = Some(GifRecorder::create(path)?);
+        Ok(())
+    }
+
+    /// Finish the GIF being recorded, if any.
+    pub fn stop_gif(&mut self) -> io::Result<()> {
+        self.gif.take().map_or(Ok(()), GifRecorder::finish)
+    }
+
+    pub fn is_recording_gif(&self) -> bool {
+        self.gif.is_some()
+    }
+
+    fn record_gif_frame(&self) {
+        if let Some(gif) = &self.gif {
+            let (fg, bg) = self.fb.colors();
+            gif.frame(self.fb.lit(), fg, bg);
+        }
+    }
+
+    /// Return to the moment `state` was saved. The loaded ROM, which `reset`
+    /// restores, is kept.
+    pub fn load_state(&mut self, state: &Chip8State) {
+        self.ram.load(0, &state.ram);
+        self.stack = state.stack.clone();
+        self.max_stack_depth = self.max_stack_depth.max(self.stack.len());
+        self.reg.PC = state.pc;
+        self.reg.SP = state.sp;
+        self.reg.I = state.i;
+        self.reg.Vx = state.vx;
+        self.reg.set_dt(state.dt);
+        self.reg.set_st(state.st);
+        self.key_pressed = None;
+        self.idle.reset();
+        self.fb.set_lit(&state.display);
+        self.frontend.draw(&self.fb);
+    }
+
+    fn decode_instruction(&self, opcode: u16) -> Result<Instruction, Chip8Error> {
+        decode(opcode).map_err(|UnknownOpcode(opcode)| Chip8Error::UnknownOpcode {
+            at: self.reg.PC,
+            opcode,
+        })
+    }
+
+    /// The `len` bytes of memory from `start`, or an error naming the first
+    /// address past its end.
+    fn ram(&self, start: usize, len: usize) -> Result<Vec<u8>, Chip8Error> {
+        self.ram.read(start, len).map_err(|v| self.violation(v))
+    }
+
+    /// Store `bytes` from `start`, or an error naming the first address that
+    /// can't be written, leaving memory as it was.
+    fn write_ram(&mut self, start: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        self.ram.write(start, bytes).map_err(|v| self.violation(v))
+    }
+
+    fn violation(&self, violation: Violation) -> Chip8Error {
+        let at = self.reg.PC;
+        match violation {
+            Violation::OutOfRange(addr) => Chip8Error::OutOfMemory { at, addr },
+            Violation::ReadOnly(addr) => Chip8Error::ReadOnly { at, addr },
+        }
+    }
+
+    /// Map `region` over `range` of the address space, in place of RAM, for
+    /// the program to read and write through. It stays mapped across
+    /// resets.
+    pub fn map_region(&mut self, range: Range<usize>, region: impl Region + 'static) {
+        self.ram.map(range, Box::new(region));
+    }
+
+    /// Point I at `addr`, cut to the 12 bits of the address space with the
+    /// `mask_i` quirk and to I's 16 bits otherwise.
+    fn set_i(&mut self, addr: u32) {
+        let mask = if self.quirks.mask_i { 0xFFF } else { 0xFFFF };
+        self.reg.I = (addr & mask) as u16;
+    }
+
+    /// Skip the next instruction, which is four bytes long if it is XO-CHIP's
+    /// `F000 NNNN`.
+    fn skip_next(&mut self) {
+        let next = self.ram.read(self.reg.PC + 2, 2).ok();
+        self.reg.PC += if next.as_deref() == Some(&[0xF0, 0x00]) {
+            4
+        } else {
+            2
+        };
+    }
+
+    /// Whether keypad `key` is down, asked on behalf of the program, which
+    /// counts towards the input latency.
+    fn observe_key(&self, key: u8) -> bool {
+        let down = self.keypad().is_down(key);
+        if down {
+            self.frontend.key_observed(key);
+        }
+        down
+    }
+
+    /// The keys the program sees: the live ones, or a movie's.
+    fn keypad(&self) -> KeypadState {
+        self.input.keys(self.live_keys())
+    }
+
+    /// The keys down on the frontend or on a controller.
+    fn live_keys(&self) -> KeypadState {
+        let keys = self.frontend.keys();
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &self.gamepad {
+            return keys.union(gamepad.keys());
         }
+        keys
     }
 
     fn get_vx_val(&self, reg: VxyRegister) -> u8 {
@@ -105,17 +1674,37 @@ impl CHIP8 {
         self.reg.Vx[*reg as usize] = val
     }
 
-    fn execute_instruction(&mut self, instr: Instruction) {
+    fn execute_instruction(&mut self, instr: Instruction) -> Result<(), Chip8Error> {
         match instr {
             Instruction::SYS(_) => {
                 // ignored
             }
+            Instruction::SCD(nibble) => {
+                self.fb.scroll_down(nibble as usize);
+                self.frontend.draw(&self.fb);
+            }
+            Instruction::SCR => {
+                self.fb.scroll_sideways(4);
+                self.frontend.draw(&self.fb);
+            }
+            Instruction::SCL => {
+                self.fb.scroll_sideways(-4);
+                self.frontend.draw(&self.fb);
+            }
+            Instruction::LOW | Instruction::HIGH => {
+                self.fb.set_hires(matches!(instr, Instruction::HIGH));
+                self.frontend.draw(&self.fb);
+            }
             Instruction::CLS => {
-                self.display.clear();
-                self.display.update_buffer();
+                self.fb.clear();
+                self.frontend.draw(&self.fb);
             }
             Instruction::RET => {
-                self.reg.PC = self.stack.pop().unwrap().clone() as usize;
+                self.reg.PC = self
+                    .stack
+                    .pop()
+                    .ok_or(Chip8Error::StackUnderflow { at: self.reg.PC })?
+                    as usize;
                 self.reg.SP = self.reg.SP.wrapping_sub(1);
             }
             Instruction::JP(addr) => {
@@ -125,8 +1714,17 @@ impl CHIP8 {
                 self.reg.PC = (addr + self.reg.Vx[0] as u16) as usize;
             }
             Instruction::CALL(addr) => {
-                self.reg.SP += 1;
+                if let StackLimit::Depth(limit) = self.stack_limit {
+                    if self.stack.len() >= limit {
+                        return Err(Chip8Error::StackOverflow {
+                            at: self.reg.PC,
+                            limit,
+                        });
+                    }
+                }
+                self.reg.SP = self.reg.SP.wrapping_add(1);
                 self.stack.push(self.reg.PC as u16);
+                self.max_stack_depth = self.max_stack_depth.max(self.stack.len());
                 self.reg.PC = addr as usize;
             }
             Instruction::SE(vx, other) => {
@@ -136,7 +1734,7 @@ impl CHIP8 {
                     Either::Right(u8) => u8,
                 };
                 if val1 == val2 {
-                    self.reg.PC += 2
+                    self.skip_next();
                 }
             }
             Instruction::SNE(vx, other) => {
@@ -146,7 +1744,7 @@ impl CHIP8 {
                     Either::Right(u8) => u8,
                 };
                 if val1 != val2 {
-                    self.reg.PC += 2
+                    self.skip_next();
                 }
             }
             Instruction::ADD(vx, other) => {
@@ -159,7 +1757,13 @@ impl CHIP8 {
                 self.set_vx_val(vx, result.0);
                 self.set_vx_val(VxyRegister(0xF), result.1 as u8);
             }
-            Instruction::ADD_I(vx) => self.reg.I += self.get_vx_val(vx) as u16,
+            Instruction::ADD_I(vx) => {
+                let sum = u32::from(self.reg.I) + u32::from(self.get_vx_val(vx));
+                if self.quirks.add_i_carry {
+                    self.set_vx_val(VxyRegister(0xF), (sum > 0xFFF) as u8);
+                }
+                self.set_i(sum);
+            }
             Instruction::SUB(vx, vy) => {
                 let val1 = self.get_vx_val(vx);
                 let val2 = self.get_vx_val(vy);
@@ -167,7 +1771,7 @@ impl CHIP8 {
                 self.set_vx_val(vx, result.0);
                 self.set_vx_val(VxyRegister(0xF), !result.1 as u8);
             }
-            Instruction::SUBN(vx, vy) => self.execute_instruction(Instruction::SUB(vy, vx)),
+            Instruction::SUBN(vx, vy) => self.execute_instruction(Instruction::SUB(vy, vx))?,
             Instruction::OR(vx, vy) => {
                 let val1 = self.get_vx_val(vx);
                 let val2 = self.get_vx_val(vy);
@@ -183,54 +1787,57 @@ impl CHIP8 {
                 let val2 = self.get_vx_val(vy);
                 self.set_vx_val(vx, val1 ^ val2)
             }
-            Instruction::SHR(vx) => {
-                let val1 = self.get_vx_val(vx);
+            Instruction::SHR(vx, vy) => {
+                let val1 = self.get_vx_val(if self.quirks.shift_vy { vy } else { vx });
                 self.set_vx_val(VxyRegister(0xF), (val1.trailing_ones() > 0) as u8);
                 self.set_vx_val(vx, val1 >> 1)
             }
-            Instruction::SHL(vx) => {
-                let val1 = self.get_vx_val(vx);
+            Instruction::SHL(vx, vy) => {
+                let val1 = self.get_vx_val(if self.quirks.shift_vy { vy } else { vx });
                 self.set_vx_val(VxyRegister(0xF), (val1.leading_ones() > 0) as u8);
                 self.set_vx_val(vx, val1 << 1)
             }
             Instruction::RND(vx, byte) => {
-                let rand: u8 = random();
+                let rand: u8 = self.rng.gen();
                 self.set_vx_val(vx, rand & byte);
             }
             Instruction::DRW(vx, vy, nibble) => {
+                let (x, y) = (self.get_vx_val(vx), self.get_vx_val(vy));
                 let start = self.reg.I as usize;
-                let end = (self.reg.I + nibble as u16) as usize;
-                let bytes = &self.ram[start..end];
-                let collision =
-                    self.display
-                        .set_pixels(self.get_vx_val(vx), self.get_vx_val(vy), bytes);
-                self.display.update_buffer();
+                let collision = if nibble == 0 {
+                    // SUPER-CHIP: a 16x16 sprite, two bytes per row.
+                    let bytes = self.ram(start, 32)?;
+                    self.fb.set_wide_pixels(x, y, &bytes)
+                } else {
+                    let bytes = self.ram(start, nibble as usize)?;
+                    self.fb.set_pixels(x, y, &bytes)
+                };
+                self.frontend.draw(&self.fb);
+                self.stats.count_draw(collision);
                 self.set_vx_val(VxyRegister(0xF), collision as u8);
             }
             Instruction::SKP(vx) => {
                 let val = self.get_vx_val(vx);
-                let key = map_u8_to_key(val).expect(
-                    format!(
-                        "Invalid key value {:?} in register {:?} used in SKP instruction",
-                        val, vx
-                    )
-                    .as_ref(),
-                );
-                if self.display.is_key_down(key) {
-                    self.reg.PC += 2;
+                if val > 0xF {
+                    return Err(Chip8Error::InvalidKey {
+                        at: self.reg.PC,
+                        key: val,
+                    });
+                }
+                if self.observe_key(val) {
+                    self.skip_next();
                 }
             }
             Instruction::SKNP(vx) => {
                 let val = self.get_vx_val(vx);
-                let key = map_u8_to_key(val).expect(
-                    format!(
-                        "Invalid key value {:?} in register {:?} used in SKNP instruction",
-                        val, vx
-                    )
-                    .as_ref(),
-                );
-                if !self.display.is_key_down(key) {
-                    self.reg.PC += 2;
+                if val > 0xF {
+                    return Err(Chip8Error::InvalidKey {
+                        at: self.reg.PC,
+                        key: val,
+                    });
+                }
+                if !self.observe_key(val) {
+                    self.skip_next();
                 }
             }
             Instruction::LD(vx, other) => {
@@ -243,14 +1850,30 @@ impl CHIP8 {
             Instruction::LD_I(addr) => {
                 self.reg.I = addr;
             }
+            Instruction::LD_I_LONG => {
+                // The address is the word after the opcode, skipped here.
+                let addr = self.ram(self.reg.PC + 2, 2)?;
+                self.reg.I = u16::from_be_bytes([addr[0], addr[1]]);
+                self.reg.PC += 2;
+            }
             Instruction::LD_Vx_DT(vx) => self.set_vx_val(vx, self.reg.get_dt()),
             Instruction::LD_Vx_K(vx) => {
-                while self.display.is_window_open() {
-                    if let Some(key) = self.display.get_key_down() {
-                        if let Some(val) = map_key_to_u8(key) {
-                            self.set_vx_val(vx, val);
-                            break;
+                // Wait for a key to be pressed and released by executing this
+                // instruction again until it is, while the timers run on.
+                let keys = self.keypad();
+                match self.key_pressed {
+                    Some(key) if !keys.is_down(key) => {
+                        self.key_pressed = None;
+                        self.stats.count_key_wait();
+                        self.set_vx_val(vx, key);
+                    }
+                    Some(_) => self.reg.PC -= 2,
+                    None => {
+                        self.key_pressed = keys.first_down();
+                        if let Some(key) = self.key_pressed {
+                            self.frontend.key_observed(key);
                         }
+                        self.reg.PC -= 2;
                     }
                 }
             }
@@ -262,67 +1885,457 @@ impl CHIP8 {
             }
             Instruction::LD_F(vx) => {
                 let val = self.get_vx_val(vx);
-                self.reg.I = CHIP8::get_sprite_addr(val)
-                    .expect(format!("Tried to get sprite with hex {:X}", val).as_ref());
+                self.reg.I = Self::get_sprite_addr(val).ok_or(Chip8Error::InvalidDigit {
+                    at: self.reg.PC,
+                    digit: val,
+                })?;
+            }
+            Instruction::LD_HF(vx) => {
+                let val = self.get_vx_val(vx);
+                self.reg.I = Self::get_big_sprite_addr(val).ok_or(Chip8Error::InvalidDigit {
+                    at: self.reg.PC,
+                    digit: val,
+                })?;
             }
             Instruction::LD_B(vx) => {
                 let val = self.get_vx_val(vx);
-                let bcd = to_bcd(val);
-                self.ram[self.reg.I as usize] = bcd[0];
-                self.ram[(self.reg.I + 1) as usize] = bcd[1];
-                self.ram[(self.reg.I + 2) as usize] = bcd[2];
-            }
-            Instruction::LD_I_Vx(vx) => match vx {
-                VxyRegister(byte) => {
-                    for i in 0..byte + 1 {
-                        let val = self.get_vx_val(VxyRegister(i));
-                        self.ram[(self.reg.I + i as u16) as usize] = val;
-                    }
+                self.write_ram(self.reg.I as usize, &to_bcd(val))?;
+            }
+            Instruction::LD_I_Vx(VxyRegister(x)) => {
+                let len = x as usize + 1;
+                let vx = self.reg.Vx;
+                self.write_ram(self.reg.I as usize, &vx[..len])?;
+                if self.quirks.increment_i {
+                    self.set_i(u32::from(self.reg.I) + len as u32);
                 }
-            },
-            Instruction::LD_Vx_I(vx) => match vx {
-                VxyRegister(byte) => {
-                    for i in 0..byte + 1 {
-                        let val = self.ram[(self.reg.I + i as u16) as usize];
-                        self.set_vx_val(VxyRegister(i), val)
+            }
+            Instruction::LD_R_Vx(VxyRegister(x)) => {
+                let len = x as usize + 1;
+                self.flags[..len].copy_from_slice(&self.reg.Vx[..len]);
+                if let Some(path) = &self.flags_path {
+                    if let Err(e) = fs::write(path, self.flags) {
+                        eprintln!("Could not save flags to `{}`: {e}", path.display());
                     }
                 }
-            },
+            }
+            Instruction::LD_Vx_R(VxyRegister(x)) => {
+                let len = x as usize + 1;
+                self.reg.Vx[..len].copy_from_slice(&self.flags[..len]);
+            }
+            Instruction::AUDIO => {
+                let mut bits = [0; 16];
+                bits.copy_from_slice(&self.ram(self.reg.I as usize, 16)?);
+                self.pattern = Some(bits);
+                self.play_pattern();
+            }
+            Instruction::LD_PITCH_Vx(vx) => {
+                self.pitch = self.get_vx_val(vx);
+                self.play_pattern();
+            }
+            Instruction::SAVE(vx, vy) => {
+                let vals: Vec<u8> = register_range(vx, vy)
+                    .map(|reg| self.get_vx_val(reg))
+                    .collect();
+                self.write_ram(self.reg.I as usize, &vals)?;
+            }
+            Instruction::LOAD(vx, vy) => {
+                let regs: Vec<VxyRegister> = register_range(vx, vy).collect();
+                let vals = self.ram(self.reg.I as usize, regs.len())?;
+                for (reg, val) in regs.into_iter().zip(vals) {
+                    self.set_vx_val(reg, val);
+                }
+            }
+            Instruction::LD_Vx_I(VxyRegister(x)) => {
+                let len = x as usize + 1;
+                let vals = self.ram(self.reg.I as usize, len)?;
+                self.reg.Vx[..len].copy_from_slice(&vals);
+                if self.quirks.increment_i {
+                    self.set_i(u32::from(self.reg.I) + len as u32);
+                }
+            }
         }
+        Ok(())
     }
 
+    /// Load the ROM in `filename`, assembling it first if it is Octo source
+    /// ending in `.8o`. `-` reads the ROM from stdin, and an `http://` or
+    /// `https://` URL downloads it in builds with the `download` feature.
     pub fn load(&mut self, filename: &str) -> Result<(), io::Error> {
-        let mut f = File::open(filename)?;
-        f.read(&mut self.ram[0x200..])?;
+        let mut rom = Vec::new();
+        if filename == "-" {
+            io::stdin().lock().read_to_end(&mut rom)?;
+        } else if download::is_url(filename) {
+            rom = download::fetch(filename)?;
+        } else {
+            File::open(filename)?.read_to_end(&mut rom)?;
+        }
+        if Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext == "8o")
+        {
+            let source = String::from_utf8(rom)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            rom = assembler::assemble(&source)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        self.load_bytes(&rom)?;
         Ok(())
     }
 
-    pub fn run(&mut self) {
-        while self.display.is_window_open() && self.reg.PC + 1 <= self.ram.len() {
-            let opcode: u16 =
-                self.ram[self.reg.PC] as u16 * 0x0100 + self.ram[self.reg.PC + 1] as u16;
-            let instr = CHIP8::decode_instruction(opcode);
-            let mut increment = true;
-            match instr {
-                Instruction::JP(_) | Instruction::JP_V0(_) | Instruction::CALL(_) => {
-                    increment = false
-                }
-                _ => {}
+    /// Copy `rom` into RAM at 0x200, or fail if it does not fit, leaving the
+    /// machine as it was. The ROM is kept so `reset` can restore it.
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), RomTooLarge> {
+        let room = self.ram.len() - 0x200;
+        if rom.len() > room {
+            return Err(RomTooLarge {
+                size: rom.len(),
+                room,
+            });
+        }
+        self.rom = rom.to_vec();
+        self.ram.load(0x200, &self.rom);
+        Ok(())
+    }
+
+    /// The loaded ROM, as `load_bytes` was given it.
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Fetch, decode and execute the instruction at PC, returning it, or
+    /// `None` for an opcode only a registered extension knows or one skipped
+    /// as unknown. Timers are
+    /// left alone; call `tick_timers` to keep them going. On an error the
+    /// machine is left as it was before the instruction, with PC pointing at
+    /// it. If an extension panics, a stack trace is printed before the panic
+    /// carries on.
+    pub fn step(&mut self) -> Result<Option<Instruction>, Chip8Error> {
+        let (pc, opcode) = (self.reg.PC, self.current_opcode());
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| self.execute_next())) {
+            Ok(result) => result,
+            Err(panic) => {
+                eprintln!("{}", self.stack_trace());
+                panic::resume_unwind(panic);
+            }
+        };
+
+        if let (Ok(_), Some(opcode), Some(profile)) = (&result, opcode, &mut self.profile) {
+            profile.count(pc, opcode);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            let logged = match (&result, opcode) {
+                (Ok(_), Some(opcode)) => tracer.executed(pc, opcode, &self.reg, &self.ram),
+                (Err(e), _) => tracer.failed(opcode, e),
+                (Ok(_), None) => Ok(()),
+            };
+            if let Err(e) = logged {
+                eprintln!("Could not write the trace, no longer tracing: {e}");
+                self.tracer = None;
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let (Ok(Some(_)), Some(opcode)) = (&result, opcode) {
+            self.run_script(Callback::Instruction(opcode));
+        }
+        result
+    }
+
+    /// Whether PC has run off the end of RAM, which ends the program unless
+    /// `set_pc_overflow` says otherwise.
+    pub fn has_halted(&self) -> bool {
+        self.pc_overflow == PcOverflow::Stop && self.reg.PC + 1 >= self.ram.len()
+    }
+
+    /// Whether the program is stuck in a jump to itself, which only a reset
+    /// gets it out of.
+    pub fn is_stuck(&self) -> bool {
+        self.current_opcode()
+            .is_some_and(|opcode| opcode >> 12 == 0x1 && usize::from(opcode & 0xFFF) == self.reg.PC)
+    }
+
+    /// Whether the run is over: PC has run off the end of RAM, or the
+    /// program is stuck and `set_on_halt` says to end there.
+    pub fn is_done(&self) -> bool {
+        self.has_halted() || matches!(self.on_halt, OnHalt::Exit | OnHalt::Print) && self.is_stuck()
+    }
+
+    /// Run the rest of the current 60th of a second's worth of instructions
+    /// at `cpu_hz`, ticking the timers at its end, then start or stop the
+    /// buzzer and present the screen: a frame for frontends that pace
+    /// themselves.
+    pub fn step_frame(&mut self) -> Result<(), Chip8Error> {
+        loop {
+            if self.has_halted() {
+                return Ok(());
             }
+            if self.step_timed()? {
+                break;
+            }
+        }
+        self.frontend.beep(self.reg.get_st() > 0);
+        self.frontend.present();
+        self.stats.count_frame();
+        Ok(())
+    }
+
+    /// Execute the instruction at PC, then tick the timers if that completed
+    /// a 60th of a second's worth at `cpu_hz`, unless something else ticks
+    /// them. Returns whether a frame ended.
+    fn step_timed(&mut self) -> Result<bool, Chip8Error> {
+        if self.frame_steps == 0 {
+            let live = self.live_keys();
+            self.input.next_frame(|| live);
+        }
+        self.step()?;
+        self.frame_steps += 1;
 
-            self.execute_instruction(instr);
+        // Spread speeds that are not a multiple of 60 evenly over the frames.
+        let hz = u64::from(self.cpu_hz);
+        let quota = |frame: u64| (frame + 1) * hz / 60 - frame * hz / 60;
+        let mut ended = false;
+        while self.frame_steps >= quota(self.ticks) {
+            self.frame_steps -= quota(self.ticks);
+            self.end_frame();
+            ended = true;
+        }
+        Ok(ended)
+    }
+
+    /// Tick the timers, unless something else ticks them, a 60th of a
+    /// second having passed.
+    fn end_frame(&mut self) {
+        self.ticks += 1;
+        if !self.reg.has_external_clock() {
+            self.reg.tick_timers();
+        }
+        self.record_gif_frame();
+        if let Some(log) = &mut self.hash_log {
+            let _ = writeln!(log, "{:016x}", self.fb.hash());
+        }
+        #[cfg(feature = "scripting")]
+        self.run_script(Callback::Frame);
+    }
 
-            if increment {
+    fn execute_next(&mut self) -> Result<Option<Instruction>, Chip8Error> {
+        if self.pc_overflow == PcOverflow::Wrap {
+            self.reg.PC %= self.ram.len();
+        }
+        let opcode = self
+            .current_opcode()
+            .ok_or(Chip8Error::RanOffEnd { at: self.reg.PC })?;
+        if self.run_extension(opcode) {
+            self.stats.count_instruction();
+            self.idle.reset();
+            return Ok(None);
+        }
+        let instr = match self.decode_instruction(opcode) {
+            Err(e) if self.on_unknown != OnUnknown::Halt => {
+                if self.on_unknown == OnUnknown::Warn && self.unknown_seen.insert(self.reg.PC) {
+                    eprintln!("Skipping {e}");
+                }
                 self.reg.PC += 2;
+                self.stats.count_instruction();
+                return Ok(None);
             }
+            result => result?,
+        };
+        let mut increment = true;
+        match instr {
+            Instruction::JP(_) | Instruction::JP_V0(_) | Instruction::CALL(_) => increment = false,
+            _ => {}
+        }
+
+        let effects = matches!(
+            instr,
+            Instruction::CLS
+                | Instruction::SCD(_)
+                | Instruction::SCR
+                | Instruction::SCL
+                | Instruction::LOW
+                | Instruction::HIGH
+                | Instruction::DRW(..)
+                | Instruction::RND(..)
+                | Instruction::LD_DT_Vx(_)
+                | Instruction::LD_ST_Vx(_)
+                | Instruction::LD_B(_)
+                | Instruction::LD_I_Vx(_)
+                | Instruction::LD_R_Vx(_)
+                | Instruction::AUDIO
+                | Instruction::LD_PITCH_Vx(_)
+                | Instruction::SAVE(..)
+        );
+
+        if let Some(mut hook) = self.pre_hook.take() {
+            hook(&self.state(), &instr);
+            self.pre_hook = Some(hook);
+        }
+        self.execute_instruction(instr)?;
+        self.stats.count_instruction();
+
+        if increment {
+            self.reg.PC += 2;
+        }
+        if let Some(mut hook) = self.post_hook.take() {
+            hook(&self.state(), &instr);
+            self.post_hook = Some(hook);
+        }
+        if effects {
+            self.idle.reset();
+        } else {
+            self.idle.observe(self.machine_state());
+        }
+        Ok(Some(instr))
+    }
+
+    fn machine_state(&self) -> MachineState {
+        (
+            self.reg.PC,
+            self.reg.Vx,
+            self.reg.I,
+            self.stack.len(),
+            self.stack.last().copied(),
+            self.reg.get_dt(),
+        )
+    }
+
+    /// A CHIP-8 level stack trace: the current PC followed by every CALL on
+    /// the stack, most recent first, each with the code around it.
+    pub fn stack_trace(&self) -> String {
+        let mut trace = String::from("CHIP-8 stack trace (most recent call first):\n");
+        let frames = iter::once(self.reg.PC).chain(self.stack.iter().rev().map(|&pc| pc as usize));
+        for (i, addr) in frames.enumerate() {
+            let _ = writeln!(trace, "  #{} at 0x{:03X}", i, addr);
+            trace.push_str(&self.disassemble_around(addr));
+        }
+        let _ = writeln!(
+            trace,
+            "  I = 0x{:03X}, V = {:02X?}",
+            self.reg.I, self.reg.Vx
+        );
+        let _ = writeln!(
+            trace,
+            "  {} nested calls, at most {} so far, limit {}",
+            self.stack.len(),
+            self.max_stack_depth,
+            self.stack_limit
+        );
+        trace
+    }
+
+    fn disassemble_around(&self, addr: usize) -> String {
+        let mut out = String::new();
+        for at in (addr.saturating_sub(4)..=addr + 4).step_by(2) {
+            let opcode = match (self.ram.get(at), self.ram.get(at + 1)) {
+                (Some(hi), Some(lo)) => u16::from_be_bytes([hi, lo]),
+                _ => continue,
+            };
+            let marker = if at == addr { "->" } else { "  " };
+            let text = decode(opcode).map_or("???".to_string(), |i| i.to_string());
+            let _ = writeln!(out, "    {} 0x{:03X}  {:04X}  {}", marker, at, opcode, text);
         }
+        out
+    }
+
+    /// Save the screen as a PNG, one image pixel per CHIP-8 pixel.
+    pub fn screenshot(&self, path: &Path) -> io::Result<()> {
+        screenshot::write_png(path, &self.fb.pixels().collect::<Vec<_>>())
+    }
+
+    pub fn is_pixel_lit(&self, x: usize, y: usize) -> bool {
+        self.fb.is_lit(x, y)
+    }
+
+    /// The whole address space as the program sees it, RAM and the regions
+    /// mapped over it.
+    pub fn memory(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    /// Write `bytes` to RAM from `addr` until it runs out, as settings left
+    /// for a ROM before it starts, e.g. the platform a test ROM reads from
+    /// 0x1FF. `reset` undoes it.
+    pub fn poke(&mut self, addr: usize, bytes: &[u8]) {
+        self.ram.load(addr, bytes);
+    }
+
+    /// A hash of which pixels are lit, the same whatever the colors: two
+    /// screens with the same hash show the same picture.
+    pub fn screen_hash(&self) -> u64 {
+        self.fb.hash()
+    }
+
+    /// Write the `screen_hash` at the end of every frame run to `log`, one
+    /// per line in hex, the same with a window or without.
+    pub fn log_frame_hashes(&mut self, log: File) {
+        self.hash_log = Some(LineWriter::new(log));
+    }
+
+    /// The screen as text, a line per row with `#` for lit pixels and `.`
+    /// for unlit ones.
+    pub fn screen_text(&self) -> String {
+        self.fb.to_text()
     }
 
     fn get_sprite_addr(hex: u8) -> Option<u16> {
         if hex > 0xF {
             None
         } else {
-            Some(hex as u16 * SPRITE_BYTE_LENGTH as u16)
+            Some(hex as u16 * GLYPH_BYTES as u16)
         }
     }
+
+    fn get_big_sprite_addr(hex: u8) -> Option<u16> {
+        if hex > 0xF {
+            None
+        } else {
+            Some((BIG_FONT_START + hex as usize * BIG_GLYPH_BYTES) as u16)
+        }
+    }
+}
+
+/// `chip8-<seconds since 1970>.<extension>` in the current directory, with a
+/// number added if that is taken.
+#[cfg(feature = "window")]
+fn capture_path(extension: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut path = PathBuf::from(format!("chip8-{}.{}", secs, extension));
+    for n in 2.. {
+        if !path.exists() {
+            break;
+        }
+        path = PathBuf::from(format!("chip8-{}-{}.{}", secs, n, extension));
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::frontend::NullFrontend;
+
+    #[test]
+    fn test_log_frame_hashes() {
+        let path = std::env::temp_dir().join(format!("chip8-hashes-{}", std::process::id()));
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.log_frame_hashes(std::fs::File::create(&path).unwrap());
+        chip8
+            .load_bytes(&[
+                0xD0, 0x01, // DRW V0, V0, 1
+                0x12, 0x02, // JP 0x202
+            ])
+            .unwrap();
+        let blank = chip8.screen_hash();
+        for _ in 0..3 {
+            chip8.step_frame().unwrap();
+        }
+        let drawn = chip8.screen_hash();
+        drop(chip8);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_ne!(blank, drawn);
+        assert_eq!(log, format!("{:016x}\n", drawn).repeat(3));
+    }
 }
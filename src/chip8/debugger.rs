@@ -0,0 +1,253 @@
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use crate::chip8::extension::OpcodePattern;
+use crate::chip8::opcodes;
+
+/// How many rows of 16 bytes `memory` shows at a time.
+pub const MEMORY_ROWS: usize = 8;
+
+/// What the debugger prompt understands, one command per line.
+pub const HELP: &str = "\
+Commands:
+  s, step [N]     run the next N instructions (1 if left out, or an empty line)
+  c, continue     run freely until F9 is pressed or a breakpoint is reached
+  b, break ADDR   pause before running the instruction at ADDR
+  break-op OP     pause before running any OP, a mnemonic such as `DRW` or
+                  an opcode pattern such as `FX0A`
+  bl, breakpoints list the breakpoints
+  d, delete [N]   delete breakpoint N, or all of them
+  r, registers    show PC, I, the timers and V0 to VF
+  k, stack        show the call stack around each return address
+  l, list         show the instructions around PC
+  m, memory [AT]  show RAM from AT (an address, `pc` or `i`), or carry on
+                  from the last view; PC is shown inverted and I underlined
+  q, quit         close the emulator
+  h, help         show this list";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Break(Breakpoint),
+    Breakpoints,
+    Delete(Option<usize>),
+    Registers,
+    Stack,
+    List,
+    Memory(Option<Address>),
+    Quit,
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = match words.next() {
+            None => return Ok(Command::Step(1)),
+            Some(command) => command,
+        };
+        let parsed = match command {
+            "s" | "step" => match words.next() {
+                Some(n) => n
+                    .parse()
+                    .map(Command::Step)
+                    .map_err(|_| format!("expected a number of steps, got `{}`", n))?,
+                None => Command::Step(1),
+            },
+            "c" | "continue" => Command::Continue,
+            "b" | "break" => match words.next().map(str::parse) {
+                Some(Ok(Address::At(addr))) => Command::Break(Breakpoint::At(addr)),
+                Some(Err(e)) => return Err(e),
+                _ => return Err("expected an address to break at".to_string()),
+            },
+            "break-op" => match words.next() {
+                Some(op) => Command::Break(Breakpoint::on_op(op)?),
+                None => return Err("expected a mnemonic or opcode pattern to break on".to_string()),
+            },
+            "bl" | "breakpoints" => Command::Breakpoints,
+            "d" | "delete" => match words.next() {
+                Some(n) => n
+                    .parse()
+                    .map(|n| Command::Delete(Some(n)))
+                    .map_err(|_| format!("expected a breakpoint number, got `{}`", n))?,
+                None => Command::Delete(None),
+            },
+            "r" | "registers" => Command::Registers,
+            "k" | "stack" => Command::Stack,
+            "l" | "list" => Command::List,
+            "m" | "memory" => Command::Memory(words.next().map(str::parse).transpose()?),
+            "q" | "quit" => Command::Quit,
+            "h" | "help" | "?" => Command::Help,
+            _ => return Err(format!("unknown command `{}`, try `help`", command)),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected `{}` after `{}`", extra, command)),
+            None => Ok(parsed),
+        }
+    }
+}
+
+/// The mnemonics `break-op` knows, as instructions are listed.
+const MNEMONICS: [&str; 25] = [
+    "SYS", "CLS", "RET", "SCD", "SCR", "SCL", "LOW", "HIGH", "JP", "CALL", "SE", "SNE", "ADD",
+    "SUB", "SUBN", "OR", "AND", "XOR", "SHR", "SHL", "RND", "DRW", "SKP", "SKNP", "LD",
+];
+
+/// Where the run stops and the debugger takes over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Breakpoint {
+    /// Before the instruction at this address.
+    At(usize),
+    /// Before any instruction listed with this mnemonic.
+    Mnemonic(&'static str),
+    /// Before any opcode matching this pattern.
+    Pattern(OpcodePattern),
+}
+
+impl Breakpoint {
+    fn on_op(op: &str) -> Result<Self, String> {
+        let upper = op.to_ascii_uppercase();
+        match MNEMONICS.iter().find(|&&mnemonic| mnemonic == upper) {
+            Some(mnemonic) => Ok(Breakpoint::Mnemonic(mnemonic)),
+            None => op.parse().map(Breakpoint::Pattern).map_err(|_| {
+                format!(
+                    "expected a mnemonic such as `DRW` or a pattern such as `DXYN`, got `{}`",
+                    op
+                )
+            }),
+        }
+    }
+
+    /// Whether to stop before running `opcode` at `pc`.
+    pub fn hits(&self, pc: usize, opcode: u16) -> bool {
+        match *self {
+            Breakpoint::At(addr) => pc == addr,
+            Breakpoint::Mnemonic(mnemonic) => opcodes::decode(opcode)
+                .is_ok_and(|instr| instr.to_string().split(' ').next() == Some(mnemonic)),
+            Breakpoint::Pattern(pattern) => pattern.matches(opcode),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::At(addr) => write!(f, "at 0x{:03X}", addr),
+            Breakpoint::Mnemonic(mnemonic) => write!(f, "on {}", mnemonic),
+            Breakpoint::Pattern(pattern) => write!(f, "on {}", pattern),
+        }
+    }
+}
+
+/// Where in RAM to look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Address {
+    At(usize),
+    Pc,
+    I,
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pc" => Ok(Address::Pc),
+            "i" => Ok(Address::I),
+            hex => usize::from_str_radix(hex.trim_start_matches("0x"), 16)
+                .map(Address::At)
+                .map_err(|_| format!("expected a hex address, `pc` or `i`, got `{}`", s)),
+        }
+    }
+}
+
+/// `MEMORY_ROWS` rows of `ram` from the row holding `start`, 16 bytes to a
+/// row, with the two bytes at `pc` in reverse video and the byte at `i`
+/// underlined.
+pub fn hexdump(ram: &[u8], start: usize, pc: usize, i: usize) -> String {
+    let mut out = String::new();
+    let first = start - start % 16;
+    for row in (first..ram.len()).step_by(16).take(MEMORY_ROWS) {
+        let _ = write!(out, "  0x{:03X} ", row);
+        for (at, byte) in ram.iter().enumerate().skip(row).take(16) {
+            let style = if at == pc || at == pc + 1 {
+                "\x1b[7m"
+            } else if at == i {
+                "\x1b[4m"
+            } else {
+                ""
+            };
+            let gap = if at % 8 == 0 { "  " } else { " " };
+            if style.is_empty() {
+                let _ = write!(out, "{}{:02X}", gap, byte);
+            } else {
+                let _ = write!(out, "{}{}{:02X}\x1b[0m", gap, style, byte);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        assert_eq!("".parse(), Ok(Command::Step(1)));
+        assert_eq!(" step 12 ".parse(), Ok(Command::Step(12)));
+        assert_eq!("c".parse(), Ok(Command::Continue));
+        assert_eq!("k".parse(), Ok(Command::Stack));
+        assert!("step many".parse::<Command>().is_err());
+        assert!("r now".parse::<Command>().is_err());
+        assert!("jump".parse::<Command>().is_err());
+        assert_eq!("m".parse(), Ok(Command::Memory(None)));
+        assert_eq!(
+            "memory 0x2A0".parse(),
+            Ok(Command::Memory(Some(Address::At(0x2A0))))
+        );
+        assert_eq!("m PC".parse(), Ok(Command::Memory(Some(Address::Pc))));
+        assert!("m here".parse::<Command>().is_err());
+        assert_eq!("d".parse(), Ok(Command::Delete(None)));
+        assert_eq!("delete 2".parse(), Ok(Command::Delete(Some(2))));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let at: Command = "break 0x2A4".parse().unwrap();
+        assert_eq!(at, Command::Break(Breakpoint::At(0x2A4)));
+        assert!("break pc".parse::<Command>().is_err());
+
+        let drw = match "break-op drw".parse() {
+            Ok(Command::Break(breakpoint)) => breakpoint,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(drw.to_string(), "on DRW");
+        assert!(drw.hits(0x200, 0xD015));
+        assert!(!drw.hits(0x200, 0x00E0));
+
+        let wait = match "break-op FX0A".parse() {
+            Ok(Command::Break(breakpoint)) => breakpoint,
+            other => panic!("{:?}", other),
+        };
+        assert!(wait.hits(0x300, 0xF30A));
+        assert!(!wait.hits(0x300, 0xF307));
+        assert!("break-op MOV".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let ram: Vec<u8> = (0..=255).collect();
+        let dump = hexdump(&ram, 0x25, 0x20, 0x31);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), MEMORY_ROWS);
+        assert!(lines[0].starts_with("  0x020   \x1b[7m20\x1b[0m \x1b[7m21\x1b[0m 22"));
+        assert!(lines[1].starts_with("  0x030   30 \x1b[4m31\x1b[0m 32"));
+        assert!(lines[0].contains(" 27  28 "));
+        assert_eq!(hexdump(&ram, 0xF5, 0, 0).lines().count(), 1);
+    }
+}
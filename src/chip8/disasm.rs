@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::chip8::opcodes::{self, Address, Instruction};
+
+/// Where ROMs are loaded, and where they start running.
+pub const START: Address = 0x200;
+
+/// A ROM taken apart by following the program from its start: what was
+/// reached is code, the rest is data. The targets of jumps, calls and `LD
+/// I` get labels, and calls make a call graph.
+///
+/// Code only reached through `JP V0`, whose target depends on V0, is not
+/// found and shows as data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disassembly {
+    rom: Vec<u8>,
+    /// The instructions reached, by address.
+    code: BTreeMap<Address, Instruction>,
+    labels: BTreeSet<Address>,
+    /// Per subroutine, by address, the subroutines it calls. The program's
+    /// start counts as one.
+    calls: BTreeMap<Address, BTreeSet<Address>>,
+}
+
+/// Disassemble `rom`, loaded at `START`.
+pub fn disassemble(rom: &[u8]) -> Disassembly {
+    let mut dis = Disassembly {
+        rom: rom.to_vec(),
+        code: BTreeMap::new(),
+        labels: BTreeSet::new(),
+        calls: BTreeMap::new(),
+    };
+    dis.label(START);
+    let mut routines = vec![START];
+    while let Some(entry) = routines.pop() {
+        if dis.calls.contains_key(&entry) {
+            continue;
+        }
+        let mut callees = BTreeSet::new();
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![entry];
+        while let Some(addr) = todo.pop() {
+            if !seen.insert(addr) {
+                continue;
+            }
+            let instruction = match dis.decode(addr) {
+                Some(instruction) => instruction,
+                None => continue,
+            };
+            dis.code.insert(addr, instruction);
+            // Past the end of the address space wraps to 0, outside the ROM.
+            let next = addr.wrapping_add(length(instruction));
+            match instruction {
+                Instruction::JP(target) => {
+                    dis.label(target);
+                    todo.push(target);
+                }
+                Instruction::CALL(target) => {
+                    dis.label(target);
+                    callees.insert(target);
+                    routines.push(target);
+                    todo.push(next);
+                }
+                Instruction::JP_V0(table) => {
+                    dis.label(table);
+                }
+                Instruction::RET => {}
+                Instruction::SE(..)
+                | Instruction::SNE(..)
+                | Instruction::SKP(_)
+                | Instruction::SKNP(_) => {
+                    todo.push(next);
+                    // A skip steps over the whole of a long `LD I`.
+                    let skipped = dis.word(next) == Some(0xF000);
+                    todo.push(next.wrapping_add(if skipped { 4 } else { 2 }));
+                }
+                Instruction::LD_I(target) => {
+                    dis.label(target);
+                    todo.push(next);
+                }
+                Instruction::LD_I_LONG => {
+                    if let Some(target) = dis.word(addr.wrapping_add(2)) {
+                        dis.label(target);
+                    }
+                    todo.push(next);
+                }
+                _ => todo.push(next),
+            }
+        }
+        dis.calls.insert(entry, callees);
+    }
+    dis
+}
+
+/// The bytes an instruction takes up.
+fn length(instruction: Instruction) -> Address {
+    match instruction {
+        Instruction::LD_I_LONG => 4,
+        _ => 2,
+    }
+}
+
+/// The name of the label at `addr`.
+pub fn label_name(addr: Address) -> String {
+    format!("L_{:04X}", addr)
+}
+
+impl Disassembly {
+    /// The 16 bit word at `addr`, if all of it is in the ROM.
+    fn word(&self, addr: Address) -> Option<u16> {
+        let at = usize::from(addr.checked_sub(START)?);
+        let bytes = self.rom.get(at..at + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// The instruction at `addr`, if it is in the ROM and decodes.
+    fn decode(&self, addr: Address) -> Option<Instruction> {
+        let instruction = opcodes::decode(self.word(addr)?).ok()?;
+        if instruction == Instruction::LD_I_LONG {
+            self.word(addr.wrapping_add(2))?;
+        }
+        Some(instruction)
+    }
+
+    fn in_rom(&self, addr: Address) -> bool {
+        addr >= START && usize::from(addr - START) < self.rom.len()
+    }
+
+    /// Give `addr` a label, if it is in the ROM.
+    fn label(&mut self, addr: Address) {
+        if self.in_rom(addr) {
+            self.labels.insert(addr);
+        }
+    }
+
+    /// Whether an instruction reached from the start begins at `addr`.
+    pub fn is_code(&self, addr: Address) -> bool {
+        self.code.contains_key(&addr)
+    }
+
+    /// The addresses with labels, lowest first.
+    pub fn labels(&self) -> impl Iterator<Item = Address> + '_ {
+        self.labels.iter().copied()
+    }
+
+    /// The subroutines called by the one at `addr`, lowest first, or `None`
+    /// if nothing calls it and it is not the start.
+    pub fn callees(&self, addr: Address) -> Option<impl Iterator<Item = Address> + '_> {
+        self.calls.get(&addr).map(|callees| callees.iter().copied())
+    }
+
+    /// An address as a label, if it has one.
+    fn target(&self, addr: Address, width: usize) -> String {
+        if self.labels.contains(&addr) {
+            label_name(addr)
+        } else {
+            format!("0x{:0width$X}", addr, width = width)
+        }
+    }
+
+    /// `instruction` at `addr`, with the addresses it uses as labels.
+    fn text(&self, addr: Address, instruction: Instruction) -> String {
+        match instruction {
+            Instruction::JP(target) => format!("JP {}", self.target(target, 3)),
+            Instruction::CALL(target) => format!("CALL {}", self.target(target, 3)),
+            Instruction::JP_V0(table) => format!("JP V0, {}", self.target(table, 3)),
+            Instruction::LD_I(target) => format!("LD I, {}", self.target(target, 3)),
+            Instruction::LD_I_LONG => match self.word(addr.wrapping_add(2)) {
+                Some(target) => format!("LD I, {}", self.target(target, 4)),
+                None => instruction.to_string(),
+            },
+            _ => instruction.to_string(),
+        }
+    }
+}
+
+/// The listing, labels on lines of their own and data as `db` lines of up
+/// to 8 bytes, followed by the call graph.
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = START as usize + self.rom.len();
+        let mut addr = START as usize;
+        while addr < end {
+            let at = addr as Address;
+            if self.labels.contains(&at) {
+                writeln!(f, "{}:", label_name(at))?;
+            }
+            if let Some(&instruction) = self.code.get(&at) {
+                let len = length(instruction) as usize;
+                let bytes = &self.rom[addr - START as usize..][..len];
+                let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                writeln!(
+                    f,
+                    "    0x{:03X}  {:<8}  {}",
+                    at,
+                    hex,
+                    self.text(at, instruction)
+                )?;
+                addr += len;
+                continue;
+            }
+            let mut run = addr + 1;
+            while run < end && run - addr < 8 {
+                let at = run as Address;
+                if self.code.contains_key(&at) || self.labels.contains(&at) {
+                    break;
+                }
+                run += 1;
+            }
+            let bytes: Vec<String> = self.rom[addr - START as usize..run - START as usize]
+                .iter()
+                .map(|b| format!("0x{:02X}", b))
+                .collect();
+            writeln!(f, "    0x{:03X}  db {}", at, bytes.join(", "))?;
+            addr = run;
+        }
+
+        write!(f, "\nCall graph:")?;
+        for (&routine, callees) in &self.calls {
+            write!(f, "\n    {}", label_name(routine))?;
+            if !callees.is_empty() {
+                let names: Vec<String> = callees.iter().map(|&c| label_name(c)).collect();
+                write!(f, " -> {}", names.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let rom = [
+            0x22, 0x0A, // 0x200: CALL 0x20A
+            0xA2, 0x10, // 0x202: LD I, 0x210
+            0x30, 0x00, // 0x204: SE V0, 0x00
+            0x12, 0x04, // 0x206: JP 0x204
+            0x12, 0x08, // 0x208: JP 0x208
+            0x22, 0x0E, // 0x20A: CALL 0x20E
+            0x00, 0xEE, // 0x20C: RET
+            0x00, 0xEE, // 0x20E: RET
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0x210: sprite
+        ];
+        let dis = disassemble(&rom);
+
+        let code: Vec<_> = (0x200..0x215).filter(|&a| dis.is_code(a)).collect();
+        assert_eq!(
+            code,
+            [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A, 0x20C, 0x20E]
+        );
+        let labels: Vec<_> = dis.labels().collect();
+        assert_eq!(labels, [0x200, 0x204, 0x208, 0x20A, 0x20E, 0x210]);
+        let callees = |addr| dis.callees(addr).map(|c| c.collect::<Vec<_>>());
+        assert_eq!(callees(0x200), Some(vec![0x20A]));
+        assert_eq!(callees(0x20A), Some(vec![0x20E]));
+        assert_eq!(callees(0x20E), Some(vec![]));
+        assert_eq!(callees(0x204), None);
+
+        assert_eq!(
+            dis.to_string(),
+            "\
+L_0200:
+    0x200  220A      CALL L_020A
+    0x202  A210      LD I, L_0210
+L_0204:
+    0x204  3000      SE V0, 0x00
+    0x206  1204      JP L_0204
+L_0208:
+    0x208  1208      JP L_0208
+L_020A:
+    0x20A  220E      CALL L_020E
+    0x20C  00EE      RET
+L_020E:
+    0x20E  00EE      RET
+L_0210:
+    0x210  db 0xF0, 0x90, 0x90, 0x90, 0xF0
+
+Call graph:
+    L_0200 -> L_020A
+    L_020A -> L_020E
+    L_020E"
+        );
+    }
+
+    #[test]
+    fn test_data_after_jump() {
+        let rom = [
+            0xF0, 0x00, 0x02, 0x08, // 0x200: LD I, long 0x208
+            0xB2, 0x0A, // 0x204: JP V0, 0x20A
+            0x60, 0x01, // 0x206: never reached
+            0xAA, 0x12, // 0x208: data
+            0x00, 0x00, // 0x20A: a jump table, not followed
+        ];
+        let dis = disassemble(&rom);
+        assert!(dis.is_code(0x200) && dis.is_code(0x204));
+        assert!(!dis.is_code(0x206) && !dis.is_code(0x20A));
+        assert_eq!(
+            dis.to_string(),
+            "\
+L_0200:
+    0x200  F0000208  LD I, L_0208
+    0x204  B20A      JP V0, L_020A
+    0x206  db 0x60, 0x01
+L_0208:
+    0x208  db 0xAA, 0x12
+L_020A:
+    0x20A  db 0x00, 0x00
+
+Call graph:
+    L_0200"
+        );
+    }
+}
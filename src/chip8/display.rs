@@ -1,115 +1,552 @@
-use std::sync::{Arc, RwLock};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use minifb::{Key, Scale, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 
-pub const WIDTH: usize = 64;
-pub const HEIGHT: usize = 32;
+use crate::chip8::dpi;
+use crate::chip8::framebuffer::FrameBuffer;
+use crate::chip8::frametime::FrameTimes;
+use crate::chip8::frontend::{Frontend, KeypadState};
+use crate::chip8::keymap::Keymap;
+use crate::chip8::latency::InputLatency;
+use crate::chip8::overlay::{self, TOAST_DURATION};
+use crate::chip8::phosphor::Phosphor;
+use crate::chip8::shm::SharedFramebuffer;
+use crate::chip8::stats::Stats;
+use crate::chip8::triple::{triple_buffer, Publisher};
 
-type Buffer = [u32; WIDTH * HEIGHT];
+pub use crate::chip8::framebuffer::{
+    hash_lit, resolution, DEFAULT_BG, DEFAULT_FG, HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH,
+};
 
-// to do :
-// update buffer is super slow. maybe only send buffer update every few hz? -> set fps
+type Buffer = Vec<u32>;
+
+/// The largest window scale, in screen pixels per CHIP-8 pixel.
+pub const MAX_SCALE: usize = 32;
+
+/// The window's title until a ROM names it.
+const DEFAULT_TITLE: &str = "rust-chip-8";
+
+/// How the window shows that the buzzer is sounding, for playing muted or
+/// hard of hearing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundIndicator {
+    /// A speaker in the top right corner.
+    Icon,
+    /// A border around the screen.
+    Border,
+}
+
+impl fmt::Display for SoundIndicator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SoundIndicator::Icon => write!(f, "icon"),
+            SoundIndicator::Border => write!(f, "border"),
+        }
+    }
+}
+
+impl FromStr for SoundIndicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "icon" => Ok(SoundIndicator::Icon),
+            "border" => Ok(SoundIndicator::Border),
+            _ => Err(format!("expected `icon` or `border`, got `{}`", s)),
+        }
+    }
+}
+
+/// Requests from the CPU side to the window thread.
+enum WindowCommand {
+    /// Name the game in the title bar.
+    SetTitle(String),
+    /// Show this beside the name in the title bar.
+    SetStatus(String),
+    /// Present this instead of the screen, or the screen again on `None`.
+    SetOverlay(Option<Vec<u32>>),
+    /// Show a notification over the bottom of the screen for a moment.
+    Toast { text: String, fg: u32, bg: u32 },
+    /// Show the frames and instructions a second in the top left corner, or
+    /// stop.
+    ShowSpeed(bool),
+    /// Fade pixels that go out over this many frames, or at once on 0.
+    SetFade(u8),
+    /// Show when the buzzer sounds like this, or not at all.
+    ShowSound(Option<SoundIndicator>),
+    /// Reopen the window at this many screen pixels per CHIP-8 pixel.
+    SetScale(usize),
+}
 
 pub struct Display {
-    screen: Arc<RwLock<Buffer>>,
-    buffer: Buffer,
-    pub handle: JoinHandle<()>,
-    keys_pressed: Arc<RwLock<Vec<Key>>>,
+    screen: Publisher<Buffer>,
+    handle: Option<JoinHandle<()>>,
+    headless: bool,
+    closing: Arc<AtomicBool>,
+    /// Keys going down (`true`) and up on the window, in the order they did.
+    key_events: Receiver<(Key, bool)>,
+    /// The keys held as of the last poll.
+    keys_held: Vec<Key>,
+    /// The keys down as of the last poll, held or tapped since the one
+    /// before.
+    keys_down: Vec<Key>,
+    keymap: Keymap,
+    latency: Arc<InputLatency>,
+    frame_times: Arc<FrameTimes>,
+    export: Option<SharedFramebuffer>,
+    commands: Sender<WindowCommand>,
+    /// The screen's colors, lit in the high half and unlit in the low.
+    colors: Arc<AtomicU64>,
+    scale: usize,
+    /// Whether the program is sounding the buzzer.
+    beeping: Arc<AtomicBool>,
+    /// Whether the screen was drawn since it was last presented.
+    drawn: bool,
+    /// Whether the window has the keyboard focus.
+    focused: Arc<AtomicBool>,
 }
 
 impl Display {
-    pub fn update_buffer(&self) {
-        // TODO: add dynamic sleep to get consistent fps, and buffer key inputs.
-        // consider using Mutex instead of RwLock
-        thread::sleep(Duration::from_micros(1));
-        *self.screen.write().unwrap() = self.buffer;
+    /// Read the keypad from the keys in `keymap`.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Mirror every frame presented into `export`.
+    pub fn set_export(&mut self, export: SharedFramebuffer) {
+        self.export = Some(export);
     }
 
-    pub fn init() -> Self {
-        let screen = Arc::new(RwLock::new([0; WIDTH * HEIGHT]));
-        let screen_lock = screen.clone();
-        let buffer = [0; WIDTH * HEIGHT];
+    /// Show `overlay` in place of the screen until it is cleared with `None`.
+    pub fn set_overlay(&self, overlay: Option<Vec<u32>>) {
+        let _ = self.commands.send(WindowCommand::SetOverlay(overlay));
+    }
+
+    /// Flash `text` over the bottom of the screen for a second, in `fg` on
+    /// `bg`.
+    pub fn toast(&self, text: &str, fg: u32, bg: u32) {
+        let _ = self.commands.send(WindowCommand::Toast {
+            text: text.to_string(),
+            fg,
+            bg,
+        });
+    }
 
-        let keys_pressed = Arc::new(RwLock::new(vec![]));
-        let key_buffer = keys_pressed.clone();
+    /// Show the frames presented and instructions run in the last second in
+    /// the top left corner, or stop.
+    pub fn show_speed(&self, show: bool) {
+        let _ = self.commands.send(WindowCommand::ShowSpeed(show));
+    }
+
+    /// How many screen pixels wide each pixel of the 64x32 screen is drawn.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// Draw each pixel of the 64x32 screen `scale` screen pixels wide, from
+    /// 1 to `MAX_SCALE`, reopening the window at its new size.
+    pub fn set_scale(&mut self, scale: usize) {
+        let scale = scale.clamp(1, MAX_SCALE);
+        if scale != self.scale {
+            self.scale = scale;
+            let _ = self.commands.send(WindowCommand::SetScale(scale));
+        }
+    }
+
+    /// Let pixels that go out fade over `frames` frames in the window,
+    /// rather than at once, to hide flicker. 0 turns fading off.
+    pub fn set_fade(&self, frames: u8) {
+        let _ = self.commands.send(WindowCommand::SetFade(frames));
+    }
+
+    /// Show in the window when the buzzer sounds, as `indicator` says, or
+    /// stop on `None`.
+    pub fn show_sound(&self, indicator: Option<SoundIndicator>) {
+        let _ = self.commands.send(WindowCommand::ShowSound(indicator));
+    }
+
+    /// Close the window.
+    pub fn close(&self) {
+        self.closing.store(true, Ordering::Relaxed);
+    }
+
+    pub fn init(stats: Arc<Stats>) -> Self {
+        let buffer = vec![DEFAULT_BG; WIDTH * HEIGHT];
+        let (screen, mut latest_screen) = triple_buffer(buffer);
+        let colors = Arc::new(AtomicU64::new(pack_colors(DEFAULT_FG, DEFAULT_BG)));
+        let screen_colors = colors.clone();
+
+        let (key_sender, key_events) = mpsc::channel();
+        let latency = Arc::new(InputLatency::new(stats.clone()));
+        let key_latency = latency.clone();
+        let frame_times = Arc::new(FrameTimes::default());
+        let frame_timing = frame_times.clone();
+
+        let (commands, command_queue) = mpsc::channel();
+
+        let closing = Arc::new(AtomicBool::new(false));
+        let close_requested = closing.clone();
+        let beeping = Arc::new(AtomicBool::new(false));
+        let sounding = beeping.clone();
+        let focused = Arc::new(AtomicBool::new(true));
+        let has_focus = focused.clone();
+
+        let scale = dpi::window_scale(dpi::scale_factor());
 
         let handle = thread::spawn(move || {
-            let mut opts = WindowOptions::default();
-            opts.scale = Scale::X16;
+            // The 64x32 screen at `scale`. Frames of other sizes, such as the
+            // SUPER-CHIP's 128x64, are stretched to fit.
+            let open = |title: &str, scale: usize| {
+                let size = (WIDTH * scale, HEIGHT * scale);
+                let mut window =
+                    Window::new(title, size.0, size.1, WindowOptions::default()).unwrap();
+                window.limit_update_rate(Some(Duration::from_micros(16600)));
+                window
+            };
 
-            let mut window = Window::new("Test - ESC to exit", WIDTH, HEIGHT, opts).unwrap();
+            let mut name = DEFAULT_TITLE.to_string();
+            let mut status = String::new();
+            let mut title = name.clone();
+            let mut window = open(&title, scale);
+            // When the frame rate in the title was last measured, the frame
+            // count then, and the rate.
+            let mut fps: (Instant, u64, Option<u64>) = (Instant::now(), 0, None);
 
-            window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+            let mut last_frame: Option<Instant> = None;
+            let mut overlay: Option<Vec<u32>> = None;
+            let mut toast: Option<(String, u32, u32, Instant)> = None;
+            let mut composed = Vec::with_capacity(HIRES_WIDTH * HIRES_HEIGHT);
+            // When the speed was last measured, the frame and instruction
+            // counts then, and the label made from them.
+            let mut speed: Option<(Instant, u64, u64, String)> = None;
+            let mut phosphor: Option<Phosphor> = None;
+            let mut sound_indicator: Option<SoundIndicator> = None;
+
+            while window.is_open()
+                && !window.is_key_down(Key::Escape)
+                && !close_requested.load(Ordering::Relaxed)
+            {
+                for command in command_queue.try_iter() {
+                    match command {
+                        WindowCommand::SetTitle(game) => name = game,
+                        WindowCommand::SetStatus(running) => status = running,
+                        WindowCommand::SetOverlay(buffer) => overlay = buffer,
+                        WindowCommand::Toast { text, fg, bg } => {
+                            toast = Some((text, fg, bg, Instant::now()))
+                        }
+                        WindowCommand::SetScale(scale) => window = open(&title, scale),
+                        WindowCommand::SetFade(frames) => {
+                            phosphor = (frames > 0).then(|| Phosphor::new(frames))
+                        }
+                        WindowCommand::ShowSound(indicator) => sound_indicator = indicator,
+                        WindowCommand::ShowSpeed(show) => {
+                            speed = show.then(|| {
+                                (
+                                    Instant::now(),
+                                    stats.frames(),
+                                    stats.instructions(),
+                                    String::new(),
+                                )
+                            })
+                        }
+                    }
+                }
 
-            while window.is_open() && !window.is_key_down(Key::Escape) {
-                match screen_lock.try_read() {
-                    Ok(gaurd) => window.update_with_buffer(&*gaurd, WIDTH, HEIGHT).unwrap(),
-                    Err(_) => window.update(),
+                let start = Instant::now();
+                let latest = latest_screen.latest();
+                let colors = screen_colors.load(Ordering::Relaxed);
+                let mut frame = match (&overlay, &mut phosphor) {
+                    (Some(overlay), _) => overlay,
+                    (None, Some(phosphor)) => {
+                        let (fg, bg) = unpack_colors(colors);
+                        phosphor.fade(latest, fg, bg)
+                    }
+                    (None, None) => latest,
                 };
+                toast = toast.filter(|(.., shown)| shown.elapsed() < TOAST_DURATION);
+                if let Some((since, frames, instructions, label)) = &mut speed {
+                    if since.elapsed() >= Duration::from_secs(1) {
+                        let per_second = |count: u64| {
+                            (count as f64 / since.elapsed().as_secs_f64()).round() as u64
+                        };
+                        *label = overlay::speed_label(
+                            per_second(stats.frames() - *frames),
+                            per_second(stats.instructions() - *instructions),
+                        );
+                        *since = Instant::now();
+                        *frames = stats.frames();
+                        *instructions = stats.instructions();
+                    }
+                }
+                let sound = sound_indicator.filter(|_| sounding.load(Ordering::Relaxed));
+                if toast.is_some() || speed.is_some() || sound.is_some() {
+                    composed.clear();
+                    composed.extend_from_slice(frame);
+                    let (fg, bg) = unpack_colors(colors);
+                    match sound {
+                        Some(SoundIndicator::Icon) => overlay::draw_speaker(&mut composed, fg, bg),
+                        Some(SoundIndicator::Border) => overlay::draw_border(&mut composed, fg),
+                        None => {}
+                    }
+                    match &speed {
+                        Some((.., label)) if !label.is_empty() => {
+                            overlay::draw_label(&mut composed, label, fg, bg);
+                        }
+                        _ => {}
+                    }
+                    if let Some((text, fg, bg, _)) = &toast {
+                        overlay::draw_toast(&mut composed, text, *fg, *bg);
+                    }
+                    frame = &composed;
+                }
+                let (width, height) = resolution(frame);
+                window.update_with_buffer(frame, width, height).unwrap();
+                stats.count_frame();
+                if let Some(last) = last_frame {
+                    frame_timing.record(start - last, start.elapsed());
+                }
+                last_frame = Some(start);
 
-                if let Some(keys) = window.get_keys() {
-                    *keys_pressed.write().unwrap() = keys.clone();
+                let (since, frames, rate) = &mut fps;
+                if since.elapsed() >= Duration::from_secs(1) {
+                    let counted = stats.frames() - *frames;
+                    *rate = Some((counted as f64 / since.elapsed().as_secs_f64()).round() as u64);
+                    *since = Instant::now();
+                    *frames = stats.frames();
+                }
+                let retitled = window_title(&name, &status, *rate);
+                if retitled != title {
+                    window.set_title(&retitled);
+                    title = retitled;
                 }
 
-                // Allow the buffer to be updated
-                thread::sleep(Duration::from_micros(1));
+                has_focus.store(window.is_active(), Ordering::Relaxed);
+                // The window's own presses and releases, rather than what is
+                // held now, so a key let go again before the CPU polls is
+                // still seen going down.
+                for key in window.get_keys_pressed(KeyRepeat::No).unwrap_or_default() {
+                    key_latency.pressed(key);
+                    let _ = key_sender.send((key, true));
+                }
+                for key in window.get_keys_released().unwrap_or_default() {
+                    let _ = key_sender.send((key, false));
+                }
             }
         });
 
         Display {
             screen,
-            buffer,
-            handle,
-            keys_pressed: key_buffer,
+            handle: Some(handle),
+            headless: false,
+            closing,
+            key_events,
+            keys_held: Vec::new(),
+            keys_down: Vec::new(),
+            keymap: Keymap::default(),
+            latency,
+            frame_times,
+            export: None,
+            commands,
+            colors,
+            scale,
+            beeping,
+            drawn: false,
+            focused,
+        }
+    }
+
+    /// A display without a window, for running ROMs from tests and tools. No
+    /// keys are ever pressed.
+    pub fn headless() -> Self {
+        let buffer = vec![DEFAULT_BG; WIDTH * HEIGHT];
+        let (commands, _) = mpsc::channel();
+        Display {
+            screen: triple_buffer(buffer).0,
+            handle: None,
+            headless: true,
+            closing: Arc::new(AtomicBool::new(false)),
+            key_events: mpsc::channel().1,
+            keys_held: Vec::new(),
+            keys_down: Vec::new(),
+            keymap: Keymap::default(),
+            latency: Arc::new(InputLatency::new(Arc::new(Stats::default()))),
+            frame_times: Arc::new(FrameTimes::default()),
+            export: None,
+            commands,
+            colors: Arc::new(AtomicU64::new(pack_colors(DEFAULT_FG, DEFAULT_BG))),
+            scale: dpi::window_scale(dpi::scale_factor()),
+            beeping: Arc::new(AtomicBool::new(false)),
+            drawn: false,
+            focused: Arc::new(AtomicBool::new(true)),
         }
     }
 
+    pub fn is_headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Whether the window is still open. A headless display is always open.
     pub fn is_window_open(&self) -> bool {
-        !self.handle.is_finished()
+        if self.headless {
+            return true;
+        }
+
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
     }
 
-    pub fn get_key_down(&self) -> Option<Key> {
-        self.keys_pressed.read().unwrap().get(0).map(Key::clone)
+    /// Whether the window has the keyboard focus. A headless display always
+    /// has it.
+    pub fn is_focused(&self) -> bool {
+        self.focused.load(Ordering::Relaxed)
     }
 
-    pub fn is_key_down(&self, key: Key) -> bool {
-        self.keys_pressed.read().unwrap().contains(&key)
+    /// Take the key presses and releases since the last poll, and give the
+    /// keys down on the keyboard since, whether or not they are on the
+    /// keypad: those held now, and those tapped in between, so a tap between
+    /// polls is seen once. The keypad reads these keys until the next poll.
+    pub fn poll_keys(&mut self) -> Vec<Key> {
+        self.keys_down = apply_key_events(&mut self.keys_held, self.key_events.try_iter());
+        self.keys_down.clone()
     }
 
-    pub fn clear(&mut self) {
-        self.buffer = [0; WIDTH * HEIGHT];
+    pub fn input_latency(&self) -> Arc<InputLatency> {
+        self.latency.clone()
     }
 
-    fn to_index(x: usize, y: usize) -> usize {
-        let y = y % HEIGHT;
-        let x = x % WIDTH;
-        WIDTH * y + x
+    pub fn frame_times(&self) -> Arc<FrameTimes> {
+        self.frame_times.clone()
     }
+}
 
-    pub fn set_pixels(&mut self, x: u8, y: u8, bytes: &[u8]) -> bool {
-        let mut collision = false;
-        let num_bytes = bytes.len();
-        let slice = &mut self.buffer;
+/// Apply key `events` to the keys `held`, giving the keys held after them
+/// along with any pressed along the way.
+fn apply_key_events(
+    held: &mut Vec<Key>,
+    events: impl IntoIterator<Item = (Key, bool)>,
+) -> Vec<Key> {
+    let mut tapped = Vec::new();
+    for (key, down) in events {
+        if !down {
+            held.retain(|&k| k != key);
+        } else if !held.contains(&key) {
+            held.push(key);
+            tapped.push(key);
+        }
+    }
+    let mut keys = held.clone();
+    keys.extend(tapped.into_iter().filter(|key| !held.contains(key)));
+    keys
+}
 
-        for j in 0..num_bytes {
-            // For every bit in byte, check if 1
-            for i in 0..8 {
-                let filter: u8 = 0b10000000 >> i;
-                if bytes[j] & filter == filter {
-                    // If so, XOR with buffer value, and track collision
-                    let index = Display::to_index(x as usize + i, y as usize + j); // % (WIDTH * HEIGHT);
-                    if slice[index] == u32::MAX {
-                        collision = true;
-                        slice[index] = 0;
-                    } else {
-                        slice[index] = u32::MAX;
-                    }
-                }
+fn pack_colors(fg: u32, bg: u32) -> u64 {
+    (u64::from(fg) << 32) | u64::from(bg)
+}
+
+fn unpack_colors(colors: u64) -> (u32, u32) {
+    ((colors >> 32) as u32, colors as u32)
+}
+
+/// "name - status - 60 FPS", leaving out whichever is unknown.
+fn window_title(name: &str, status: &str, fps: Option<u64>) -> String {
+    let mut title = name.to_string();
+    if !status.is_empty() {
+        title = format!("{} - {}", title, status);
+    }
+    if let Some(fps) = fps {
+        title = format!("{} - {} FPS", title, fps);
+    }
+    title
+}
+
+impl Frontend for Display {
+    fn draw(&mut self, fb: &FrameBuffer) {
+        let screen = self.screen.back();
+        screen.clear();
+        screen.extend(fb.pixels());
+        self.drawn = true;
+        let (fg, bg) = fb.colors();
+        self.colors.store(pack_colors(fg, bg), Ordering::Relaxed);
+    }
+
+    /// Hand the window, and any export, the screen drawn this frame, so
+    /// neither ever shows one half drawn.
+    fn present(&mut self) {
+        if self.drawn {
+            if let Some(export) = &mut self.export {
+                export.publish(self.screen.back());
             }
+            self.screen.publish();
+            self.drawn = false;
+        }
+    }
+
+    fn keys(&self) -> KeypadState {
+        self.keys_down
+            .iter()
+            .filter_map(|&key| self.keymap.keypad_key(key))
+            .fold(KeypadState::default(), KeypadState::with)
+    }
+
+    /// The window has no sound of its own, but can show it.
+    fn beep(&mut self, on: bool) {
+        self.beeping.store(on, Ordering::Relaxed);
+    }
+
+    fn set_title(&self, title: &str) {
+        let _ = self
+            .commands
+            .send(WindowCommand::SetTitle(title.to_string()));
+    }
+
+    fn set_status(&self, status: &str) {
+        let _ = self
+            .commands
+            .send(WindowCommand::SetStatus(status.to_string()));
+    }
+
+    fn key_observed(&self, key: u8) {
+        if let Some(key) = self.keymap.key(key) {
+            self.latency.observed(key);
+        }
+    }
+}
+
+impl Drop for Display {
+    /// Close the window and wait for its thread to exit.
+    fn drop(&mut self) {
+        self.closing.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_key_events() {
+        let mut held = vec![Key::X];
+        let keys = apply_key_events(&mut held, [(Key::A, true), (Key::A, false), (Key::W, true)]);
+        // A was tapped between polls, so it counts as down this once.
+        assert_eq!(keys, [Key::X, Key::W, Key::A]);
+        assert_eq!(held, [Key::X, Key::W]);
 
-        collision
+        let keys = apply_key_events(&mut held, [(Key::X, false)]);
+        assert_eq!(keys, [Key::W]);
+        assert_eq!(apply_key_events(&mut held, []), [Key::W]);
     }
 }
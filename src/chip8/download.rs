@@ -0,0 +1,67 @@
+use std::io;
+#[cfg(feature = "download")]
+use std::io::Read;
+
+/// The largest download accepted. XO-CHIP programs can fill 64K of RAM; no
+/// ROM is bigger than that.
+#[cfg(feature = "download")]
+const MAX_ROM_SIZE: u64 = 0x10000;
+
+pub fn is_url(filename: &str) -> bool {
+    filename.starts_with("http://") || filename.starts_with("https://")
+}
+
+/// The file name at the end of `url`, to show in the window title.
+pub fn rom_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(url)
+}
+
+#[cfg(feature = "download")]
+pub fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+
+    let mut rom = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_ROM_SIZE + 1)
+        .read_to_end(&mut rom)?;
+    if rom.len() as u64 > MAX_ROM_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("larger than {MAX_ROM_SIZE} bytes, not a ROM"),
+        ));
+    }
+    Ok(rom)
+}
+
+#[cfg(not(feature = "download"))]
+pub fn fetch(_url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "loading ROMs from URLs requires building with the `download` feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rom_name() {
+        assert!(is_url(
+            "https://johnearnest.github.io/chip8Archive/roms/br8kout.ch8"
+        ));
+        assert!(!is_url("roms/br8kout.ch8"));
+        assert_eq!(
+            rom_name("https://johnearnest.github.io/chip8Archive/roms/br8kout.ch8"),
+            "br8kout.ch8"
+        );
+        assert_eq!(rom_name("http://example.com/pong.ch8?raw=1"), "pong.ch8");
+        assert_eq!(rom_name("http://example.com/"), "example.com");
+    }
+}
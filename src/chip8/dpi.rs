@@ -0,0 +1,41 @@
+use std::env;
+
+/// Scale of a 1x (96 DPI) display, which makes the window 1024x512.
+const BASE_SCALE: f64 = 16.0;
+
+/// The desktop's scale factor, as exported to applications by GTK, Qt and
+/// winit on Linux. Only these variables are read, not the display's DPI,
+/// which minifb gives no way to ask for; 1.0 when none is set.
+pub fn scale_factor() -> f64 {
+    ["GDK_SCALE", "QT_SCALE_FACTOR", "WINIT_X11_SCALE_FACTOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok()?.parse::<f64>().ok())
+        .find(|factor| *factor > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// The largest power of two that does not go over the base scale at
+/// `factor`: how many screen pixels wide each CHIP-8 pixel is drawn. Scales
+/// are whole, so pixel edges stay crisp.
+pub fn window_scale(factor: f64) -> usize {
+    let scale = BASE_SCALE * factor;
+    [32, 16, 8, 4, 2]
+        .iter()
+        .copied()
+        .find(|&at_least| scale >= at_least as f64)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_scale() {
+        assert_eq!(window_scale(1.0), 16);
+        assert_eq!(window_scale(1.5), 16);
+        assert_eq!(window_scale(2.0), 32);
+        assert_eq!(window_scale(3.0), 32);
+        assert_eq!(window_scale(0.5), 8);
+    }
+}
@@ -0,0 +1,268 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Why the instruction at `at` could not run, usually a bug in the ROM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chip8Error {
+    /// The opcode is not one the interpreter or its extensions know.
+    UnknownOpcode { at: usize, opcode: u16 },
+    /// RET with no CALL to return to.
+    StackUnderflow { at: usize },
+    /// CALL nested deeper than the stack limit allows.
+    StackOverflow { at: usize, limit: usize },
+    /// PC is past the last whole opcode in RAM.
+    RanOffEnd { at: usize },
+    /// A read or write reached `addr`, past the end of RAM.
+    OutOfMemory { at: usize, addr: usize },
+    /// A write reached `addr`, in a region mapped read-only.
+    ReadOnly { at: usize, addr: usize },
+    /// SKP or SKNP asked about a key that is not on the keypad.
+    InvalidKey { at: usize, key: u8 },
+    /// LD F asked for the glyph of a value that is not a hex digit.
+    InvalidDigit { at: usize, digit: u8 },
+}
+
+impl Chip8Error {
+    /// The address of the instruction that failed.
+    pub fn at(&self) -> usize {
+        match *self {
+            Chip8Error::UnknownOpcode { at, .. }
+            | Chip8Error::StackUnderflow { at }
+            | Chip8Error::StackOverflow { at, .. }
+            | Chip8Error::RanOffEnd { at }
+            | Chip8Error::OutOfMemory { at, .. }
+            | Chip8Error::ReadOnly { at, .. }
+            | Chip8Error::InvalidKey { at, .. }
+            | Chip8Error::InvalidDigit { at, .. } => at,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { opcode, .. } => write!(f, "unknown opcode {:04X}", opcode),
+            Chip8Error::StackUnderflow { .. } => write!(f, "RET with an empty stack"),
+            Chip8Error::StackOverflow { limit, .. } => {
+                write!(f, "stack overflow: more than {} nested calls", limit)
+            }
+            Chip8Error::RanOffEnd { .. } => write!(f, "PC ran off the end of RAM"),
+            Chip8Error::OutOfMemory { addr, .. } => {
+                write!(f, "memory access at 0x{:X}, past the end of RAM", addr)
+            }
+            Chip8Error::ReadOnly { addr, .. } => {
+                write!(f, "write to read-only memory at 0x{:X}", addr)
+            }
+            Chip8Error::InvalidKey { key, .. } => write!(f, "no key 0x{:X} on the keypad", key),
+            Chip8Error::InvalidDigit { digit, .. } => {
+                write!(f, "no font glyph for 0x{:X}, only for hex digits", digit)
+            }
+        }?;
+        write!(f, " at 0x{:03X}", self.at())
+    }
+}
+
+impl Error for Chip8Error {}
+
+/// A ROM of `size` bytes that does not fit in the `room` from 0x200 to the
+/// end of RAM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomTooLarge {
+    pub size: usize,
+    pub room: usize,
+}
+
+impl fmt::Display for RomTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the ROM is {} bytes, but only {} fit in RAM",
+            self.size, self.room
+        )
+    }
+}
+
+impl Error for RomTooLarge {}
+
+impl From<RomTooLarge> for io::Error {
+    fn from(e: RomTooLarge) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::frontend::NullFrontend;
+    use crate::chip8::memory::ReadOnly;
+    use crate::chip8::registers::Registers;
+    use crate::chip8::{OnHalt, OnUnknown, PcOverflow, CHIP8, MAX_RAM_SIZE, MIN_RAM_SIZE};
+
+    fn run(rom: &[u8]) -> Result<(), Chip8Error> {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.load_bytes(rom).unwrap();
+        (0..rom.len() / 2).try_for_each(|_| chip8.step().map(drop))
+    }
+
+    #[test]
+    fn test_bad_roms() {
+        assert_eq!(
+            run(&[0x00, 0xEE]),
+            Err(Chip8Error::StackUnderflow { at: 0x200 })
+        );
+        assert_eq!(
+            run(&[0x60, 0x00, 0xE0, 0x00]),
+            Err(Chip8Error::UnknownOpcode {
+                at: 0x202,
+                opcode: 0xE000
+            })
+        );
+        // LD I, 0xFFC then DRW V0, V0, 5 reads past the last address.
+        assert_eq!(
+            run(&[0xAF, 0xFC, 0xD0, 0x05]),
+            Err(Chip8Error::OutOfMemory {
+                at: 0x202,
+                addr: 0x1000
+            })
+        );
+        let e = run(&[0x60, 0x10, 0xE0, 0x9E]).unwrap_err();
+        assert_eq!(e.to_string(), "no key 0x10 on the keypad at 0x202");
+    }
+
+    #[test]
+    fn test_read_only_region() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.map_region(0x000..0x050, ReadOnly(vec![0; 0x50]));
+        // LD I, 0x04E then LD [I], V1 writes 0x04E and 0x04F.
+        chip8.load_bytes(&[0xA0, 0x4E, 0xF1, 0x55]).unwrap();
+        chip8.step().unwrap();
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::ReadOnly {
+                at: 0x202,
+                addr: 0x04E
+            })
+        );
+    }
+
+    #[test]
+    fn test_pc_overflow() {
+        let run = |overflow| {
+            let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+            chip8.set_pc_overflow(overflow);
+            let mut state = chip8.state();
+            let end = state.ram.len();
+            // ADD V0, 1 in the last four bytes and the first two.
+            for at in [end - 4, end - 2, 0] {
+                state.ram[at..at + 2].copy_from_slice(&[0x70, 0x01]);
+            }
+            state.pc = end - 4;
+            chip8.load_state(&state);
+            chip8.step().unwrap();
+            chip8.step().unwrap();
+            (end, chip8.has_halted(), chip8.step())
+        };
+
+        let (end, halted, _) = run(PcOverflow::Stop);
+        assert!(halted);
+        let (_, halted, result) = run(PcOverflow::Error);
+        assert!(!halted);
+        assert_eq!(result, Err(Chip8Error::RanOffEnd { at: end }));
+        let (_, halted, result) = run(PcOverflow::Wrap);
+        assert!(!halted);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_on_halt() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8
+            .load_bytes(&[
+                0x60, 0x00, // LD V0, 0
+                0xF0, 0x29, // LD F, V0
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x12, 0x06, // JP 0x206
+            ])
+            .unwrap();
+        chip8.step_frame().unwrap();
+        assert!(chip8.is_stuck());
+        assert!(!chip8.is_done(), "spins by default");
+        chip8.set_on_halt(OnHalt::Print);
+        assert!(chip8.is_done());
+        let screen = chip8.screen_text();
+        assert_eq!(screen.lines().count(), 32);
+        assert!(screen.starts_with(&format!("####{}\n#..#.", ".".repeat(60))));
+
+        chip8.reset();
+        assert!(!chip8.is_stuck() && !chip8.is_done());
+        assert_eq!("overlay".parse(), Ok(OnHalt::Overlay));
+        assert!("stop".parse::<OnHalt>().is_err());
+    }
+
+    #[test]
+    fn test_skip_unknown_opcodes() {
+        for on_unknown in [OnUnknown::Skip, OnUnknown::Warn] {
+            let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+            chip8.set_on_unknown(on_unknown);
+            chip8
+                .load_bytes(&[
+                    0xE0, 0x00, // unknown
+                    0x60, 0x07, // LD V0, 7
+                ])
+                .unwrap();
+            assert_eq!(chip8.step(), Ok(None));
+            assert!(chip8.step().is_ok());
+            assert_eq!(chip8.registers().Vx[0], 7);
+        }
+    }
+
+    #[test]
+    fn test_ram_size() {
+        // LD I, 0xFFC then DRW V0, V0, 4 reads up to the last byte of 4K.
+        assert_eq!(run(&[0xAF, 0xFC, 0xD0, 0x04]), Ok(()));
+
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.set_ram_size(MAX_RAM_SIZE);
+        chip8.load_bytes(&[0xAF, 0xFC, 0xD0, 0x05]).unwrap();
+        chip8.step().unwrap();
+        assert!(chip8.step().is_ok());
+        assert_eq!(chip8.state().ram.len(), MAX_RAM_SIZE);
+    }
+
+    #[test]
+    fn test_max_stack_depth() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8
+            .load_bytes(&[
+                0x22, 0x04, // CALL 0x204
+                0x12, 0x02, // JP 0x202
+                0x22, 0x08, // CALL 0x208
+                0x00, 0xEE, // RET
+                0x00, 0xEE, // RET
+            ])
+            .unwrap();
+        for _ in 0..6 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(chip8.max_stack_depth(), 2);
+        assert!(chip8
+            .stack_trace()
+            .ends_with("0 nested calls, at most 2 so far, limit 16\n"));
+        chip8.reset();
+        assert_eq!(chip8.max_stack_depth(), 0);
+    }
+
+    #[test]
+    fn test_rom_too_large() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.set_ram_size(0x100);
+        assert_eq!(chip8.state().ram.len(), MIN_RAM_SIZE);
+        assert_eq!(chip8.load_bytes(&[0x12, 0x00]), Ok(()));
+        assert_eq!(
+            chip8.load_bytes(&[0x00, 0xE0, 0x12, 0x00]),
+            Err(RomTooLarge { size: 4, room: 2 })
+        );
+        assert_eq!(chip8.state().ram[0x200..], [0x12, 0x00]);
+    }
+}
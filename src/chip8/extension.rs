@@ -0,0 +1,100 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "window")]
+use crate::chip8::display::Display;
+use crate::chip8::CHIP8;
+
+/// Runs an opcode the interpreter does not implement. PC already points past
+/// it when the handler is called, so handlers may jump by setting PC.
+#[cfg(feature = "window")]
+pub type Handler<F = Display> = Box<dyn FnMut(&mut CHIP8<F>, u16)>;
+#[cfg(not(feature = "window"))]
+pub type Handler<F> = Box<dyn FnMut(&mut CHIP8<F>, u16)>;
+
+/// A set of opcodes written the way opcode tables do, e.g. `5XY1` or `F0NN`:
+/// hex digits must match, while `X`, `Y`, `N`, `K` and `?` match any nibble.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpcodePattern {
+    mask: u16,
+    value: u16,
+}
+
+impl OpcodePattern {
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+impl fmt::Display for OpcodePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for shift in [12, 8, 4, 0] {
+            if (self.mask >> shift) & 0xF == 0 {
+                write!(f, "?")?;
+            } else {
+                write!(f, "{:X}", (self.value >> shift) & 0xF)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OpcodePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.chars().count() != 4 {
+            return Err(format!("expected four nibbles like `5XY1`, got `{}`", s));
+        }
+        let mut pattern = OpcodePattern { mask: 0, value: 0 };
+        for c in s.chars() {
+            pattern.mask <<= 4;
+            pattern.value <<= 4;
+            match c {
+                'X' | 'Y' | 'N' | 'K' | 'x' | 'y' | 'n' | 'k' | '?' => {}
+                _ => {
+                    let nibble = c
+                        .to_digit(16)
+                        .ok_or_else(|| format!("`{}` is not a hex digit or wildcard", c))?;
+                    pattern.mask |= 0xF;
+                    pattern.value |= nibble as u16;
+                }
+            }
+        }
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::opcodes::Instruction;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_patterns() {
+        let pattern: OpcodePattern = "5xY1".parse().unwrap();
+        assert!(pattern.matches(0x5121));
+        assert!(!pattern.matches(0x5120));
+        assert_eq!(pattern.to_string(), "5??1");
+        assert!("5XY".parse::<OpcodePattern>().is_err());
+        assert!("5XYG".parse::<OpcodePattern>().is_err());
+    }
+
+    #[test]
+    fn test_handlers_run_unknown_opcodes() {
+        let seen = Rc::new(Cell::new(0));
+        let mut chip8 = CHIP8::headless();
+        // 0xF100 is unknown, 0x00E0 (CLS) is not and stays with the CPU.
+        chip8.load_bytes(&[0xF1, 0x00, 0x00, 0xE0]).unwrap();
+        for pattern in ["FX00", "00E0"] {
+            let seen = seen.clone();
+            chip8.register_opcode(pattern.parse().unwrap(), move |_, opcode| seen.set(opcode));
+        }
+        assert_eq!(chip8.step(), Ok(None));
+        assert_eq!(chip8.step(), Ok(Some(Instruction::CLS)));
+        assert_eq!(seen.get(), 0xF100);
+        assert_eq!(chip8.registers().PC, 0x204);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::str::FromStr;
+
+pub const GLYPH_BYTES: usize = 5;
+
+pub const BIG_GLYPH_BYTES: usize = 10;
+
+/// Where the big glyphs are loaded, just after the small ones at 0.
+pub const BIG_FONT_START: usize = GLYPH_BYTES * 16;
+
+type Glyphs = [u8; GLYPH_BYTES * 16];
+
+const VIP: Glyphs = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x60, 0x20, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
+    0x10, 0xF0, 0x10, 0xF0, 0xA0, 0xA0, 0xF0, 0x20, 0x20, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
+    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x10, 0x10, 0x10, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
+    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xF0, 0x50, 0x70, 0x50, 0xF0, 0xF0, 0x80, 0x80, 0x80,
+    0xF0, 0xF0, 0x50, 0x50, 0x50, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+];
+
+const CHIP48: Glyphs = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
+    0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
+    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
+    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, 0xF0, 0x80, 0x80, 0x80,
+    0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+];
+
+const DREAM_6800: Glyphs = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, 0x40, 0x40, 0x40, 0x40, 0x40, 0xE0, 0x20, 0xE0, 0x80, 0xE0, 0xE0,
+    0x20, 0xE0, 0x20, 0xE0, 0x80, 0xA0, 0xA0, 0xE0, 0x20, 0xE0, 0x80, 0xE0, 0x20, 0xE0, 0xE0, 0x80,
+    0xE0, 0xA0, 0xE0, 0xE0, 0x20, 0x20, 0x20, 0x20, 0xE0, 0xA0, 0xE0, 0xA0, 0xE0, 0xE0, 0xA0, 0xE0,
+    0x20, 0xE0, 0xE0, 0xA0, 0xE0, 0xA0, 0xA0, 0xC0, 0xA0, 0xE0, 0xA0, 0xC0, 0xE0, 0x80, 0x80, 0x80,
+    0xE0, 0xC0, 0xA0, 0xA0, 0xA0, 0xC0, 0xE0, 0x80, 0xE0, 0x80, 0xE0, 0xE0, 0x80, 0xC0, 0x80, 0x80,
+];
+
+const ETI_660: Glyphs = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, 0x20, 0x20, 0x20, 0x20, 0x20, 0xE0, 0x20, 0xE0, 0x80, 0xE0, 0xE0,
+    0x20, 0xE0, 0x20, 0xE0, 0xA0, 0xA0, 0xE0, 0x20, 0x20, 0xE0, 0x80, 0xE0, 0x20, 0xE0, 0xE0, 0x80,
+    0xE0, 0xA0, 0xE0, 0xE0, 0x20, 0x20, 0x20, 0x20, 0xE0, 0xA0, 0xE0, 0xA0, 0xE0, 0xE0, 0xA0, 0xE0,
+    0x20, 0xE0, 0xE0, 0xA0, 0xE0, 0xA0, 0xA0, 0x80, 0x80, 0xE0, 0xA0, 0xE0, 0xE0, 0x80, 0x80, 0x80,
+    0xE0, 0x20, 0x20, 0xE0, 0xA0, 0xE0, 0xE0, 0x80, 0xE0, 0x80, 0xE0, 0xE0, 0x80, 0xE0, 0x80, 0x80,
+];
+
+/// SUPER-CHIP's big digits, eight pixels wide and ten tall, for scores.
+/// SUPER-CHIP only drew 0 to 9; A to F are Octo's, as XO-CHIP has them.
+pub const BIG_GLYPHS: [u8; BIG_GLYPH_BYTES * 16] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// The hex digit glyphs loaded at address 0, as drawn by the interpreters of
+/// different machines.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FontStyle {
+    /// COSMAC VIP: the original CHIP-8 interpreter.
+    Vip,
+    /// CHIP-48 on the HP-48, the font most interpreters use today.
+    #[default]
+    Chip48,
+    /// SUPER-CHIP kept the CHIP-48 glyphs for its small font.
+    Schip,
+    /// DREAM 6800: three pixel wide glyphs.
+    Dream6800,
+    /// ETI-660: three pixel wide glyphs with lower case b and d.
+    Eti660,
+}
+
+impl FontStyle {
+    pub fn glyphs(&self) -> &'static [u8] {
+        match self {
+            FontStyle::Vip => &VIP,
+            FontStyle::Chip48 | FontStyle::Schip => &CHIP48,
+            FontStyle::Dream6800 => &DREAM_6800,
+            FontStyle::Eti660 => &ETI_660,
+        }
+    }
+}
+
+impl fmt::Display for FontStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FontStyle::Vip => "vip",
+            FontStyle::Chip48 => "chip48",
+            FontStyle::Schip => "schip",
+            FontStyle::Dream6800 => "dream6800",
+            FontStyle::Eti660 => "eti660",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FontStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vip" => Ok(FontStyle::Vip),
+            "chip48" => Ok(FontStyle::Chip48),
+            "schip" => Ok(FontStyle::Schip),
+            "dream6800" => Ok(FontStyle::Dream6800),
+            "eti660" => Ok(FontStyle::Eti660),
+            _ => Err(format!(
+                "unknown font style `{}`, expected vip, chip48, schip, dream6800 or eti660",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_styles() {
+        for style in ["vip", "chip48", "schip", "dream6800", "eti660"] {
+            let font: FontStyle = style.parse().unwrap();
+            assert_eq!(font.to_string(), style);
+            assert_eq!(font.glyphs().len(), GLYPH_BYTES * 16);
+        }
+        // The VIP's 1 has a flat top, CHIP-48's a serif.
+        assert_eq!(FontStyle::Vip.glyphs()[5], 0x60);
+        assert_eq!(FontStyle::Chip48.glyphs()[5], 0x20);
+        assert!("cosmac".parse::<FontStyle>().is_err());
+    }
+
+    #[test]
+    fn test_big_font() {
+        let mut chip8 = crate::chip8::CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x01, // LD V0, 1
+                0xF0, 0x30, // LD HF, V0
+                0x61, 0x00, // LD V1, 0
+                0xD1, 0x1A, // DRW V1, V1, 10
+            ])
+            .unwrap();
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(chip8.state().i as usize, BIG_FONT_START + BIG_GLYPH_BYTES);
+        // The 1's foot is a full row, its stem two pixels wide.
+        assert!((0..8).all(|x| chip8.is_pixel_lit(x, 9)));
+        assert!(chip8.is_pixel_lit(3, 5) && chip8.is_pixel_lit(4, 5));
+        assert!(!chip8.is_pixel_lit(2, 5));
+    }
+}
@@ -0,0 +1,291 @@
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+/// The SUPER-CHIP's high resolution screen.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+pub const DEFAULT_FG: u32 = u32::MAX;
+pub const DEFAULT_BG: u32 = 0;
+
+/// The width and height of a frame of `pixels`: the high resolution screen
+/// if there are enough of them, the standard one otherwise.
+pub fn resolution<T>(pixels: &[T]) -> (usize, usize) {
+    if pixels.len() == HIRES_WIDTH * HIRES_HEIGHT {
+        (HIRES_WIDTH, HIRES_HEIGHT)
+    } else {
+        (WIDTH, HEIGHT)
+    }
+}
+
+/// 64-bit FNV-1a over the screen packed one bit per pixel, row by row, with
+/// the leftmost pixel in the most significant bit of each byte.
+pub fn hash_lit(lit: &[bool]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    lit.chunks(8).fold(OFFSET_BASIS, |hash, pixels| {
+        let byte = pixels.iter().fold(0u8, |byte, &lit| byte << 1 | lit as u8);
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// The screen as the program draws it: which pixels are lit, and the colors
+/// to show lit and unlit pixels in. Only presenting the screen turns it into
+/// colors, so they never change what the program sees.
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    lit: Vec<bool>,
+    fg: u32,
+    bg: u32,
+    /// Whether sprites wrap around the edges rather than being clipped.
+    wrap: bool,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        FrameBuffer {
+            lit: vec![false; WIDTH * HEIGHT],
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            wrap: false,
+        }
+    }
+}
+
+impl FrameBuffer {
+    /// The width and height of the current mode.
+    pub fn resolution(&self) -> (usize, usize) {
+        resolution(&self.lit)
+    }
+
+    /// The pixels row by row in the screen's colors, `0x00RRGGBB`.
+    pub fn pixels(&self) -> impl Iterator<Item = u32> + '_ {
+        let (fg, bg) = (self.fg, self.bg);
+        (self.lit.iter()).map(move |&lit| if lit { fg } else { bg })
+    }
+
+    /// The `hash_lit` of the screen, the same whatever its colors.
+    pub fn hash(&self) -> u64 {
+        hash_lit(&self.lit)
+    }
+
+    /// The colors of lit and unlit pixels.
+    pub fn colors(&self) -> (u32, u32) {
+        (self.fg, self.bg)
+    }
+
+    /// Change the colors of lit and unlit pixels.
+    pub fn set_colors(&mut self, fg: u32, bg: u32) {
+        self.fg = fg;
+        self.bg = bg;
+    }
+
+    /// Wrap the parts of sprites drawn past an edge of the screen around to
+    /// the other side, instead of clipping them. Either way, a sprite's
+    /// starting position wraps.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Switch between the 64x32 and the SUPER-CHIP's 128x64 screen, which
+    /// clears it.
+    pub fn set_hires(&mut self, hires: bool) {
+        let (width, height) = if hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (WIDTH, HEIGHT)
+        };
+        self.lit = vec![false; width * height];
+    }
+
+    /// Whether the pixel at (`x`, `y`) is lit.
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.lit[self.to_index(x, y)]
+    }
+
+    /// Which pixels are lit, row by row.
+    pub fn lit(&self) -> &[bool] {
+        &self.lit
+    }
+
+    /// Light the pixels in `lit`, row by row, switching to the mode with as
+    /// many pixels.
+    pub fn set_lit(&mut self, lit: &[bool]) {
+        self.lit = lit.to_vec();
+    }
+
+    /// The screen as text, a line per row with `#` for lit pixels and `.`
+    /// for unlit ones.
+    pub fn to_text(&self) -> String {
+        let (width, _) = self.resolution();
+        let rows: Vec<String> = (self.lit.chunks(width))
+            .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+            .collect();
+        rows.join("\n")
+    }
+
+    pub fn clear(&mut self) {
+        self.lit.fill(false);
+    }
+
+    fn to_index(&self, x: usize, y: usize) -> usize {
+        let (width, height) = self.resolution();
+        let y = y % height;
+        let x = x % width;
+        width * y + x
+    }
+
+    /// XOR an 8 pixel wide sprite onto the screen, one byte per row.
+    pub fn set_pixels(&mut self, x: u8, y: u8, bytes: &[u8]) -> bool {
+        let rows = bytes.iter().map(|&byte| u16::from(byte) << 8);
+        self.draw(x, y, rows)
+    }
+
+    /// XOR a 16 pixel wide SUPER-CHIP sprite onto the screen, two bytes per
+    /// row.
+    pub fn set_wide_pixels(&mut self, x: u8, y: u8, bytes: &[u8]) -> bool {
+        let rows = bytes
+            .chunks(2)
+            .map(|row| u16::from_be_bytes([row[0], *row.get(1).unwrap_or(&0)]));
+        self.draw(x, y, rows)
+    }
+
+    fn draw(&mut self, x: u8, y: u8, rows: impl Iterator<Item = u16>) -> bool {
+        let mut collision = false;
+        let (width, height) = self.resolution();
+        let (x, y) = (x as usize % width, y as usize % height);
+
+        for (j, row) in rows.enumerate() {
+            // For every bit in the row, check if 1
+            for i in 0..16 {
+                let filter: u16 = 0x8000 >> i;
+                let (px, py) = (x + i, y + j);
+                if !self.wrap && (px >= width || py >= height) {
+                    continue;
+                }
+                if row & filter == filter {
+                    // If so, XOR with buffer value, and track collision
+                    let index = self.to_index(px, py);
+                    collision |= self.lit[index];
+                    self.lit[index] = !self.lit[index];
+                }
+            }
+        }
+
+        collision
+    }
+
+    /// Whether the SUPER-CHIP's 128x64 screen is on.
+    pub fn is_hires(&self) -> bool {
+        self.lit.len() == HIRES_WIDTH * HIRES_HEIGHT
+    }
+
+    /// Move the picture down by `rows`, blanking the rows scrolled in. As on
+    /// the SUPER-CHIP, rows are counted on the 128x64 screen, so the 64x32
+    /// one scrolls half as far.
+    pub fn scroll_down(&mut self, rows: usize) {
+        let (width, _) = self.resolution();
+        let rows = if self.is_hires() { rows } else { rows / 2 };
+        let shift = (rows * width).min(self.lit.len());
+        self.lit.rotate_right(shift);
+        self.lit[..shift].fill(false);
+    }
+
+    /// Move the picture right by `columns`, or left when negative, blanking
+    /// the columns scrolled in. Like `scroll_down`, columns are counted on
+    /// the 128x64 screen.
+    pub fn scroll_sideways(&mut self, columns: isize) {
+        let (width, _) = self.resolution();
+        let columns = if self.is_hires() {
+            columns
+        } else {
+            columns / 2
+        };
+        let shift = columns.unsigned_abs().min(width);
+        for row in self.lit.chunks_mut(width) {
+            if columns > 0 {
+                row.rotate_right(shift);
+                row[..shift].fill(false);
+            } else {
+                row.rotate_left(shift);
+                row[width - shift..].fill(false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hires_sprites_and_scrolling() {
+        let mut fb = FrameBuffer::default();
+        fb.set_hires(true);
+        assert_eq!(fb.resolution(), (HIRES_WIDTH, HIRES_HEIGHT));
+
+        // A 16x16 sprite's right half, clipped at the right edge, then
+        // wrapped around from it.
+        let sprite = [0xFF; 32];
+        assert!(!fb.set_wide_pixels(120, 0, &sprite));
+        assert!(fb.is_lit(127, 15) && !fb.is_lit(0, 0));
+        fb.clear();
+        fb.set_wrap(true);
+        assert!(!fb.set_wide_pixels(120, 0, &sprite));
+        assert!(fb.is_lit(127, 15) && fb.is_lit(0, 0) && fb.is_lit(7, 0));
+        assert!(!fb.is_lit(8, 0) && !fb.is_lit(0, 16));
+
+        fb.scroll_down(4);
+        assert!(!fb.is_lit(0, 3) && fb.is_lit(0, 4) && fb.is_lit(0, 19));
+        fb.scroll_sideways(-4);
+        assert!(fb.is_lit(3, 4) && !fb.is_lit(4, 4) && fb.is_lit(116, 4));
+        fb.scroll_sideways(4);
+        assert!(!fb.is_lit(3, 4) && fb.is_lit(7, 4) && fb.is_lit(127, 4));
+        assert!(!fb.is_lit(119, 4));
+
+        fb.set_hires(false);
+        assert_eq!(fb.lit().len(), WIDTH * HEIGHT);
+    }
+
+    #[test]
+    fn test_lores_scrolls_half_as_far() {
+        let mut fb = FrameBuffer::default();
+        assert!(!fb.is_hires());
+        assert!(!fb.set_pixels(0, 0, &[0x80]));
+
+        fb.scroll_down(4);
+        assert!(!fb.is_lit(0, 0) && fb.is_lit(0, 2));
+        fb.scroll_down(1);
+        assert!(fb.is_lit(0, 2));
+        fb.scroll_sideways(4);
+        assert!(!fb.is_lit(0, 2) && fb.is_lit(2, 2));
+        fb.scroll_sideways(-4);
+        assert!(fb.is_lit(0, 2) && !fb.is_lit(2, 2));
+    }
+
+    #[test]
+    fn test_hash_lit() {
+        let mut lit = [false; WIDTH * HEIGHT];
+        assert_eq!(hash_lit(&lit), 0xd80ac658736bb725);
+
+        lit[0] = true;
+        assert_eq!(hash_lit(&lit), 0x351292af4fedb7a5);
+    }
+
+    #[test]
+    fn test_colors_do_not_change_collisions() {
+        for (fg, bg) in [
+            (DEFAULT_FG, DEFAULT_BG),
+            (0, 0xFFFFFF),
+            (0x123456, 0x123456),
+        ] {
+            let mut fb = FrameBuffer::default();
+            fb.set_colors(fg, bg);
+            assert!(!fb.set_pixels(0, 0, &[0xC0]));
+            assert!(fb.set_pixels(1, 0, &[0x80]));
+            assert!(fb.is_lit(0, 0) && !fb.is_lit(1, 0));
+            let pixels: Vec<u32> = fb.pixels().take(2).collect();
+            assert_eq!(pixels, [fg, bg]);
+        }
+    }
+}
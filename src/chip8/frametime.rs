@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How many frames are kept, about 18 minutes at 60Hz.
+const CAPACITY: usize = 1 << 16;
+
+/// Durations of the most recent frames presented by the window: the time
+/// between consecutive frames, and how long presenting each one took.
+#[derive(Debug, Default)]
+pub struct FrameTimes {
+    samples: Mutex<Samples>,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    intervals: VecDeque<Duration>,
+    present: VecDeque<Duration>,
+}
+
+impl FrameTimes {
+    pub fn record(&self, interval: Duration, present: Duration) {
+        let samples = &mut *self.samples.lock().unwrap();
+        for (series, sample) in [
+            (&mut samples.intervals, interval),
+            (&mut samples.present, present),
+        ] {
+            if series.len() == CAPACITY {
+                series.pop_front();
+            }
+            series.push_back(sample);
+        }
+    }
+}
+
+/// Percentiles of one series, in milliseconds.
+struct Summary {
+    p50: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+    /// Standard deviation.
+    jitter: f64,
+}
+
+impl Summary {
+    fn of(series: &VecDeque<Duration>) -> Option<Self> {
+        let mut ms: Vec<f64> = series.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        if ms.is_empty() {
+            return None;
+        }
+        ms.sort_by(f64::total_cmp);
+
+        let at = |p: f64| ms[((ms.len() - 1) as f64 * p).round() as usize];
+        let mean = ms.iter().sum::<f64>() / ms.len() as f64;
+        let variance = ms.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ms.len() as f64;
+        Some(Summary {
+            p50: at(0.50),
+            p95: at(0.95),
+            p99: at(0.99),
+            max: ms[ms.len() - 1],
+            jitter: variance.sqrt(),
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms, max {:.2} ms, jitter {:.2} ms",
+            self.p50, self.p95, self.p99, self.max, self.jitter
+        )
+    }
+}
+
+impl fmt::Display for FrameTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let samples = self.samples.lock().unwrap();
+        match (
+            Summary::of(&samples.intervals),
+            Summary::of(&samples.present),
+        ) {
+            (Some(intervals), Some(present)) => {
+                writeln!(f, "Frame times over {} frames:", samples.intervals.len())?;
+                writeln!(f, "  interval: {}", intervals)?;
+                write!(f, "  present:  {}", present)
+            }
+            _ => write!(f, "Frame times: no frames were presented"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let times = FrameTimes::default();
+        for ms in 1..=100 {
+            times.record(Duration::from_millis(ms), Duration::from_millis(1));
+        }
+
+        let samples = times.samples.lock().unwrap();
+        let intervals = Summary::of(&samples.intervals).unwrap();
+        assert_eq!(intervals.p50.round(), 51.0);
+        assert_eq!(intervals.p99.round(), 99.0);
+        assert_eq!(intervals.max.round(), 100.0);
+
+        let present = Summary::of(&samples.present).unwrap();
+        assert_eq!(present.jitter, 0.0);
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chip8::framebuffer::FrameBuffer;
+
+/// Which of the 16 keypad keys are down, one bit per key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeypadState(u16);
+
+impl KeypadState {
+    /// `self` with keypad key `key` (0x0 to 0xF) held down as well.
+    pub fn with(self, key: u8) -> Self {
+        KeypadState(self.0 | 1 << (key & 0xF))
+    }
+
+    /// `self` with keypad key `key` released.
+    pub fn without(self, key: u8) -> Self {
+        KeypadState(self.0 & !(1 << (key & 0xF)))
+    }
+
+    /// The keys down in `self` or in `other`.
+    pub fn union(self, other: Self) -> Self {
+        KeypadState(self.0 | other.0)
+    }
+
+    pub fn is_down(&self, key: u8) -> bool {
+        key <= 0xF && self.0 & 1 << key != 0
+    }
+
+    /// The lowest key that is down.
+    pub fn first_down(&self) -> Option<u8> {
+        (0..=0xF).find(|&key| self.is_down(key))
+    }
+}
+
+/// Whatever shows the screen, reads the keypad and sounds the buzzer for a
+/// `CHIP8`: the emulator's window, or a frontend of an embedder's own.
+pub trait Frontend {
+    /// Show the screen, after every change the program makes to it.
+    fn draw(&mut self, fb: &FrameBuffer);
+
+    /// Show the screen as last drawn, once a frame, for frontends that show
+    /// whole frames rather than every change.
+    fn present(&mut self) {}
+
+    /// The keys that are down now.
+    fn keys(&self) -> KeypadState;
+
+    /// Start or stop the buzzer, once a frame.
+    fn beep(&mut self, on: bool);
+
+    /// The program found `key` down, for frontends that time their input.
+    fn key_observed(&self, _key: u8) {}
+
+    /// Name the game being played, e.g. in a window's title bar.
+    fn set_title(&self, _title: &str) {}
+
+    /// Show how the emulator is running beside the title, e.g. "Paused", or
+    /// nothing when `status` is empty.
+    fn set_status(&self, _status: &str) {}
+
+    /// Whether the player has not closed it yet, for frontends that run
+    /// their own frame loop.
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+/// A frontend with no screen, sound or keys, for running ROMs from tests and
+/// tools.
+#[derive(Debug, Default)]
+pub struct NullFrontend;
+
+impl Frontend for NullFrontend {
+    fn draw(&mut self, _fb: &FrameBuffer) {}
+
+    fn keys(&self) -> KeypadState {
+        KeypadState::default()
+    }
+
+    fn beep(&mut self, _on: bool) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::registers::Registers;
+    use crate::chip8::CHIP8;
+
+    #[derive(Default)]
+    struct Recorder {
+        draws: usize,
+        presents: usize,
+        keys: KeypadState,
+        beeping: bool,
+    }
+
+    impl Frontend for Recorder {
+        fn draw(&mut self, _fb: &FrameBuffer) {
+            self.draws += 1;
+        }
+
+        fn present(&mut self) {
+            self.presents += 1;
+        }
+
+        fn keys(&self) -> KeypadState {
+            self.keys
+        }
+
+        fn beep(&mut self, on: bool) {
+            self.beeping = on;
+        }
+    }
+
+    #[test]
+    fn test_keypad_state() {
+        let keys = KeypadState::default().with(0xA).with(0x3);
+        assert!(keys.is_down(0x3) && keys.is_down(0xA));
+        assert!(!keys.is_down(0x0) && !keys.is_down(0x13));
+        assert_eq!(keys.first_down(), Some(0x3));
+        assert_eq!(KeypadState::default().first_down(), None);
+        assert_eq!(
+            keys.union(KeypadState::default().with(0x5)).first_down(),
+            Some(0x3)
+        );
+        assert!(keys.union(KeypadState::default().with(0x5)).is_down(0x5));
+    }
+
+    #[test]
+    fn test_custom_frontend() {
+        let frontend = Recorder {
+            keys: KeypadState::default().with(0x0),
+            ..Recorder::default()
+        };
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), frontend);
+        chip8
+            .load_bytes(&[
+                0x00, 0xE0, // CLS
+                0x61, 0x05, // LD V1, 5
+                0xF1, 0x18, // LD ST, V1
+                0xE0, 0x9E, // SKP V0
+                0x12, 0x08, // JP 0x208, skipped
+                0x12, 0x0A, // JP 0x20A
+            ])
+            .unwrap();
+        chip8.step_frame().unwrap();
+        assert_eq!(chip8.registers().PC, 0x20A);
+        assert_eq!(chip8.frontend().draws, 1);
+        assert_eq!(chip8.frontend().presents, 1);
+        assert!(chip8.frontend().beeping);
+    }
+
+    #[test]
+    fn test_key_wait_ends_on_release() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), Recorder::default());
+        chip8
+            .load_bytes(&[
+                0x60, 0x3C, // LD V0, 60
+                0xF0, 0x15, // LD DT, V0
+                0xF1, 0x0A, // LD V1, K
+            ])
+            .unwrap();
+        chip8.step_frame().unwrap();
+        assert_eq!(chip8.registers().PC, 0x204);
+
+        chip8.frontend_mut().keys = KeypadState::default().with(0x7);
+        chip8.step_frame().unwrap();
+        assert_eq!(chip8.registers().PC, 0x204, "still held");
+
+        chip8.frontend_mut().keys = KeypadState::default();
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers().PC, 0x206);
+        assert_eq!(chip8.registers().Vx[1], 0x7);
+        assert_eq!(chip8.key_waits(), 1);
+        assert_eq!(chip8.registers().get_dt(), 58, "the timers ran on");
+    }
+
+    #[test]
+    fn test_cpu_hz() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        chip8.load_bytes(&[0x12, 0x00]).unwrap(); // JP 0x200
+        chip8.set_cpu_hz(700);
+        for _ in 0..60 {
+            chip8.step_frame().unwrap();
+        }
+        assert_eq!(chip8.instructions(), 700);
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "gamepad")]
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+#[cfg(feature = "gamepad")]
+use std::sync::{mpsc, Arc};
+#[cfg(feature = "gamepad")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "gamepad")]
+use std::time::Duration;
+
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button, Gilrs};
+
+#[cfg(feature = "gamepad")]
+use crate::chip8::frontend::KeypadState;
+
+/// The controller buttons that can be mapped, by name. South, east, north
+/// and west are the face buttons by position, e.g. A, B, Y and X on an Xbox
+/// controller.
+const BUTTONS: [&str; 14] = [
+    "up", "down", "left", "right", "south", "east", "north", "west", "l1", "r1", "l2", "r2",
+    "select", "start",
+];
+
+/// The buttons in `BUTTONS`, as gilrs knows them.
+#[cfg(feature = "gamepad")]
+const GILRS_BUTTONS: [Button; 14] = [
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::LeftTrigger,
+    Button::RightTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+];
+
+/// How far the left stick has to be pushed to count as the d-pad.
+#[cfg(feature = "gamepad")]
+const DEADZONE: f32 = 0.5;
+/// How long to wait for controller events before checking for shutdown.
+#[cfg(feature = "gamepad")]
+const POLL: Duration = Duration::from_millis(16);
+
+/// Which keypad key each controller button stands for. Written as
+/// `button=key` pairs, e.g. `up=2,down=8,left=4,right=6,south=5`, the
+/// default. Buttons left out do nothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PadMap {
+    keys: [Option<u8>; 14],
+}
+
+impl Default for PadMap {
+    fn default() -> Self {
+        "up=2,down=8,left=4,right=6,south=5".parse().unwrap()
+    }
+}
+
+impl PadMap {
+    /// The keypad key the button called `button` stands for, if any.
+    pub fn keypad_key(&self, button: &str) -> Option<u8> {
+        BUTTONS
+            .iter()
+            .position(|&b| b == button)
+            .and_then(|i| self.keys[i])
+    }
+}
+
+impl fmt::Display for PadMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut sep = "";
+        for (button, key) in BUTTONS.iter().zip(&self.keys) {
+            if let Some(key) = key {
+                write!(f, "{}{}={:X}", sep, button, key)?;
+                sep = ",";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PadMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = [None; 14];
+        for pair in s.split(',') {
+            let (button, key) = pair.split_once('=').ok_or_else(|| {
+                format!(
+                    "expected `button=key` pairs, like `up=2,south=5`, got `{}`",
+                    pair
+                )
+            })?;
+            let (button, key) = (button.trim(), key.trim());
+            let i = BUTTONS
+                .iter()
+                .position(|&b| b.eq_ignore_ascii_case(button))
+                .ok_or_else(|| {
+                    format!(
+                        "`{}` is not a button, expected one of {}",
+                        button,
+                        BUTTONS.join(", ")
+                    )
+                })?;
+            if keys[i].is_some() {
+                return Err(format!("`{}` is mapped twice", button));
+            }
+            let val = key
+                .chars()
+                .next()
+                .filter(|_| key.len() == 1)
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| format!("`{}` is not a keypad key 0 to F", key))?;
+            keys[i] = Some(val as u8);
+        }
+        Ok(PadMap { keys })
+    }
+}
+
+/// Reads the keypad from every connected controller through a `PadMap`,
+/// until dropped. The left stick works as the d-pad.
+#[cfg(feature = "gamepad")]
+pub struct Gamepad {
+    keys: Arc<AtomicU16>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepad {
+    pub fn start(map: PadMap) -> Result<Self, String> {
+        let keys = Arc::new(AtomicU16::new(0));
+        let running = Arc::new(AtomicBool::new(true));
+        let (ready, started) = mpsc::channel();
+        let thread = {
+            let (keys, running) = (keys.clone(), running.clone());
+            thread::spawn(move || {
+                // Gilrs can't move between threads, so it lives on this one.
+                let mut gilrs = match Gilrs::new() {
+                    Ok(gilrs) => gilrs,
+                    Err(e) => return ready.send(Err(e.to_string())).unwrap_or(()),
+                };
+                let _ = ready.send(Ok(()));
+                while running.load(Ordering::Relaxed) {
+                    if gilrs.next_event_blocking(Some(POLL)).is_some() {
+                        while gilrs.next_event().is_some() {}
+                    }
+                    keys.store(read(&gilrs, &map), Ordering::Relaxed);
+                }
+            })
+        };
+        started
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map(|()| Gamepad {
+                keys,
+                running,
+                thread: Some(thread),
+            })
+    }
+
+    /// The keys down on any controller.
+    pub fn keys(&self) -> KeypadState {
+        let bits = self.keys.load(Ordering::Relaxed);
+        (0..=0xF)
+            .filter(|key| bits & 1 << key != 0)
+            .fold(KeypadState::default(), KeypadState::with)
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl Drop for Gamepad {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The keys down on every connected controller, one bit per key.
+#[cfg(feature = "gamepad")]
+fn read(gilrs: &Gilrs, map: &PadMap) -> u16 {
+    let mut bits = 0;
+    for (_, gamepad) in gilrs.gamepads() {
+        let (x, y) = (
+            gamepad.value(Axis::LeftStickX),
+            gamepad.value(Axis::LeftStickY),
+        );
+        let stick = [y > DEADZONE, y < -DEADZONE, x < -DEADZONE, x > DEADZONE];
+        for (i, &button) in GILRS_BUTTONS.iter().enumerate() {
+            let down = gamepad.is_pressed(button) || stick.get(i) == Some(&true);
+            if let (true, Some(key)) = (down, map.keys[i]) {
+                bits |= 1 << key;
+            }
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_map() {
+        let map = PadMap::default();
+        assert_eq!(map.keypad_key("up"), Some(0x2));
+        assert_eq!(map.keypad_key("south"), Some(0x5));
+        assert_eq!(map.keypad_key("start"), None);
+        assert_eq!(map.to_string(), "up=2,down=8,left=4,right=6,south=5");
+
+        let map: PadMap = "Up = 1, down=4, south=c, east=C".parse().unwrap();
+        assert_eq!(map.keypad_key("up"), Some(0x1));
+        assert_eq!(map.keypad_key("east"), Some(0xC));
+        assert_eq!(map.to_string(), "up=1,down=4,south=C,east=C");
+        assert!("up".parse::<PadMap>().is_err());
+        assert!("up=10".parse::<PadMap>().is_err());
+        assert!("trigger=1".parse::<PadMap>().is_err());
+        assert!("up=1,up=2".parse::<PadMap>().is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::chip8::framebuffer::{self, HIRES_HEIGHT, HIRES_WIDTH};
+
+/// One 60th of a second of the screen: which pixels are lit, and the colors
+/// of lit and unlit ones.
+type Frame = (Vec<bool>, u32, u32);
+
+/// Twice the SUPER-CHIP's screen.
+const GIF_WIDTH: usize = 2 * HIRES_WIDTH;
+const GIF_HEIGHT: usize = 2 * HIRES_HEIGHT;
+
+/// Records the screen to an animated GIF, encoding on a thread of its own.
+/// Every frame is scaled to the same size, whichever resolution the program
+/// is in.
+pub struct GifRecorder {
+    frames: Option<Sender<Frame>>,
+    encoder: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl GifRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut encoder = Encoder::new(BufWriter::new(File::create(path)?))?;
+        let (frames, received) = mpsc::channel::<Frame>();
+        let encoder = thread::spawn(move || {
+            for frame in received {
+                encoder.push(frame)?;
+            }
+            encoder.finish()
+        });
+        Ok(GifRecorder {
+            frames: Some(frames),
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Add a 60th of a second showing the pixels `lit`, in `fg` on `bg`.
+    pub fn frame(&self, lit: &[bool], fg: u32, bg: u32) {
+        if let Some(frames) = &self.frames {
+            let _ = frames.send((lit.to_vec(), fg, bg));
+        }
+    }
+
+    /// Write out the frames still queued and end the GIF.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.join()
+    }
+
+    fn join(&mut self) -> io::Result<()> {
+        self.frames.take();
+        match self.encoder.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("the GIF encoder panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for GifRecorder {
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+/// Turns 60Hz frames into GIF frames, holding each picture until it changes
+/// as GIF delays count hundredths of a second.
+struct Encoder<W: Write> {
+    gif: gif::Encoder<W>,
+    /// The picture shown since frame `since`, as palette indices and palette.
+    held: Option<(Vec<u8>, Vec<u8>)>,
+    since: u64,
+    frames: u64,
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(out: W) -> io::Result<Self> {
+        let mut gif = gif::Encoder::new(out, GIF_WIDTH as u16, GIF_HEIGHT as u16, &[])
+            .map_err(io::Error::other)?;
+        gif.set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        Ok(Encoder {
+            gif,
+            held: None,
+            since: 0,
+            frames: 0,
+        })
+    }
+
+    fn push(&mut self, (lit, fg, bg): Frame) -> io::Result<()> {
+        let picture = (scale(&lit), palette(fg, bg));
+        if self.held.as_ref() != Some(&picture) {
+            self.write_held()?;
+            self.held = Some(picture);
+            self.since = self.frames;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.write_held()?;
+        self.gif.into_inner().map(drop)
+    }
+
+    fn write_held(&mut self) -> io::Result<()> {
+        let (indices, palette) = match self.held.take() {
+            Some(held) => held,
+            None => return Ok(()),
+        };
+        // Round the frame's start and end to hundredths, so delays add up
+        // to the time that passed.
+        let centis = |frame: u64| (frame * 100 + 30) / 60;
+        let frame = gif::Frame {
+            delay: (centis(self.frames) - centis(self.since)).max(1) as u16,
+            width: GIF_WIDTH as u16,
+            height: GIF_HEIGHT as u16,
+            palette: Some(palette),
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        self.gif.write_frame(&frame).map_err(io::Error::other)
+    }
+}
+
+/// The pixels `lit` scaled to the GIF's size, 1 for lit and 0 for unlit.
+fn scale(lit: &[bool]) -> Vec<u8> {
+    let (width, _) = framebuffer::resolution(lit);
+    let factor = GIF_WIDTH / width;
+    let mut indices = Vec::with_capacity(GIF_WIDTH * GIF_HEIGHT);
+    for row in lit.chunks(width) {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&lit| std::iter::repeat_n(lit as u8, factor))
+            .collect();
+        for _ in 0..factor {
+            indices.extend_from_slice(&line);
+        }
+    }
+    indices
+}
+
+fn palette(fg: u32, bg: u32) -> Vec<u8> {
+    let [_, bg_r, bg_g, bg_b] = bg.to_be_bytes();
+    let [_, fg_r, fg_g, fg_b] = fg.to_be_bytes();
+    vec![bg_r, bg_g, bg_b, fg_r, fg_g, fg_b]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::framebuffer::{HEIGHT, WIDTH};
+
+    #[test]
+    fn test_encode() {
+        let blank = vec![false; WIDTH * HEIGHT];
+        let mut dot = blank.clone();
+        dot[WIDTH + 1] = true;
+
+        let mut out = Vec::new();
+        let mut encoder = Encoder::new(&mut out).unwrap();
+        for frame in [&blank, &blank, &blank, &dot, &dot, &blank] {
+            encoder.push((frame.clone(), 0xFFFFFF, 0)).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(out.as_slice()).unwrap();
+        let mut delays = Vec::new();
+        let mut lit = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+            // Pixel (1, 1) scaled by 4 starts at (4, 4).
+            lit.push(frame.buffer[4 * GIF_WIDTH + 4]);
+        }
+        assert_eq!(delays, [5, 3, 2]);
+        assert_eq!(lit, [0, 1, 0]);
+    }
+}
@@ -0,0 +1,80 @@
+/// Spots a program spinning in place, such as `LD Vx, K` waiting for a key or
+/// a loop polling the delay timer, so the CPU can sleep instead.
+///
+/// The CPU is deterministic apart from its timers, keys and random numbers,
+/// so once the machine state repeats without anything having been drawn,
+/// stored or rolled in between, it will keep repeating until a timer ticks or
+/// a key changes. Repeats are found with Brent's cycle detection: the state
+/// is remembered at power of two step counts and compared after every step.
+#[derive(Debug)]
+pub struct Idle<S> {
+    seen: Option<S>,
+    steps: u32,
+    limit: u32,
+    spinning: bool,
+}
+
+impl<S: PartialEq> Idle<S> {
+    pub fn new() -> Self {
+        Idle {
+            seen: None,
+            steps: 0,
+            limit: 1,
+            spinning: false,
+        }
+    }
+
+    /// Record the state after a step that had no effects outside the state
+    /// itself.
+    pub fn observe(&mut self, state: S) {
+        if self.seen.as_ref() == Some(&state) {
+            self.spinning = true;
+            return;
+        }
+        self.steps += 1;
+        if self.seen.is_none() || self.steps == self.limit {
+            self.seen = Some(state);
+            self.steps = 0;
+            self.limit *= 2;
+        }
+    }
+
+    /// Forget what has been seen, after a step that drew, stored or rolled a
+    /// random number, or once the machine has slept.
+    pub fn reset(&mut self) {
+        *self = Idle::new();
+    }
+
+    /// Whether the program is stuck until a timer or key changes.
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    pub fn is_spinning(&self) -> bool {
+        self.spinning
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_cycles() {
+        let mut idle = Idle::new();
+        idle.observe(0x200);
+        idle.observe(0x200);
+        assert!(idle.is_spinning());
+
+        // A loop that does not come back to where it was first seen.
+        let mut idle = Idle::new();
+        let pcs = [vec![0x200, 0x202], [0x204, 0x206, 0x208].repeat(4)].concat();
+        for pc in pcs {
+            idle.observe(pc);
+        }
+        assert!(idle.is_spinning());
+
+        idle.reset();
+        for counter in 0..1000 {
+            idle.observe(counter);
+        }
+        assert!(!idle.is_spinning());
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use minifb::Key;
+
+/// The keypad's keys as they sit on the pad, row by row.
+pub const PAD_ORDER: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Which keyboard key stands for each keypad key. Written as the 16 keys
+/// that take the keypad's place, row by row: `1234qwerasdfzxcv` by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keymap {
+    keys: [Key; 16],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        "1234qwerasdfzxcv".parse().unwrap()
+    }
+}
+
+impl Keymap {
+    /// The keymap with `keys` standing for the keypad keys row by row, as
+    /// they are written.
+    pub fn from_rows(keys: [Key; 16]) -> Result<Self, String> {
+        let mut map = [Key::Unknown; 16];
+        for (&val, &key) in PAD_ORDER.iter().zip(&keys) {
+            if map.contains(&key) {
+                return Err(match char_for(key) {
+                    Some(c) => format!("`{}` is mapped twice", c),
+                    None => format!("{:?} is mapped twice", key),
+                });
+            }
+            map[val as usize] = key;
+        }
+        Ok(Keymap { keys: map })
+    }
+
+    /// The keypad key `key` stands for, if any.
+    pub fn keypad_key(&self, key: Key) -> Option<u8> {
+        self.keys.iter().position(|&k| k == key).map(|i| i as u8)
+    }
+
+    /// The keyboard key standing for keypad key `val`.
+    pub fn key(&self, val: u8) -> Option<Key> {
+        self.keys.get(val as usize).copied()
+    }
+
+    /// Set the `keymap` setting in the TOML file at `path` to this, keeping
+    /// its other settings, or start the file with it.
+    pub fn save_setting(&self, path: &Path) -> io::Result<()> {
+        let mut settings = match fs::read_to_string(path) {
+            Ok(toml) => {
+                toml::from_str(&toml).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => toml::value::Table::new(),
+            Err(e) => return Err(e),
+        };
+        settings.insert("keymap".to_string(), self.to_string().into());
+        let toml = toml::to_string(&settings)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, toml)
+    }
+}
+
+/// A keymap for a keyboard layout, putting the keypad on the same keys
+/// whatever they are called.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// `1234qwerasdfzxcv`, the left of the keyboard.
+    #[default]
+    Qwerty,
+    /// `1234azerqsdfwxcv`, the same keys on AZERTY.
+    Azerty,
+    /// `1234',.paoeu;qjk`, the same keys on Dvorak.
+    Dvorak,
+    /// The number pad alone: the digits for themselves, then / * - + Enter
+    /// and . for A to F.
+    Numpad,
+}
+
+impl Layout {
+    pub fn keymap(self) -> Keymap {
+        match self {
+            Layout::Qwerty => Keymap::default(),
+            Layout::Azerty => "1234azerqsdfwxcv".parse().unwrap(),
+            Layout::Dvorak => "1234',.paoeu;qjk".parse().unwrap(),
+            Layout::Numpad => Keymap::from_rows(PAD_ORDER.map(|val| NUMPAD[val as usize])).unwrap(),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Layout::Qwerty => write!(f, "qwerty"),
+            Layout::Azerty => write!(f, "azerty"),
+            Layout::Dvorak => write!(f, "dvorak"),
+            Layout::Numpad => write!(f, "numpad"),
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qwerty" => Ok(Layout::Qwerty),
+            "azerty" => Ok(Layout::Azerty),
+            "dvorak" => Ok(Layout::Dvorak),
+            "numpad" => Ok(Layout::Numpad),
+            _ => Err(format!(
+                "expected `qwerty`, `azerty`, `dvorak` or `numpad`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// The number pad key for each keypad key, 0 to F.
+const NUMPAD: [Key; 16] = [
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadSlash,
+    Key::NumPadAsterisk,
+    Key::NumPadMinus,
+    Key::NumPadPlus,
+    Key::NumPadEnter,
+    Key::NumPadDot,
+];
+
+/// Whether `key` can stand for a keypad key.
+pub fn can_map(key: Key) -> bool {
+    char_for(key).is_some()
+}
+
+/// The key that types `c`, if it can be mapped.
+pub fn key_for(c: char) -> Option<Key> {
+    let c = c.to_ascii_lowercase();
+    KEYS.iter().find(|&&(k, _)| k == c).map(|&(_, key)| key)
+}
+
+fn char_for(key: Key) -> Option<char> {
+    KEYS.iter().find(|&&(_, k)| k == key).map(|&(c, _)| c)
+}
+
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &val in &PAD_ORDER {
+            write!(f, "{}", char_for(self.keys[val as usize]).unwrap_or('?'))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        if chars.len() != 16 {
+            return Err(format!(
+                "expected 16 keys for the keypad row by row, like `1234qwerasdfzxcv`, got `{}`",
+                s
+            ));
+        }
+
+        let mut keys = [Key::Unknown; 16];
+        for (key, &c) in keys.iter_mut().zip(&chars) {
+            *key = key_for(c).ok_or_else(|| format!("`{}` is not a key that can be mapped", c))?;
+        }
+        Keymap::from_rows(keys)
+    }
+}
+
+/// The keys that can be mapped, by the character they type.
+const KEYS: [(char, Key); 47] = [
+    ('0', Key::Key0),
+    ('1', Key::Key1),
+    ('2', Key::Key2),
+    ('3', Key::Key3),
+    ('4', Key::Key4),
+    ('5', Key::Key5),
+    ('6', Key::Key6),
+    ('7', Key::Key7),
+    ('8', Key::Key8),
+    ('9', Key::Key9),
+    ('a', Key::A),
+    ('b', Key::B),
+    ('c', Key::C),
+    ('d', Key::D),
+    ('e', Key::E),
+    ('f', Key::F),
+    ('g', Key::G),
+    ('h', Key::H),
+    ('i', Key::I),
+    ('j', Key::J),
+    ('k', Key::K),
+    ('l', Key::L),
+    ('m', Key::M),
+    ('n', Key::N),
+    ('o', Key::O),
+    ('p', Key::P),
+    ('q', Key::Q),
+    ('r', Key::R),
+    ('s', Key::S),
+    ('t', Key::T),
+    ('u', Key::U),
+    ('v', Key::V),
+    ('w', Key::W),
+    ('x', Key::X),
+    ('y', Key::Y),
+    ('z', Key::Z),
+    ('`', Key::Backquote),
+    ('-', Key::Minus),
+    ('=', Key::Equal),
+    ('[', Key::LeftBracket),
+    (']', Key::RightBracket),
+    ('\\', Key::Backslash),
+    (';', Key::Semicolon),
+    ('\'', Key::Apostrophe),
+    (',', Key::Comma),
+    ('.', Key::Period),
+    ('/', Key::Slash),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_default_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.keypad_key(Key::X), Some(0x0));
+        assert_eq!(keymap.keypad_key(Key::Key4), Some(0xC));
+        assert_eq!(keymap.key(0xF), Some(Key::V));
+        assert_eq!(keymap.keypad_key(Key::P), None);
+        assert_eq!(keymap.to_string(), "1234qwerasdfzxcv");
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let keymap: Keymap = "1234AZERQSDFWXCV".parse().unwrap();
+        assert_eq!(keymap.keypad_key(Key::A), Some(0x4));
+        assert_eq!(keymap.keypad_key(Key::W), Some(0xA));
+        assert!("1234".parse::<Keymap>().is_err());
+        assert!("1234qwerasdfzxc!".parse::<Keymap>().is_err());
+        assert!("1234qwerasdfzxcc".parse::<Keymap>().is_err());
+    }
+
+    #[test]
+    fn test_layouts() {
+        assert_eq!(Layout::default().keymap(), Keymap::default());
+        let azerty = "azerty".parse::<Layout>().unwrap().keymap();
+        assert_eq!(azerty.keypad_key(Key::A), Some(0x4));
+        let dvorak = Layout::Dvorak.keymap();
+        assert_eq!(dvorak.keypad_key(Key::Apostrophe), Some(0x4));
+        assert_eq!(dvorak.keypad_key(Key::K), Some(0xF));
+        let numpad = Layout::Numpad.keymap();
+        assert_eq!(numpad.keypad_key(Key::NumPad7), Some(0x7));
+        assert_eq!(numpad.keypad_key(Key::NumPadEnter), Some(0xE));
+        assert_eq!(numpad.key(0xA), Some(Key::NumPadSlash));
+        assert!("colemak".parse::<Layout>().is_err());
+    }
+
+    #[test]
+    fn test_save_setting() {
+        let path = env::temp_dir().join(format!("chip8-test-{}.toml", std::process::id()));
+        fs::write(&path, "cpu-hz = 1000\nkeymap = \"1234qwerasdfzxcv\"\n").unwrap();
+        let keymap: Keymap = "1234azerqsdfwxcv".parse().unwrap();
+        keymap.save_setting(&path).unwrap();
+        let toml = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(toml.contains("cpu-hz = 1000"));
+        assert!(toml.contains("keymap = \"1234azerqsdfwxcv\""));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use minifb::Key;
+
+use crate::chip8::stats::Stats;
+
+/// Time from the window seeing a key go down to the program first testing
+/// for that key (SKP, SKNP or LD Vx, K) and finding it down.
+#[derive(Debug)]
+pub struct InputLatency {
+    stats: Arc<Stats>,
+    pending: Mutex<HashMap<Key, (Instant, u64)>>,
+    measured: Mutex<Measured>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Measured {
+    presses: u32,
+    total: Duration,
+    worst: Duration,
+    worst_frames: u64,
+}
+
+impl InputLatency {
+    /// Frames are counted off `stats`.
+    pub fn new(stats: Arc<Stats>) -> Self {
+        InputLatency {
+            stats,
+            pending: Mutex::new(HashMap::new()),
+            measured: Mutex::new(Measured::default()),
+        }
+    }
+
+    /// The window saw `key` go down.
+    pub fn pressed(&self, key: Key) {
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), self.stats.frames()));
+    }
+
+    /// The program found `key` down. Only the first time after each press
+    /// counts.
+    pub fn observed(&self, key: Key) {
+        let (at, frame) = match self.pending.lock().unwrap().remove(&key) {
+            Some(press) => press,
+            None => return,
+        };
+        let latency = at.elapsed();
+        let frames = self.stats.frames().saturating_sub(frame);
+
+        let mut measured = self.measured.lock().unwrap();
+        measured.presses += 1;
+        measured.total += latency;
+        if latency > measured.worst {
+            measured.worst = latency;
+            measured.worst_frames = frames;
+        }
+    }
+}
+
+impl fmt::Display for InputLatency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let measured = *self.measured.lock().unwrap();
+        if measured.presses == 0 {
+            return write!(f, "Input latency: no key presses were seen by the program");
+        }
+        write!(
+            f,
+            "Input latency over {} key presses: average {:.1} ms, worst {:.1} ms ({} frames)",
+            measured.presses,
+            (measured.total / measured.presses).as_secs_f64() * 1000.0,
+            measured.worst.as_secs_f64() * 1000.0,
+            measured.worst_frames
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_first_observation_counts() {
+        let stats = Arc::new(Stats::default());
+        let latency = InputLatency::new(stats.clone());
+        latency.observed(Key::Key1);
+        assert!(latency.to_string().contains("no key presses"));
+
+        latency.pressed(Key::Key1);
+        stats.count_frame();
+        stats.count_frame();
+        latency.observed(Key::Key1);
+        latency.observed(Key::Key1);
+
+        let measured = *latency.measured.lock().unwrap();
+        assert_eq!(measured.presses, 1);
+        assert_eq!(measured.worst_frames, 2);
+    }
+}
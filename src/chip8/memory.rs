@@ -0,0 +1,194 @@
+use std::ops::Range;
+
+/// A device mapped over part of the address space in place of RAM, such as
+/// write-protected data, flags kept between runs or a peripheral. Offsets
+/// count from the start of the range it is mapped at.
+pub trait Region {
+    fn read(&self, offset: usize) -> u8;
+
+    fn write(&mut self, offset: usize, value: u8);
+
+    /// Whether writes are refused as access violations.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
+
+/// Bytes the program can read but not overwrite, e.g. to catch a ROM
+/// scribbling over the font.
+pub struct ReadOnly(pub Vec<u8>);
+
+impl Region for ReadOnly {
+    fn read(&self, offset: usize) -> u8 {
+        self.0.get(offset).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, _offset: usize, _value: u8) {}
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+/// Why an access was refused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// `addr` is past the end of the address space.
+    OutOfRange(usize),
+    /// `addr` is in a read-only region.
+    ReadOnly(usize),
+}
+
+/// The address space the program sees: RAM, with regions mapped over it.
+/// Accesses are checked as a whole, so one that fails changes nothing.
+pub struct Memory {
+    ram: Vec<u8>,
+    regions: Vec<(Range<usize>, Box<dyn Region>)>,
+}
+
+impl Memory {
+    /// `size` bytes of zeroed RAM.
+    pub fn new(size: usize) -> Self {
+        Memory {
+            ram: vec![0; size],
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ram.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ram.is_empty()
+    }
+
+    /// Map `region` over `range`, in front of RAM and any region mapped
+    /// there before.
+    pub fn map(&mut self, range: Range<usize>, region: Box<dyn Region>) {
+        self.regions.insert(0, (range, region));
+    }
+
+    fn region(&self, addr: usize) -> Option<(usize, &dyn Region)> {
+        self.regions
+            .iter()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(range, region)| (addr - range.start, region.as_ref()))
+    }
+
+    /// The byte at `addr`, if it is in the address space.
+    pub fn get(&self, addr: usize) -> Option<u8> {
+        let byte = *self.ram.get(addr)?;
+        Some(match self.region(addr) {
+            Some((offset, region)) => region.read(offset),
+            None => byte,
+        })
+    }
+
+    /// The `len` bytes from `start`.
+    pub fn read(&self, start: usize, len: usize) -> Result<Vec<u8>, Violation> {
+        (start..start + len)
+            .map(|addr| self.get(addr).ok_or(Violation::OutOfRange(addr)))
+            .collect()
+    }
+
+    /// Store `bytes` from `start`, or nothing if any of them can't be
+    /// stored.
+    pub fn write(&mut self, start: usize, bytes: &[u8]) -> Result<(), Violation> {
+        let range = start..start + bytes.len();
+        if range.end > self.ram.len() {
+            return Err(Violation::OutOfRange(start.max(self.ram.len())));
+        }
+        if let Some(addr) = range.clone().find(|&addr| {
+            self.region(addr)
+                .is_some_and(|(_, region)| region.is_read_only())
+        }) {
+            return Err(Violation::ReadOnly(addr));
+        }
+        for (addr, &byte) in range.zip(bytes) {
+            match self
+                .regions
+                .iter_mut()
+                .find(|(range, _)| range.contains(&addr))
+            {
+                Some((range, region)) => region.write(addr - range.start, byte),
+                None => self.ram[addr] = byte,
+            }
+        }
+        Ok(())
+    }
+
+    /// Put `bytes` in RAM from `start`, underneath any regions, e.g. to
+    /// load a ROM. Bytes past the end of RAM are dropped.
+    pub fn load(&mut self, start: usize, bytes: &[u8]) {
+        let end = (start + bytes.len()).min(self.ram.len());
+        if start < end {
+            self.ram[start..end].copy_from_slice(&bytes[..end - start]);
+        }
+    }
+
+    /// Grow or shrink RAM to `size` bytes, zeroing any new ones. Regions stay
+    /// where they are.
+    pub fn resize(&mut self, size: usize) {
+        self.ram.resize(size, 0);
+    }
+
+    /// Zero all of RAM. Regions keep their contents.
+    pub fn clear(&mut self) {
+        self.ram.fill(0);
+    }
+
+    /// The whole address space as the program would read it.
+    pub fn to_vec(&self) -> Vec<u8> {
+        (0..self.len()).filter_map(|addr| self.get(addr)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Counts the writes to its single byte.
+    struct Counter(Rc<Cell<u8>>);
+
+    impl Region for Counter {
+        fn read(&self, _offset: usize) -> u8 {
+            self.0.get()
+        }
+
+        fn write(&mut self, _offset: usize, _value: u8) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_regions() {
+        let mut memory = Memory::new(0x100);
+        memory.load(0x10, &[1, 2, 3]);
+        memory.map(0x00..0x05, Box::new(ReadOnly(vec![0xF0, 0x90])));
+        let writes = Rc::new(Cell::new(0));
+        memory.map(0x20..0x21, Box::new(Counter(writes.clone())));
+
+        assert_eq!(memory.read(0x00, 3), Ok(vec![0xF0, 0x90, 0x00]));
+        assert_eq!(memory.read(0x10, 3), Ok(vec![1, 2, 3]));
+        assert_eq!(memory.read(0xFF, 2), Err(Violation::OutOfRange(0x100)));
+
+        assert_eq!(
+            memory.write(0x03, &[7, 7, 7]),
+            Err(Violation::ReadOnly(0x03))
+        );
+        assert_eq!(memory.get(0x05), Some(0), "a refused write changes nothing");
+        assert_eq!(
+            memory.write(0xFE, &[7, 7, 7]),
+            Err(Violation::OutOfRange(0x100))
+        );
+        assert_eq!(memory.get(0xFE), Some(0));
+
+        memory.write(0x1F, &[9, 9, 9]).unwrap();
+        assert_eq!(writes.get(), 1);
+        assert_eq!(memory.read(0x1F, 3), Ok(vec![9, 1, 9]));
+        assert_eq!(memory.to_vec().len(), 0x100);
+    }
+}
@@ -0,0 +1,165 @@
+use minifb::Key;
+
+use crate::chip8::display::{HEIGHT, WIDTH};
+use crate::chip8::font::FontStyle;
+use crate::chip8::overlay::{self, GLYPH_HEIGHT};
+
+/// Key that opens and closes the pause menu.
+pub const MENU_KEY: Key = Key::Tab;
+
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+const FONT_STYLES: [FontStyle; 5] = [
+    FontStyle::Vip,
+    FontStyle::Chip48,
+    FontStyle::Schip,
+    FontStyle::Dream6800,
+    FontStyle::Eti660,
+];
+
+/// What the CPU should do after a key press in the menu.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    Resume,
+    Reset,
+    OpenRom,
+    SetFontStyle(FontStyle),
+    Quit,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Page {
+    Main,
+    Settings,
+}
+
+const MAIN: [&str; 5] = ["RESUME", "RESET", "OPEN ROM", "SETTINGS", "QUIT"];
+
+/// The pause menu, drawn over the screen while the program is paused and
+/// driven with the arrow keys, Enter and Tab.
+#[derive(Debug)]
+pub struct Menu {
+    page: Page,
+    selected: usize,
+    font: FontStyle,
+}
+
+impl Menu {
+    pub fn new(font: FontStyle) -> Self {
+        Menu {
+            page: Page::Main,
+            selected: 0,
+            font,
+        }
+    }
+
+    fn items(&self) -> Vec<String> {
+        match self.page {
+            Page::Main => MAIN.iter().map(|item| item.to_string()).collect(),
+            Page::Settings => vec![format!("FONT {}", self.font), "BACK".to_string()],
+        }
+    }
+
+    /// Handle a key going down.
+    pub fn press(&mut self, key: Key) -> Option<Action> {
+        let items = self.items().len();
+        match key {
+            MENU_KEY => return Some(Action::Resume),
+            Key::Up => self.selected = (self.selected + items - 1) % items,
+            Key::Down => self.selected = (self.selected + 1) % items,
+            Key::Left | Key::Right if self.page == Page::Settings && self.selected == 0 => {
+                return Some(self.cycle_font(key == Key::Right));
+            }
+            Key::Enter => return self.choose(),
+            _ => {}
+        }
+        None
+    }
+
+    fn choose(&mut self) -> Option<Action> {
+        match (self.page, self.selected) {
+            (Page::Main, 0) => Some(Action::Resume),
+            (Page::Main, 1) => Some(Action::Reset),
+            (Page::Main, 2) => Some(Action::OpenRom),
+            (Page::Main, 3) => {
+                self.open(Page::Settings, 0);
+                None
+            }
+            (Page::Main, _) => Some(Action::Quit),
+            (Page::Settings, 0) => Some(self.cycle_font(true)),
+            (Page::Settings, _) => {
+                self.open(Page::Main, 3);
+                None
+            }
+        }
+    }
+
+    fn open(&mut self, page: Page, selected: usize) {
+        self.page = page;
+        self.selected = selected;
+    }
+
+    fn cycle_font(&mut self, forward: bool) -> Action {
+        let at = FONT_STYLES
+            .iter()
+            .position(|f| *f == self.font)
+            .unwrap_or(0);
+        let len = FONT_STYLES.len();
+        let next = (if forward { at + 1 } else { at + len - 1 }) % len;
+        self.font = FONT_STYLES[next];
+        Action::SetFontStyle(self.font)
+    }
+
+    /// The menu as a full screen of `fg` text on `bg`.
+    pub fn render(&self, fg: u32, bg: u32) -> Vec<u32> {
+        let mut buffer = vec![bg; WIDTH * HEIGHT];
+        for (i, item) in self.items().iter().enumerate() {
+            let y = 1 + i * LINE_HEIGHT;
+            if i == self.selected {
+                overlay::draw_text(&mut buffer, 1, y, ">", fg);
+            }
+            overlay::draw_text(&mut buffer, 5, y, item, fg);
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation() {
+        let mut menu = Menu::new(FontStyle::Chip48);
+        assert_eq!(menu.press(Key::Up), None);
+        assert_eq!(menu.press(Key::Enter), Some(Action::Quit));
+
+        menu.press(Key::Down);
+        menu.press(Key::Down);
+        assert_eq!(menu.press(Key::Enter), Some(Action::Reset));
+
+        menu.press(Key::Down);
+        menu.press(Key::Down);
+        assert_eq!(menu.press(Key::Enter), None);
+        assert_eq!(
+            menu.press(Key::Right),
+            Some(Action::SetFontStyle(FontStyle::Schip))
+        );
+        assert_eq!(
+            menu.press(Key::Left),
+            Some(Action::SetFontStyle(FontStyle::Chip48))
+        );
+        menu.press(Key::Down);
+        assert_eq!(menu.press(Key::Enter), None);
+        assert_eq!(menu.press(Key::Enter), None);
+        assert_eq!(menu.press(MENU_KEY), Some(Action::Resume));
+    }
+
+    #[test]
+    fn test_render_marks_selection() {
+        let menu = Menu::new(FontStyle::Chip48);
+        let buffer = menu.render(1, 0);
+        // The `>` marker's first column, next to RESUME.
+        assert_eq!(buffer[WIDTH + 1], 1);
+        assert_eq!(buffer[(1 + LINE_HEIGHT) * WIDTH + 1], 0);
+    }
+}
@@ -1,6 +1,64 @@
+pub mod assembler;
+#[cfg(feature = "audio")]
+mod audio;
+pub mod canvas;
+pub mod console;
 mod cpu;
+pub mod debugger;
+pub mod disasm;
+#[cfg(feature = "window")]
 pub mod display;
+pub mod download;
+#[cfg(feature = "window")]
+mod dpi;
+mod error;
+pub mod extension;
+pub mod font;
+pub mod framebuffer;
+pub mod frametime;
+pub mod frontend;
+pub mod gamepad;
+mod gif;
+mod idle;
+#[cfg(feature = "window")]
+pub mod keymap;
+#[cfg(feature = "window")]
+pub mod latency;
+pub mod memory;
+#[cfg(feature = "window")]
+mod menu;
+pub mod movie;
+pub mod octo;
 pub mod opcodes;
+#[cfg(feature = "window")]
+mod overlay;
+pub mod palette;
+#[cfg(feature = "window")]
+mod phosphor;
+pub mod profile;
+pub mod quirks;
 pub mod registers;
+#[cfg(feature = "window")]
+mod remap;
+pub mod romdb;
+mod screenshot;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "sdl2")]
+pub mod sdl;
+#[cfg(feature = "window")]
+pub mod shm;
+pub mod state;
+pub mod stats;
+#[cfg(all(feature = "tui", unix))]
+pub mod terminal;
+pub mod tone;
+pub mod tracelog;
+#[cfg(feature = "window")]
+mod triple;
 
-pub use cpu::CHIP8;
+pub use cpu::{
+    OnHalt, OnUnknown, PcOverflow, StackLimit, CHIP8, DEFAULT_CPU_HZ, DEFAULT_RAM_SIZE,
+    MAX_RAM_SIZE, MIN_RAM_SIZE, STEPS_PER_FRAME,
+};
+pub use error::{Chip8Error, RomTooLarge};
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chip8::frontend::KeypadState;
+
+/// A recorded run: the keypad frame by frame, with what else decides how the
+/// program plays out, so replaying it from power on repeats the run exactly.
+/// Frames are 60ths of a second of emulated time, not of the window's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Movie {
+    /// The seed of the random numbers RND draws from.
+    pub seed: u64,
+    pub cpu_hz: u32,
+    /// How many frames were recorded.
+    pub frames: u64,
+    /// The frames the keypad changed in, with the keys down from then on.
+    pub inputs: Vec<(u64, KeypadState)>,
+}
+
+impl Movie {
+    pub fn new(seed: u64, cpu_hz: u32) -> Self {
+        Movie {
+            seed,
+            cpu_hz,
+            frames: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+/// Where the keys the program sees come from.
+#[derive(Debug)]
+pub(crate) enum Input {
+    /// The frontend, as they are pressed.
+    Live,
+    /// The frontend, taken once a frame and written down.
+    Recording { movie: Movie, keys: KeypadState },
+    /// A movie, until it runs out and the frontend takes over.
+    Replaying {
+        movie: Movie,
+        frame: u64,
+        next: usize,
+        keys: KeypadState,
+    },
+}
+
+impl Input {
+    /// Start a frame, reading the frontend's keys with `live` if they are
+    /// to be recorded.
+    pub fn next_frame(&mut self, live: impl FnOnce() -> KeypadState) {
+        match self {
+            Input::Live => {}
+            Input::Recording { movie, keys } => {
+                let now = live();
+                if now != *keys || movie.frames == 0 {
+                    movie.inputs.push((movie.frames, now));
+                    *keys = now;
+                }
+                movie.frames += 1;
+            }
+            Input::Replaying {
+                movie,
+                frame,
+                next,
+                keys,
+            } => {
+                if *frame >= movie.frames {
+                    *self = Input::Live;
+                    return;
+                }
+                while let Some(&(at, now)) = movie.inputs.get(*next) {
+                    if at > *frame {
+                        break;
+                    }
+                    *keys = now;
+                    *next += 1;
+                }
+                *frame += 1;
+            }
+        }
+    }
+
+    /// The keys the program sees, given those down on the frontend now.
+    pub fn keys(&self, live: KeypadState) -> KeypadState {
+        match self {
+            Input::Live => live,
+            Input::Recording { keys, .. } | Input::Replaying { keys, .. } => *keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let pressed = [0, 0, 1, 1, 0];
+        let mut input = Input::Recording {
+            movie: Movie::new(7, 600),
+            keys: KeypadState::default(),
+        };
+        for &down in &pressed {
+            let live = if down == 1 {
+                KeypadState::default().with(0x5)
+            } else {
+                KeypadState::default()
+            };
+            input.next_frame(|| live);
+            assert_eq!(input.keys(KeypadState::default()), live);
+        }
+        let movie = match input {
+            Input::Recording { movie, .. } => movie,
+            _ => unreachable!(),
+        };
+        assert_eq!(movie.frames, 5);
+        assert_eq!(movie.inputs.len(), 3);
+
+        let mut input = Input::Replaying {
+            movie,
+            frame: 0,
+            next: 0,
+            keys: KeypadState::default(),
+        };
+        let mut replayed = Vec::new();
+        for _ in &pressed {
+            input.next_frame(|| unreachable!());
+            replayed.push(input.keys(KeypadState::default()).is_down(0x5) as i32);
+        }
+        assert_eq!(replayed, pressed);
+
+        // The movie is over, so the frontend's keys count again.
+        input.next_frame(KeypadState::default);
+        let live = KeypadState::default().with(0x1);
+        assert_eq!(input.keys(live), live);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::chip8::assembler;
+
+/// Program metadata in the format Octo and the CHIP-8 archive use to describe
+/// a game: who made it and the options it expects to be run with.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Metadata {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(alias = "description")]
+    pub desc: Option<String>,
+    #[serde(default)]
+    pub options: Options,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Instructions per 60th of a second.
+    pub tickrate: Option<u32>,
+    pub fill_color: Option<String>,
+    pub background_color: Option<String>,
+    /// Whether sprites are clipped at the edges of the screen rather than
+    /// wrapped.
+    pub clip_quirks: Option<bool>,
+    /// Whether `SHR` and `SHL` shift VX in place rather than VY into VX.
+    pub shift_quirks: Option<bool>,
+    /// Whether `LD [I], Vx` and `LD Vx, [I]` leave I unchanged.
+    pub load_store_quirks: Option<bool>,
+}
+
+impl Metadata {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// The metadata stored next to `rom` as `<stem>.json`, if there is any.
+    pub fn for_rom(rom: &Path) -> Option<io::Result<Self>> {
+        let path = rom.with_extension("json");
+        if !path.is_file() {
+            return None;
+        }
+        Some(fs::read_to_string(&path).and_then(|json| {
+            Metadata::from_json(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }))
+    }
+
+    /// "Title by Author" when a title is known.
+    pub fn display_name(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        if self.authors.is_empty() {
+            Some(title.clone())
+        } else {
+            Some(format!("{} by {}", title, self.authors.join(", ")))
+        }
+    }
+}
+
+/// An Octo cartridge: a GIF whose pixels hide a program's source and the
+/// options it runs with, two bits in the low bits of each palette index.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cartridge {
+    #[serde(default)]
+    pub options: Options,
+    /// The program's Octo source.
+    pub program: String,
+}
+
+impl Cartridge {
+    /// Whether `path` looks like a cartridge, by its extension.
+    pub fn is_cartridge(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+    }
+
+    /// Extract the cartridge hidden in the GIF `bytes`.
+    pub fn read(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options
+            .read_info(bytes)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        // Each byte is spread over four pixels, high bits first.
+        let mut payload = Vec::new();
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|e| invalid(e.to_string()))?
+        {
+            payload.extend(
+                frame
+                    .buffer
+                    .chunks_exact(4)
+                    .map(|pixels| pixels.iter().fold(0, |byte, pixel| byte << 2 | (pixel & 3))),
+            );
+        }
+        // A 32 bit length, then the JSON.
+        let json = match payload.get(..4) {
+            Some(len) => {
+                let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+                payload
+                    .get(4..4 + len)
+                    .ok_or_else(|| invalid("the cartridge is cut short".into()))?
+            }
+            None => return Err(invalid("not an Octo cartridge".into())),
+        };
+        serde_json::from_slice(json).map_err(|e| invalid(format!("not an Octo cartridge: {}", e)))
+    }
+
+    /// The cartridge's program, assembled.
+    pub fn assemble(&self) -> io::Result<Vec<u8>> {
+        assembler::assemble(&self.program)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Metadata for the cartridge, which has no title of its own, named
+    /// `title`.
+    pub fn metadata(&self, title: &str) -> Metadata {
+        Metadata {
+            title: Some(title.to_string()),
+            options: self.options.clone(),
+            ..Metadata::default()
+        }
+    }
+}
+
+/// Parse an Octo `#RRGGBB` (or `#RGB`) color into a framebuffer pixel.
+pub fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    match hex.len() {
+        6 => u32::from_str_radix(hex, 16).ok(),
+        3 => {
+            let rgb = u32::from_str_radix(hex, 16).ok()?;
+            let (r, g, b) = ((rgb >> 8) & 0xF, (rgb >> 4) & 0xF, rgb & 0xF);
+            Some((r * 0x11) << 16 | (g * 0x11) << 8 | (b * 0x11))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_entry() {
+        let meta = Metadata::from_json(
+            r##"{
+                "title": "Br8kout",
+                "authors": ["SharpenedSpoon"],
+                "desc": "Breakout for the CHIP-8.",
+                "event": "OctoJam 1",
+                "options": {
+                    "tickrate": 7,
+                    "fillColor": "#FFAA00",
+                    "backgroundColor": "#000"
+                }
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(meta.display_name().unwrap(), "Br8kout by SharpenedSpoon");
+        assert_eq!(meta.desc.as_deref(), Some("Breakout for the CHIP-8."));
+        assert_eq!(meta.options.tickrate, Some(7));
+        assert_eq!(
+            parse_color(meta.options.fill_color.as_ref().unwrap()),
+            Some(0xFFAA00)
+        );
+        assert_eq!(
+            parse_color(meta.options.background_color.as_ref().unwrap()),
+            Some(0)
+        );
+        assert_eq!(parse_color("#abc"), Some(0xAABBCC));
+        assert_eq!(parse_color("red"), None);
+    }
+
+    /// A cartridge as Octo would save it: `payload` in the low two bits of
+    /// each pixel of 32x16 frames.
+    fn cartridge_gif(payload: &[u8]) -> Vec<u8> {
+        let mut pixels: Vec<u8> = payload
+            .iter()
+            .flat_map(|byte| (0..4).rev().map(move |i| 0x10 | (byte >> (i * 2) & 3)))
+            .collect();
+        pixels.resize(pixels.len().div_ceil(512) * 512, 0);
+
+        let mut gif = Vec::new();
+        let palette: Vec<u8> = (0..=255).flat_map(|i| [i, i, i]).collect();
+        let mut encoder = gif::Encoder::new(&mut gif, 32, 16, &palette).unwrap();
+        for frame in pixels.chunks(512) {
+            encoder
+                .write_frame(&gif::Frame::from_indexed_pixels(32, 16, frame, None))
+                .unwrap();
+        }
+        drop(encoder);
+        gif
+    }
+
+    #[test]
+    fn test_cartridge() {
+        let json = br##"{"options": {"tickrate": 20, "fillColor": "#FF0000", "shiftQuirks": true},
+            "program": ": main\n  v0 := 1\n  jump main\n"}"##;
+        let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+        payload.extend_from_slice(json);
+
+        let cart = Cartridge::read(&cartridge_gif(&payload)).unwrap();
+        assert_eq!(cart.options.tickrate, Some(20));
+        assert_eq!(cart.options.shift_quirks, Some(true));
+        assert_eq!(cart.assemble().unwrap(), [0x60, 0x01, 0x12, 0x00]);
+        let meta = cart.metadata("pong");
+        assert_eq!(meta.display_name().unwrap(), "pong");
+        assert_eq!(meta.options.fill_color.as_deref(), Some("#FF0000"));
+
+        assert!(Cartridge::read(&cartridge_gif(&payload[..20])).is_err());
+        assert!(Cartridge::read(b"not a gif").is_err());
+        assert!(Cartridge::is_cartridge(Path::new("carts/pong.GIF")));
+    }
+}
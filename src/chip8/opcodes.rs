@@ -1,7 +1,8 @@
+use std::error::Error;
+use std::fmt;
 use std::ops::Deref;
 
 use either::Either;
-use minifb::Key;
 
 pub type Address = u16;
 pub type Nibble = u8;
@@ -18,12 +19,23 @@ impl Deref for VxyRegister {
     }
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
+impl fmt::Display for VxyRegister {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "V{:X}", self.0)
+    }
+}
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Instruction {
     SYS(Address), // Ignored?
     CLS,
     RET,
+    SCD(Nibble),
+    SCR,
+    SCL,
+    LOW,
+    HIGH,
     JP(Address),
     JP_V0(Address),
     CALL(Address),
@@ -36,22 +48,257 @@ pub enum Instruction {
     OR(VxyRegister, VxyRegister),
     AND(VxyRegister, VxyRegister),
     XOR(VxyRegister, VxyRegister),
-    SHR(VxyRegister),
-    SHL(VxyRegister),
+    SHR(VxyRegister, VxyRegister),
+    SHL(VxyRegister, VxyRegister),
     RND(VxyRegister, u8),
     DRW(VxyRegister, VxyRegister, Nibble),
     SKP(VxyRegister),
     SKNP(VxyRegister),
     LD(VxyRegister, Either<VxyRegister, u8>),
     LD_I(Address),
+    /// XO-CHIP: load I with the 16-bit word following the opcode.
+    LD_I_LONG,
     LD_Vx_DT(VxyRegister),
     LD_Vx_K(VxyRegister),
     LD_DT_Vx(VxyRegister),
     LD_ST_Vx(VxyRegister),
     LD_F(VxyRegister),
+    /// SCHIP: point I at the big glyph for the digit in Vx.
+    LD_HF(VxyRegister),
     LD_B(VxyRegister),
     LD_I_Vx(VxyRegister),
     LD_Vx_I(VxyRegister),
+    /// SCHIP: save V0 to Vx in the RPL user flags.
+    LD_R_Vx(VxyRegister),
+    /// SCHIP: restore V0 to Vx from the RPL user flags.
+    LD_Vx_R(VxyRegister),
+    /// XO-CHIP: load the 16 bytes at I into the audio pattern buffer.
+    AUDIO,
+    /// XO-CHIP: set the pitch the audio pattern plays at from Vx.
+    LD_PITCH_Vx(VxyRegister),
+    /// XO-CHIP: store Vx to Vy at I, leaving I alone.
+    SAVE(VxyRegister, VxyRegister),
+    /// XO-CHIP: read Vx to Vy from I, leaving I alone.
+    LOAD(VxyRegister, VxyRegister),
+}
+
+/// An opcode that is not a known instruction.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct UnknownOpcode(pub u16);
+
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown opcode {:04X}", self.0)
+    }
+}
+
+impl Error for UnknownOpcode {}
+
+impl Instruction {
+    /// The opcode for this instruction, which `decode` turns back into it,
+    /// or `None` if there is none: an operand is too wide for its field, or
+    /// `SYS` has the address of another `0NNN` instruction.
+    /// `LD_I_LONG`'s address is the word after it, which is not part of the
+    /// opcode.
+    pub fn encode(&self) -> Option<u16> {
+        let xy = |vx: &VxyRegister, vy: &VxyRegister| {
+            u16::from(**vx & 0xF) << 8 | u16::from(**vy & 0xF) << 4
+        };
+        let x = |vx: &VxyRegister| u16::from(**vx & 0xF) << 8;
+        let either = |vx, other: &Either<VxyRegister, u8>, reg, byte| match other {
+            Either::Left(vy) => reg | xy(vx, vy),
+            Either::Right(nn) => byte | x(vx) | u16::from(*nn),
+        };
+        // Operands are cut to fit their fields, and what does not decode
+        // back to `self` was cut or stands for another instruction.
+        let opcode = match self {
+            Instruction::SYS(addr) => addr & 0xFFF,
+            Instruction::CLS => 0x00E0,
+            Instruction::RET => 0x00EE,
+            Instruction::SCD(n) => 0x00C0 | u16::from(n & 0xF),
+            Instruction::SCR => 0x00FB,
+            Instruction::SCL => 0x00FC,
+            Instruction::LOW => 0x00FE,
+            Instruction::HIGH => 0x00FF,
+            Instruction::JP(addr) => 0x1000 | addr & 0xFFF,
+            Instruction::CALL(addr) => 0x2000 | addr & 0xFFF,
+            Instruction::SE(vx, other) => either(vx, other, 0x5000, 0x3000),
+            Instruction::SNE(vx, other) => either(vx, other, 0x9000, 0x4000),
+            Instruction::SAVE(vx, vy) => 0x5002 | xy(vx, vy),
+            Instruction::LOAD(vx, vy) => 0x5003 | xy(vx, vy),
+            Instruction::LD(vx, other) => either(vx, other, 0x8000, 0x6000),
+            Instruction::ADD(vx, other) => either(vx, other, 0x8004, 0x7000),
+            Instruction::OR(vx, vy) => 0x8001 | xy(vx, vy),
+            Instruction::AND(vx, vy) => 0x8002 | xy(vx, vy),
+            Instruction::XOR(vx, vy) => 0x8003 | xy(vx, vy),
+            Instruction::SUB(vx, vy) => 0x8005 | xy(vx, vy),
+            Instruction::SHR(vx, vy) => 0x8006 | xy(vx, vy),
+            Instruction::SUBN(vx, vy) => 0x8007 | xy(vx, vy),
+            Instruction::SHL(vx, vy) => 0x800E | xy(vx, vy),
+            Instruction::LD_I(addr) => 0xA000 | addr & 0xFFF,
+            Instruction::JP_V0(addr) => 0xB000 | addr & 0xFFF,
+            Instruction::RND(vx, nn) => 0xC000 | x(vx) | u16::from(*nn),
+            Instruction::DRW(vx, vy, n) => 0xD000 | xy(vx, vy) | u16::from(n & 0xF),
+            Instruction::SKP(vx) => 0xE09E | x(vx),
+            Instruction::SKNP(vx) => 0xE0A1 | x(vx),
+            Instruction::LD_I_LONG => 0xF000,
+            Instruction::AUDIO => 0xF002,
+            Instruction::LD_Vx_DT(vx) => 0xF007 | x(vx),
+            Instruction::LD_Vx_K(vx) => 0xF00A | x(vx),
+            Instruction::LD_DT_Vx(vx) => 0xF015 | x(vx),
+            Instruction::LD_ST_Vx(vx) => 0xF018 | x(vx),
+            Instruction::ADD_I(vx) => 0xF01E | x(vx),
+            Instruction::LD_F(vx) => 0xF029 | x(vx),
+            Instruction::LD_HF(vx) => 0xF030 | x(vx),
+            Instruction::LD_B(vx) => 0xF033 | x(vx),
+            Instruction::LD_PITCH_Vx(vx) => 0xF03A | x(vx),
+            Instruction::LD_I_Vx(vx) => 0xF055 | x(vx),
+            Instruction::LD_Vx_I(vx) => 0xF065 | x(vx),
+            Instruction::LD_R_Vx(vx) => 0xF075 | x(vx),
+            Instruction::LD_Vx_R(vx) => 0xF085 | x(vx),
+        };
+        Some(opcode).filter(|&opcode| decode(opcode) == Ok(*self))
+    }
+}
+
+/// Decode `bytes` into the instruction it stands for. Every `u16` either
+/// decodes or is an `UnknownOpcode`, without panicking, so arbitrary data
+/// can be fed to it, e.g. by a fuzzer or a disassembler.
+pub fn decode(bytes: u16) -> Result<Instruction, UnknownOpcode> {
+    let unknown = Err(UnknownOpcode(bytes));
+    let instr = match get_first(bytes) {
+        0x0 => match bytes {
+            0x00E0 => Instruction::CLS,
+            0x00EE => Instruction::RET,
+            0x00C0..=0x00CF => Instruction::SCD(get_nibble(bytes)),
+            0x00FB => Instruction::SCR,
+            0x00FC => Instruction::SCL,
+            0x00FE => Instruction::LOW,
+            0x00FF => Instruction::HIGH,
+            _ => Instruction::SYS(get_addr(bytes)),
+        },
+        0x1 => Instruction::JP(get_addr(bytes)),
+        0x2 => Instruction::CALL(get_addr(bytes)),
+        0x3 => Instruction::SE(get_vx(bytes), Either::Right(get_byte(bytes))),
+        0x4 => Instruction::SNE(get_vx(bytes), Either::Right(get_byte(bytes))),
+        0x5 => match get_nibble(bytes) {
+            0x0 => Instruction::SE(get_vx(bytes), Either::Left(get_vy(bytes))),
+            0x2 => Instruction::SAVE(get_vx(bytes), get_vy(bytes)),
+            0x3 => Instruction::LOAD(get_vx(bytes), get_vy(bytes)),
+            _ => return unknown,
+        },
+        0x6 => Instruction::LD(get_vx(bytes), Either::Right(get_byte(bytes))),
+        0x7 => Instruction::ADD(get_vx(bytes), Either::Right(get_byte(bytes))),
+        0x8 => match get_nibble(bytes) {
+            0x0 => Instruction::LD(get_vx(bytes), Either::Left(get_vy(bytes))),
+            0x1 => Instruction::OR(get_vx(bytes), get_vy(bytes)),
+            0x2 => Instruction::AND(get_vx(bytes), get_vy(bytes)),
+            0x3 => Instruction::XOR(get_vx(bytes), get_vy(bytes)),
+            0x4 => Instruction::ADD(get_vx(bytes), Either::Left(get_vy(bytes))),
+            0x5 => Instruction::SUB(get_vx(bytes), get_vy(bytes)),
+            0x6 => Instruction::SHR(get_vx(bytes), get_vy(bytes)),
+            0x7 => Instruction::SUBN(get_vx(bytes), get_vy(bytes)),
+            0xE => Instruction::SHL(get_vx(bytes), get_vy(bytes)),
+            _ => return unknown,
+        },
+        0x9 => match get_nibble(bytes) {
+            0x0 => Instruction::SNE(get_vx(bytes), Either::Left(get_vy(bytes))),
+            _ => return unknown,
+        },
+        0xA => Instruction::LD_I(get_addr(bytes)),
+        0xB => Instruction::JP_V0(get_addr(bytes)),
+        0xC => Instruction::RND(get_vx(bytes), get_byte(bytes)),
+        0xD => Instruction::DRW(get_vx(bytes), get_vy(bytes), get_nibble(bytes)),
+        0xE => match bytes.to_be_bytes()[1] {
+            0x9E => Instruction::SKP(get_vx(bytes)),
+            0xA1 => Instruction::SKNP(get_vx(bytes)),
+            _ => return unknown,
+        },
+        // 0xF, the only first nibble left.
+        _ if bytes == 0xF000 => Instruction::LD_I_LONG,
+        _ if bytes == 0xF002 => Instruction::AUDIO,
+        _ => match bytes.to_be_bytes()[1] {
+            0x07 => Instruction::LD_Vx_DT(get_vx(bytes)),
+            0x0A => Instruction::LD_Vx_K(get_vx(bytes)),
+            0x15 => Instruction::LD_DT_Vx(get_vx(bytes)),
+            0x18 => Instruction::LD_ST_Vx(get_vx(bytes)),
+            0x1E => Instruction::ADD_I(get_vx(bytes)),
+            0x29 => Instruction::LD_F(get_vx(bytes)),
+            0x30 => Instruction::LD_HF(get_vx(bytes)),
+            0x3A => Instruction::LD_PITCH_Vx(get_vx(bytes)),
+            0x33 => Instruction::LD_B(get_vx(bytes)),
+            0x55 => Instruction::LD_I_Vx(get_vx(bytes)),
+            0x65 => Instruction::LD_Vx_I(get_vx(bytes)),
+            0x75 => Instruction::LD_R_Vx(get_vx(bytes)),
+            0x85 => Instruction::LD_Vx_R(get_vx(bytes)),
+            _ => return unknown,
+        },
+    };
+    Ok(instr)
+}
+
+/// Formats a register-or-byte operand as `Vy` or `0xNN`.
+struct Operand<'a>(&'a Either<VxyRegister, u8>);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Either::Left(reg) => write!(f, "{}", reg),
+            Either::Right(byte) => write!(f, "0x{:02X}", byte),
+        }
+    }
+}
+
+/// Disassembles to the mnemonics of Cowgod's Chip-8 technical reference.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::SYS(addr) => write!(f, "SYS 0x{:03X}", addr),
+            Instruction::CLS => write!(f, "CLS"),
+            Instruction::RET => write!(f, "RET"),
+            Instruction::SCD(nibble) => write!(f, "SCD {}", nibble),
+            Instruction::SCR => write!(f, "SCR"),
+            Instruction::SCL => write!(f, "SCL"),
+            Instruction::LOW => write!(f, "LOW"),
+            Instruction::HIGH => write!(f, "HIGH"),
+            Instruction::JP(addr) => write!(f, "JP 0x{:03X}", addr),
+            Instruction::JP_V0(addr) => write!(f, "JP V0, 0x{:03X}", addr),
+            Instruction::CALL(addr) => write!(f, "CALL 0x{:03X}", addr),
+            Instruction::SE(vx, other) => write!(f, "SE {}, {}", vx, Operand(other)),
+            Instruction::SNE(vx, other) => write!(f, "SNE {}, {}", vx, Operand(other)),
+            Instruction::ADD(vx, other) => write!(f, "ADD {}, {}", vx, Operand(other)),
+            Instruction::ADD_I(vx) => write!(f, "ADD I, {}", vx),
+            Instruction::SUB(vx, vy) => write!(f, "SUB {}, {}", vx, vy),
+            Instruction::SUBN(vx, vy) => write!(f, "SUBN {}, {}", vx, vy),
+            Instruction::OR(vx, vy) => write!(f, "OR {}, {}", vx, vy),
+            Instruction::AND(vx, vy) => write!(f, "AND {}, {}", vx, vy),
+            Instruction::XOR(vx, vy) => write!(f, "XOR {}, {}", vx, vy),
+            Instruction::SHR(vx, vy) => write!(f, "SHR {}, {}", vx, vy),
+            Instruction::SHL(vx, vy) => write!(f, "SHL {}, {}", vx, vy),
+            Instruction::RND(vx, byte) => write!(f, "RND {}, 0x{:02X}", vx, byte),
+            Instruction::DRW(vx, vy, nibble) => write!(f, "DRW {}, {}, {}", vx, vy, nibble),
+            Instruction::SKP(vx) => write!(f, "SKP {}", vx),
+            Instruction::SKNP(vx) => write!(f, "SKNP {}", vx),
+            Instruction::LD(vx, other) => write!(f, "LD {}, {}", vx, Operand(other)),
+            Instruction::LD_I(addr) => write!(f, "LD I, 0x{:03X}", addr),
+            Instruction::LD_I_LONG => write!(f, "LD I, long"),
+            Instruction::LD_Vx_DT(vx) => write!(f, "LD {}, DT", vx),
+            Instruction::LD_Vx_K(vx) => write!(f, "LD {}, K", vx),
+            Instruction::LD_DT_Vx(vx) => write!(f, "LD DT, {}", vx),
+            Instruction::LD_ST_Vx(vx) => write!(f, "LD ST, {}", vx),
+            Instruction::LD_F(vx) => write!(f, "LD F, {}", vx),
+            Instruction::LD_HF(vx) => write!(f, "LD HF, {}", vx),
+            Instruction::LD_B(vx) => write!(f, "LD B, {}", vx),
+            Instruction::LD_I_Vx(vx) => write!(f, "LD [I], {}", vx),
+            Instruction::LD_Vx_I(vx) => write!(f, "LD {}, [I]", vx),
+            Instruction::LD_R_Vx(vx) => write!(f, "LD R, {}", vx),
+            Instruction::LD_Vx_R(vx) => write!(f, "LD {}, R", vx),
+            Instruction::AUDIO => write!(f, "AUDIO"),
+            Instruction::LD_PITCH_Vx(vx) => write!(f, "LD PITCH, {}", vx),
+            Instruction::SAVE(vx, vy) => write!(f, "LD [I], {}-{}", vx, vy),
+            Instruction::LOAD(vx, vy) => write!(f, "LD {}-{}, [I]", vx, vy),
+        }
+    }
 }
 
 pub fn get_first(bytes: OPcode) -> u8 {
@@ -78,48 +325,16 @@ pub fn get_byte(bytes: OPcode) -> u8 {
     (bytes & 0x00FF) as u8
 }
 
-pub fn map_key_to_u8(key: Key) -> Option<u8> {
-    match key {
-        Key::Key1 => Some(0x1),
-        Key::Key2 => Some(0x2),
-        Key::Key3 => Some(0x3),
-        Key::Key4 => Some(0xC),
-        Key::Q => Some(0x4),
-        Key::W => Some(0x5),
-        Key::E => Some(0x6),
-        Key::R => Some(0xD),
-        Key::A => Some(0x7),
-        Key::S => Some(0x8),
-        Key::D => Some(0x9),
-        Key::F => Some(0xE),
-        Key::Z => Some(0xA),
-        Key::X => Some(0x0),
-        Key::C => Some(0xB),
-        Key::V => Some(0xF),
-        _ => None,
-    }
-}
-
-pub fn map_u8_to_key(val: u8) -> Option<Key> {
-    match val {
-        0x1 => Some(Key::Key1),
-        0x2 => Some(Key::Key2),
-        0x3 => Some(Key::Key3),
-        0xC => Some(Key::Key4),
-        0x4 => Some(Key::Q),
-        0x5 => Some(Key::W),
-        0x6 => Some(Key::E),
-        0xD => Some(Key::R),
-        0x7 => Some(Key::A),
-        0x8 => Some(Key::S),
-        0x9 => Some(Key::D),
-        0xE => Some(Key::F),
-        0xA => Some(Key::Z),
-        0x0 => Some(Key::X),
-        0xB => Some(Key::C),
-        0xF => Some(Key::V),
-        _ => None,
-    }
+/// The registers from `vx` to `vy` inclusive, counting down when `vy` comes
+/// first, as XO-CHIP's register range save and load do.
+pub fn register_range(vx: VxyRegister, vy: VxyRegister) -> impl Iterator<Item = VxyRegister> {
+    let (x, y) = (*vx, *vy);
+    let range = if x <= y {
+        Either::Left(x..=y)
+    } else {
+        Either::Right((y..=x).rev())
+    };
+    range.map(VxyRegister)
 }
 
 pub fn to_bcd(byte: u8) -> [u8; 3] {
@@ -145,6 +360,112 @@ mod tests {
         assert_eq!(get_byte(TESTCODE), 0x34)
     }
 
+    #[test]
+    fn test_decode_every_opcode() {
+        for opcode in 0..=u16::MAX {
+            match decode(opcode) {
+                Ok(instr) => assert!(!instr.to_string().is_empty()),
+                Err(e) => assert_eq!(e, UnknownOpcode(opcode)),
+            }
+        }
+        assert_eq!(decode(0x00E0), Ok(Instruction::CLS));
+        assert_eq!(decode(0x8008), Err(UnknownOpcode(0x8008)));
+        assert_eq!(decode(0x9121), Err(UnknownOpcode(0x9121)));
+        assert_eq!(
+            decode(0xE0A2).unwrap_err().to_string(),
+            "unknown opcode E0A2"
+        );
+    }
+
+    #[test]
+    fn test_encode_round_trips() {
+        for opcode in 0..=u16::MAX {
+            if let Ok(instr) = decode(opcode) {
+                let encoded = instr.encode().map(decode);
+                assert_eq!(encoded, Some(Ok(instr)), "{:04X}", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_rejects_what_does_not_fit() {
+        let v = VxyRegister;
+        let fits = [
+            Instruction::SYS(0x123),
+            Instruction::JP(0xFFF),
+            Instruction::SE(v(0xF), Either::Right(0xFF)),
+            Instruction::DRW(v(1), v(2), 0xF),
+            Instruction::SCD(0xF),
+            Instruction::LD_R_Vx(v(7)),
+        ];
+        for instr in fits {
+            assert_eq!(instr.encode().map(decode), Some(Ok(instr)), "{}", instr);
+        }
+        let cannot = [
+            // The opcodes of CLS, RET and the SUPER-CHIP instructions.
+            Instruction::SYS(0x0E0),
+            Instruction::SYS(0x0EE),
+            Instruction::SYS(0x0FF),
+            Instruction::SYS(0x1000),
+            Instruction::JP(0x1200),
+            Instruction::DRW(v(0x11), v(2), 0xF),
+            Instruction::DRW(v(1), v(2), 0x1F),
+            Instruction::SCD(0x10),
+            Instruction::LD(v(1), Either::Left(v(0x10))),
+            Instruction::LD_F(v(0x10)),
+        ];
+        for instr in cannot {
+            assert_eq!(instr.encode(), None, "{:?}", instr);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Instruction::CLS.to_string(), "CLS");
+        assert_eq!(Instruction::JP_V0(0x2A4).to_string(), "JP V0, 0x2A4");
+        assert_eq!(
+            Instruction::SE(VxyRegister(0xA), Either::Right(0x0F)).to_string(),
+            "SE VA, 0x0F"
+        );
+        assert_eq!(
+            Instruction::LD(VxyRegister(1), Either::Left(VxyRegister(2))).to_string(),
+            "LD V1, V2"
+        );
+        assert_eq!(
+            Instruction::DRW(VxyRegister(0), VxyRegister(1), 5).to_string(),
+            "DRW V0, V1, 5"
+        );
+        assert_eq!(Instruction::SCD(4).to_string(), "SCD 4");
+        assert_eq!(
+            decode(0xF730).map(|instr| instr.to_string()),
+            Ok("LD HF, V7".to_string())
+        );
+        assert_eq!(
+            [0xF002, 0xF53A, 0xF102].map(|op| decode(op).map(|i| i.to_string()).ok()),
+            [
+                Some("AUDIO".to_string()),
+                Some("LD PITCH, V5".to_string()),
+                None
+            ]
+        );
+        assert_eq!(
+            Instruction::LD_I_Vx(VxyRegister(3)).to_string(),
+            "LD [I], V3"
+        );
+    }
+
+    #[test]
+    fn test_register_range() {
+        let range = |x, y| -> Vec<u8> {
+            register_range(VxyRegister(x), VxyRegister(y))
+                .map(|reg| *reg)
+                .collect()
+        };
+        assert_eq!(range(2, 4), [2, 3, 4]);
+        assert_eq!(range(4, 2), [4, 3, 2]);
+        assert_eq!(range(7, 7), [7]);
+    }
+
     #[test]
     fn test_bcd() {
         assert_eq!(to_bcd(255), [2, 5, 5]);
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use crate::chip8::display;
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+/// Horizontal distance between the starts of two characters.
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// A 3x5 glyph for the characters overlays use, one row per byte, leftmost
+/// pixel in bit 2. Lower case is drawn as upper case.
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        ' ' => [0; GLYPH_HEIGHT],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// Draw `text` with its top-left corner at (`x`, `y`). Characters without a
+/// glyph are left blank and anything off screen is clipped.
+pub fn draw_text(buffer: &mut [u32], x: usize, y: usize, text: &str, color: u32) {
+    for (i, c) in text.chars().enumerate() {
+        let rows = glyph(c).unwrap_or([0; GLYPH_HEIGHT]);
+        for (dy, row) in rows.iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                if row & (0b100 >> dx) != 0 {
+                    set(buffer, x + i * ADVANCE + dx, y + dy, color);
+                }
+            }
+        }
+    }
+}
+
+/// Fill the `width` by `height` rectangle at (`x`, `y`), clipped to the screen.
+pub fn fill_rect(buffer: &mut [u32], x: usize, y: usize, width: usize, height: usize, color: u32) {
+    for py in y..y + height {
+        for px in x..x + width {
+            set(buffer, px, py, color);
+        }
+    }
+}
+
+/// How long a toast stays on screen.
+pub const TOAST_DURATION: Duration = Duration::from_secs(1);
+
+/// Draw `text` as a notification strip along the bottom of `frame`, cut to
+/// the characters that fit.
+pub fn draw_toast(frame: &mut [u32], text: &str, fg: u32, bg: u32) {
+    let (width, height) = display::resolution(frame);
+    let y = height - GLYPH_HEIGHT - 2;
+    let fits: String = text.chars().take(width / ADVANCE).collect();
+    fill_rect(frame, 0, y, width, GLYPH_HEIGHT + 2, bg);
+    draw_text(frame, 1, y + 1, &fits, fg);
+}
+
+/// Draw `text` in a box just big enough for it at the top left of `frame`,
+/// e.g. a counter.
+pub fn draw_label(frame: &mut [u32], text: &str, fg: u32, bg: u32) {
+    let width = text.chars().count() * ADVANCE + 1;
+    fill_rect(frame, 0, 0, width, GLYPH_HEIGHT + 2, bg);
+    draw_text(frame, 1, 1, text, fg);
+}
+
+/// A speaker with a sound wave, one row per byte, leftmost pixel in bit 5.
+const SPEAKER: [u8; 5] = [0b000100, 0b001101, 0b111101, 0b001101, 0b000100];
+
+/// Draw a speaker in a box at the top right of `frame`, to show the buzzer
+/// is sounding.
+pub fn draw_speaker(frame: &mut [u32], fg: u32, bg: u32) {
+    let (width, _) = display::resolution(frame);
+    let x = width - 8;
+    fill_rect(frame, x, 0, 8, SPEAKER.len() + 2, bg);
+    for (dy, row) in SPEAKER.iter().enumerate() {
+        for dx in 0..6 {
+            if row & (0b100000 >> dx) != 0 {
+                set(frame, x + 1 + dx, 1 + dy, fg);
+            }
+        }
+    }
+}
+
+/// Draw a border around the edge of `frame`, a 64th of its width thick.
+pub fn draw_border(frame: &mut [u32], color: u32) {
+    let (width, height) = display::resolution(frame);
+    let thickness = width / display::WIDTH;
+    fill_rect(frame, 0, 0, width, thickness, color);
+    fill_rect(frame, 0, height - thickness, width, thickness, color);
+    fill_rect(frame, 0, 0, thickness, height, color);
+    fill_rect(frame, width - thickness, 0, thickness, height, color);
+}
+
+/// The frames and instructions a second, short enough to fit across the
+/// 64 pixel screen.
+pub fn speed_label(fps: u64, ips: u64) -> String {
+    if ips >= 10_000 {
+        format!("{} FPS {}K IPS", fps, ips / 1000)
+    } else {
+        format!("{} FPS {} IPS", fps, ips)
+    }
+}
+
+fn set(buffer: &mut [u32], x: usize, y: usize, color: u32) {
+    let (width, height) = display::resolution(buffer);
+    if x < width && y < height {
+        buffer[y * width + x] = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::display::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+
+    #[test]
+    fn test_draw_text() {
+        let mut buffer = [0; WIDTH * HEIGHT];
+        draw_text(&mut buffer, 62, 0, "T1", 1);
+        // The T's top bar is clipped after two pixels, the 1 entirely.
+        assert_eq!(buffer[..WIDTH].iter().sum::<u32>(), 2);
+        assert_eq!(buffer[WIDTH + 63], 1);
+
+        let mut buffer = [0; WIDTH * HEIGHT];
+        draw_text(&mut buffer, 0, 0, "l", 1);
+        assert_eq!(buffer[4 * WIDTH..4 * WIDTH + 3], [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_toast_covers_bottom_strip() {
+        let mut frame = [1; WIDTH * HEIGHT];
+        draw_toast(&mut frame, "State saved to slot 2", 1, 0);
+        assert_eq!(frame[(HEIGHT - 8) * WIDTH], 1);
+        assert_eq!(frame[(HEIGHT - 7) * WIDTH], 0);
+        assert_eq!(frame[(HEIGHT - 1) * WIDTH + 63], 0);
+    }
+
+    #[test]
+    fn test_sound_indicators() {
+        let mut frame = [0; WIDTH * HEIGHT];
+        draw_speaker(&mut frame, 1, 0);
+        assert_eq!(frame[3 * WIDTH + 57..3 * WIDTH + 63], [1, 1, 1, 1, 0, 1]);
+        assert_eq!(frame[..WIDTH - 8].iter().sum::<u32>(), 0);
+
+        let mut frame = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        draw_border(&mut frame, 1);
+        assert_eq!(frame[HIRES_WIDTH + 1], 1);
+        assert_eq!(frame[2 * HIRES_WIDTH + 2], 0);
+        assert_eq!(frame[HIRES_WIDTH * HIRES_HEIGHT - 1], 1);
+    }
+
+    #[test]
+    fn test_speed_label() {
+        assert_eq!(speed_label(60, 600), "60 FPS 600 IPS");
+        assert_eq!(speed_label(59, 123_456), "59 FPS 123K IPS");
+
+        let mut frame = [1; WIDTH * HEIGHT];
+        draw_label(&mut frame, &speed_label(60, 99_999), 1, 0);
+        assert!(speed_label(60, 99_999).len() * ADVANCE <= WIDTH);
+        assert_eq!(frame[0], 0);
+        assert_eq!(frame[(GLYPH_HEIGHT + 2) * WIDTH], 1);
+    }
+}
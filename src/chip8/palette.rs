@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chip8::framebuffer::{DEFAULT_BG, DEFAULT_FG};
+use crate::chip8::octo;
+
+/// A color written `RRGGBB` or `RGB`, with or without a leading `#`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub u32);
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        octo::parse_color(&format!("#{}", s.trim_start_matches('#')))
+            .map(Color)
+            .ok_or_else(|| format!("expected a color like `00FF00`, got `{}`", s))
+    }
+}
+
+/// The colors of lit and unlit pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub fg: u32,
+    pub bg: u32,
+}
+
+/// The built-in palettes, by name.
+pub const THEMES: [(&str, Palette); 6] = [
+    (
+        "classic",
+        Palette {
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+        },
+    ),
+    (
+        "phosphor",
+        Palette {
+            fg: 0x33FF66,
+            bg: 0x001100,
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            fg: 0xFFB000,
+            bg: 0x1A0F00,
+        },
+    ),
+    (
+        "lcd",
+        Palette {
+            fg: 0x0F380F,
+            bg: 0x9BBC0F,
+        },
+    ),
+    (
+        "paper",
+        Palette {
+            fg: 0x222222,
+            bg: 0xEEEEEE,
+        },
+    ),
+    (
+        "octo",
+        Palette {
+            fg: 0xFFCC00,
+            bg: 0x996600,
+        },
+    ),
+];
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match THEMES.iter().find(|(_, palette)| palette == self) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{:06X}/{:06X}", self.fg & 0xFFFFFF, self.bg & 0xFFFFFF),
+        }
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        THEMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, palette)| palette)
+            .ok_or_else(|| {
+                let names: Vec<_> = THEMES.iter().map(|(name, _)| *name).collect();
+                format!("no palette `{}`, expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_and_palettes() {
+        assert_eq!("00FF00".parse(), Ok(Color(0x00FF00)));
+        assert_eq!("#0f0".parse(), Ok(Color(0x00FF00)));
+        assert!("green".parse::<Color>().is_err());
+
+        let amber: Palette = "Amber".parse().unwrap();
+        assert_eq!(amber.fg, 0xFFB000);
+        assert_eq!(amber.to_string(), "amber");
+        assert!("neon".parse::<Palette>().is_err());
+    }
+}
@@ -0,0 +1,75 @@
+/// Lets pixels that go out fade over a few frames, as a CRT's phosphor
+/// does, instead of going dark at once. This hides most of the flicker of
+/// sprites that are erased and redrawn every frame.
+pub struct Phosphor {
+    frames: u8,
+    /// Per pixel, the frames it has left to glow, one more than `frames`
+    /// while it is lit.
+    glow: Vec<u8>,
+    faded: Vec<u32>,
+}
+
+impl Phosphor {
+    /// Fade unlit pixels out over `frames` frames.
+    pub fn new(frames: u8) -> Self {
+        Phosphor {
+            frames,
+            glow: Vec::new(),
+            faded: Vec::new(),
+        }
+    }
+
+    /// The next frame of `pixels`, lit in `fg` on `bg`, with the pixels
+    /// that went out in the last few frames shaded between the two.
+    pub fn fade(&mut self, pixels: &[u32], fg: u32, bg: u32) -> &[u32] {
+        if self.glow.len() != pixels.len() {
+            // The resolution changed, so start over.
+            self.glow = vec![0; pixels.len()];
+        }
+        let full = self.frames.saturating_add(1);
+        self.faded.clear();
+        for (glow, &pixel) in self.glow.iter_mut().zip(pixels) {
+            *glow = if pixel == fg {
+                full
+            } else {
+                glow.saturating_sub(1)
+            };
+            self.faded.push(match *glow {
+                0 => pixel,
+                glow if glow == full => fg,
+                glow => blend(bg, fg, glow, full),
+            });
+        }
+        &self.faded
+    }
+}
+
+/// `parts` out of `whole` of the way from `from` to `to`, channel by channel.
+fn blend(from: u32, to: u32, parts: u8, whole: u8) -> u32 {
+    let (from, to) = (from.to_be_bytes(), to.to_be_bytes());
+    let mut mixed = [0; 4];
+    for (channel, mixed) in mixed.iter_mut().enumerate() {
+        let (from, to) = (i32::from(from[channel]), i32::from(to[channel]));
+        *mixed = (from + (to - from) * i32::from(parts) / i32::from(whole)) as u8;
+    }
+    u32::from_be_bytes(mixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade() {
+        let (fg, bg) = (0xFFFFFF, 0x000000);
+        let mut phosphor = Phosphor::new(3);
+        assert_eq!(phosphor.fade(&[fg, bg], fg, bg), [fg, bg]);
+        assert_eq!(phosphor.fade(&[bg, bg], fg, bg), [0xBFBFBF, bg]);
+        assert_eq!(phosphor.fade(&[bg, fg], fg, bg), [0x7F7F7F, fg]);
+        assert_eq!(phosphor.fade(&[bg, bg], fg, bg), [0x3F3F3F, 0xBFBFBF]);
+        assert_eq!(phosphor.fade(&[bg, bg], fg, bg), [bg, 0x7F7F7F]);
+
+        assert_eq!(blend(0x102030, 0x204060, 1, 2), 0x183048);
+        assert_eq!(phosphor.fade(&[bg; 4], fg, bg), [bg; 4]);
+    }
+}
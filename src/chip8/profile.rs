@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::chip8::opcodes;
+
+/// How many addresses the report lists.
+const HOT_SPOTS: usize = 10;
+
+/// How many times each address and each kind of opcode ran, for finding
+/// where a program spends its time.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Per address, the runs and the opcode last run there.
+    by_pc: Vec<(u64, u16)>,
+    /// Per opcode, the runs.
+    by_opcode: Vec<u64>,
+    total: u64,
+}
+
+impl Profile {
+    /// An empty profile of a machine with `ram` bytes of RAM.
+    pub fn new(ram: usize) -> Self {
+        Profile {
+            by_pc: vec![(0, 0); ram],
+            by_opcode: vec![0; 1 << 16],
+            total: 0,
+        }
+    }
+
+    /// `opcode` ran at `pc`.
+    pub fn count(&mut self, pc: usize, opcode: u16) {
+        if let Some(entry) = self.by_pc.get_mut(pc) {
+            *entry = (entry.0 + 1, opcode);
+        }
+        self.by_opcode[opcode as usize] += 1;
+        self.total += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The addresses that ran most, most first, with their runs and opcode.
+    pub fn hot_spots(&self) -> Vec<(usize, u64, u16)> {
+        let mut spots: Vec<_> = (self.by_pc.iter().enumerate())
+            .filter(|(_, &(runs, _))| runs > 0)
+            .map(|(pc, &(runs, opcode))| (pc, runs, opcode))
+            .collect();
+        spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        spots
+    }
+
+    /// The runs of each kind of opcode, as a pattern like `8XY4`, most
+    /// first.
+    pub fn by_kind(&self) -> Vec<(String, u64)> {
+        let mut kinds: Vec<(String, u64)> = Vec::new();
+        for (opcode, &runs) in self.by_opcode.iter().enumerate() {
+            if runs == 0 {
+                continue;
+            }
+            let kind = kind(opcode as u16);
+            match kinds.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, total)) => *total += runs,
+                None => kinds.push((kind, runs)),
+            }
+        }
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        kinds
+    }
+}
+
+/// The pattern `opcode` is an instance of, with its operands as letters.
+pub fn kind(opcode: u16) -> String {
+    let hex = format!("{:04X}", opcode);
+    match opcode >> 12 {
+        0x0 if opcode & 0xFFF0 == 0x00C0 => "00CN".to_string(),
+        0x0 if matches!(opcode, 0x00E0 | 0x00EE | 0x00FB..=0x00FF) => hex,
+        0x0 | 0x1 | 0x2 | 0xA | 0xB => format!("{}NNN", &hex[..1]),
+        0x3 | 0x4 | 0x6 | 0x7 | 0xC => format!("{}XNN", &hex[..1]),
+        0x5 | 0x8 | 0x9 => format!("{}XY{}", &hex[..1], &hex[3..]),
+        0xD => "DXYN".to_string(),
+        _ => format!("{}X{}", &hex[..1], &hex[2..]),
+    }
+}
+
+fn percent(runs: u64, total: u64) -> f64 {
+    runs as f64 * 100.0 / total.max(1) as f64
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions: {}", self.total)?;
+        writeln!(f, "Hot spots:")?;
+        for (pc, runs, opcode) in self.hot_spots().into_iter().take(HOT_SPOTS) {
+            let instr = opcodes::decode(opcode).map_or("???".to_string(), |i| i.to_string());
+            writeln!(
+                f,
+                "  0x{:03X}  {:04X}  {:<16} {:>10} {:>5.1}%",
+                pc,
+                opcode,
+                instr,
+                runs,
+                percent(runs, self.total)
+            )?;
+        }
+        write!(f, "Opcodes:")?;
+        for (kind, runs) in self.by_kind() {
+            write!(
+                f,
+                "\n  {:<6} {:>10} {:>5.1}%",
+                kind,
+                runs,
+                percent(runs, self.total)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let mut profile = Profile::new(0x1000);
+        for _ in 0..3 {
+            profile.count(0x202, 0x7001);
+            profile.count(0x204, 0x8014);
+        }
+        profile.count(0x200, 0x7102);
+        profile.count(0x206, 0x8024);
+
+        assert_eq!(profile.total(), 8);
+        assert_eq!(
+            profile.hot_spots(),
+            [
+                (0x202, 3, 0x7001),
+                (0x204, 3, 0x8014),
+                (0x200, 1, 0x7102),
+                (0x206, 1, 0x8024)
+            ]
+        );
+        assert_eq!(
+            profile.by_kind(),
+            [("7XNN".to_string(), 4), ("8XY4".to_string(), 4)]
+        );
+    }
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(kind(0x00E0), "00E0");
+        assert_eq!(kind(0x00C4), "00CN");
+        assert_eq!(kind(0x1234), "1NNN");
+        assert_eq!(kind(0x5120), "5XY0");
+        assert_eq!(kind(0xD125), "DXYN");
+        assert_eq!(kind(0xE19E), "EX9E");
+        assert_eq!(kind(0xF233), "FX33");
+    }
+}
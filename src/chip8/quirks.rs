@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Behaviours that differ between CHIP-8 interpreters, which a ROM written
+/// for one of them may depend on. Each is off by default, where this
+/// interpreter does what most ROMs expect.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Quirks {
+    /// Sprites drawn partly off the screen wrap around to the other side,
+    /// instead of being clipped at the edges.
+    pub wrap: bool,
+    /// `SHR Vx, Vy` and `SHL Vx, Vy` shift VY and store the result in VX,
+    /// as on the COSMAC VIP, instead of shifting VX in place.
+    pub shift_vy: bool,
+    /// `LD [I], Vx` and `LD Vx, [I]` (FX55 and FX65) leave I just past the
+    /// registers they stored or loaded, instead of where it was.
+    pub increment_i: bool,
+    /// `ADD I, Vx` (FX1E) sets VF to 1 when I goes past 0xFFF and to 0
+    /// otherwise, as on the Amiga interpreter. Spacefight 2091! relies on
+    /// it.
+    pub add_i_carry: bool,
+    /// I stays within the 12 bit address space, wrapping from 0xFFF to 0,
+    /// instead of counting on to 0xFFFF.
+    pub mask_i: bool,
+}
+
+/// The names quirks are listed by, as `--quirks` takes them.
+pub const NAMES: &[&str] = &["wrap", "shift-vy", "increment-i", "add-i-carry", "mask-i"];
+
+impl Quirks {
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "wrap" => Some(&mut self.wrap),
+            "shift-vy" => Some(&mut self.shift_vy),
+            "increment-i" => Some(&mut self.increment_i),
+            "add-i-carry" => Some(&mut self.add_i_carry),
+            "mask-i" => Some(&mut self.mask_i),
+            _ => None,
+        }
+    }
+
+    /// The names of the quirks turned on.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut quirks = *self;
+        NAMES
+            .iter()
+            .copied()
+            .filter(|name| quirks.flag(name).is_some_and(|on| *on))
+            .collect()
+    }
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.names().join(",") {
+            names if names.is_empty() => write!(f, "none"),
+            names => write!(f, "{}", names),
+        }
+    }
+}
+
+/// A comma-separated list of the quirks to turn on, or `none`.
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Quirks::default();
+        for name in s.split(',').map(str::trim) {
+            if name == "none" || name.is_empty() {
+                continue;
+            }
+            match quirks.flag(name) {
+                Some(on) => *on = true,
+                None => {
+                    return Err(format!(
+                        "unknown quirk `{}`, expected some of: {}",
+                        name,
+                        NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(quirks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::CHIP8;
+
+    #[test]
+    fn test_parse_quirks() {
+        assert_eq!("none".parse(), Ok(Quirks::default()));
+        let quirks = Quirks {
+            wrap: true,
+            shift_vy: true,
+            increment_i: false,
+            add_i_carry: false,
+            mask_i: false,
+        };
+        assert_eq!(" shift-vy, wrap".parse(), Ok(quirks));
+        assert_eq!(quirks.to_string(), "wrap,shift-vy");
+        assert_eq!(Quirks::default().to_string(), "none");
+        assert!("wrap,bogus".parse::<Quirks>().is_err());
+    }
+
+    #[test]
+    fn test_add_i_quirks() {
+        let run = |quirks: &str| {
+            let mut chip8 = CHIP8::headless();
+            chip8.set_quirks(quirks.parse().unwrap());
+            chip8
+                .load_bytes(&[
+                    0xAF, 0xFE, // LD I, 0xFFE
+                    0x60, 0x03, // LD V0, 3
+                    0x6F, 0x07, // LD VF, 7
+                    0xF0, 0x1E, // ADD I, V0
+                ])
+                .unwrap();
+            for _ in 0..4 {
+                chip8.step().unwrap();
+            }
+            let state = chip8.state();
+            (state.i, state.vx[0xF])
+        };
+        assert_eq!(run("none"), (0x1001, 7));
+        assert_eq!(run("add-i-carry"), (0x1001, 1));
+        assert_eq!(run("mask-i"), (0x001, 7));
+        assert_eq!(run("add-i-carry,mask-i"), (0x001, 1));
+    }
+}
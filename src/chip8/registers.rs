@@ -1,10 +1,6 @@
-use std::{
-    sync::{
-        atomic::{AtomicU8, Ordering},
-        Arc,
-    },
-    thread,
-    time::Duration,
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
 };
 
 #[allow(non_snake_case)]
@@ -16,37 +12,68 @@ pub struct Registers {
     pub Vx: [u8; 16], // General Purpose Vx registers
     DT: Arc<AtomicU8>,
     ST: Arc<AtomicU8>, // Sound & Timer registers
+    external_clock: bool,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Registers::new()
+    }
 }
 
 impl Registers {
+    /// Registers whose timers the CPU ticks, once every 60th of a second's
+    /// worth of instructions.
     pub fn new() -> Self {
-        let r = Registers {
+        Registers {
             PC: 0x200,
             SP: 0,
             I: 0,
             Vx: [0; 16],
             DT: Arc::new(AtomicU8::new(0)),
             ST: Arc::new(AtomicU8::new(0)),
-        };
-        r.init();
-        r
+            external_clock: false,
+        }
+    }
+
+    /// Registers whose timers are not ticked by the CPU. Whoever holds the
+    /// `timer_handles` must call `Registers::tick` on them at 60Hz.
+    pub fn with_external_clock() -> Self {
+        Registers {
+            external_clock: true,
+            ..Registers::new()
+        }
     }
 
-    fn spawn_timer_thread(lock: Arc<AtomicU8>) {
-        thread::spawn(move || loop {
-            thread::sleep(Duration::from_nanos(16_666_667));
-            if lock.load(Ordering::Relaxed) != 0 {
-                lock.fetch_sub(1, Ordering::SeqCst);
-            }
-        });
+    /// Whether the timers are ticked by something other than the CPU.
+    pub fn has_external_clock(&self) -> bool {
+        self.external_clock
     }
 
-    pub fn init(&self) {
-        let dt_lock = self.DT.clone();
-        let st_lock = self.ST.clone();
+    /// Back to power-on state. The timers keep their clock, but are zeroed.
+    pub fn reset(&mut self) {
+        self.PC = 0x200;
+        self.SP = 0;
+        self.I = 0;
+        self.Vx = [0; 16];
+        self.set_dt(0);
+        self.set_st(0);
+    }
+
+    #[cfg(any(feature = "audio", feature = "gamepad"))]
+    pub fn timer_handles(&self) -> (Arc<AtomicU8>, Arc<AtomicU8>) {
+        (self.DT.clone(), self.ST.clone())
+    }
+
+    /// Decrement a timer by one, stopping at zero.
+    pub fn tick(lock: &AtomicU8) {
+        let _ = lock.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |val| val.checked_sub(1));
+    }
 
-        Registers::spawn_timer_thread(dt_lock);
-        Registers::spawn_timer_thread(st_lock);
+    /// Tick both timers once.
+    pub fn tick_timers(&self) {
+        Registers::tick(&self.DT);
+        Registers::tick(&self.ST);
     }
 
     pub fn is_dt_active(&self) -> bool {
@@ -0,0 +1,95 @@
+use minifb::Key;
+
+use crate::chip8::display::{HEIGHT, WIDTH};
+use crate::chip8::keymap::{self, Keymap, PAD_ORDER};
+use crate::chip8::overlay::{self, GLYPH_HEIGHT};
+
+/// Key that starts remapping the keypad, and gives it up part way.
+pub const REMAP_KEY: Key = Key::F2;
+
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// Asks for the keyboard key to stand for each keypad key in turn, in the
+/// order they sit on the pad, drawn over the screen while the program is
+/// paused.
+#[derive(Debug, Default)]
+pub struct Remap {
+    keys: Vec<Key>,
+}
+
+impl Remap {
+    pub fn new() -> Self {
+        Remap::default()
+    }
+
+    /// The keypad key waiting for a keyboard key.
+    pub fn keypad_key(&self) -> u8 {
+        PAD_ORDER[self.keys.len()]
+    }
+
+    /// Handle a key going down, giving the new keymap once every keypad key
+    /// has a key. Keys that can't be mapped or are already taken are passed
+    /// over.
+    pub fn press(&mut self, key: Key) -> Option<Keymap> {
+        if !keymap::can_map(key) || self.keys.contains(&key) {
+            return None;
+        }
+        self.keys.push(key);
+        if self.keys.len() < PAD_ORDER.len() {
+            return None;
+        }
+        let mut rows = [Key::Unknown; 16];
+        rows.copy_from_slice(&self.keys);
+        Keymap::from_rows(rows).ok()
+    }
+
+    pub fn render(&self, fg: u32, bg: u32) -> Vec<u32> {
+        let mut buffer = vec![bg; WIDTH * HEIGHT];
+        let lines = [
+            format!("REMAP KEYS {}/16", self.keys.len() + 1),
+            "PRESS THE KEY".to_string(),
+            format!("FOR KEYPAD {:X}", self.keypad_key()),
+            String::new(),
+            "F2: CANCEL".to_string(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            overlay::draw_text(&mut buffer, 1, 1 + i * LINE_HEIGHT, line, fg);
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let mut remap = Remap::new();
+        let keys = [
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::A,
+            Key::Z,
+            Key::E,
+            Key::R,
+            Key::Q,
+            Key::S,
+            Key::D,
+            Key::F,
+            Key::W,
+            Key::X,
+            Key::C,
+        ];
+        for &key in &keys {
+            assert_eq!(remap.press(key), None);
+        }
+        assert_eq!(remap.keypad_key(), 0xF);
+        // Taken already, and not a key for the keypad.
+        assert_eq!(remap.press(Key::A), None);
+        assert_eq!(remap.press(Key::Enter), None);
+        assert_eq!(remap.press(Key::V), "1234azerqsdfwxcv".parse().ok());
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use crate::chip8::octo::Metadata;
+
+/// Settings known to suit particular ROMs, keyed by `rom_hash`, in the
+/// format of Octo's metadata.
+const DATABASE: &str = include_str!("romdb.json");
+
+/// The SHA-1 of `rom` in lowercase hex, which identifies it in the
+/// database whatever the file is called.
+pub fn rom_hash(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+/// The database's entry for `rom`, if it has one.
+pub fn lookup(rom: &[u8]) -> Option<Metadata> {
+    let mut database: HashMap<String, Metadata> =
+        serde_json::from_str(DATABASE).expect("romdb.json is valid");
+    database.remove(&rom_hash(rom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let meta = lookup(include_bytes!("../../roms/pong.ch8")).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Pong"));
+        assert_eq!(meta.options.tickrate, Some(8));
+        assert_eq!(meta.options.clip_quirks, Some(true));
+        assert_eq!(meta.options.shift_quirks, Some(false));
+        assert_eq!(meta.options.load_store_quirks, Some(false));
+
+        assert!(lookup(&[0x12, 0x00]).is_none());
+    }
+}
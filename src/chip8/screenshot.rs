@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::chip8::framebuffer;
+
+/// Write `pixels` (`0x00RRGGBB`, at either `framebuffer::resolution`) to `path`
+/// as an RGB PNG.
+pub fn write_png(path: &Path, pixels: &[u32]) -> io::Result<()> {
+    encode(BufWriter::new(File::create(path)?), pixels)
+}
+
+fn encode<W: Write>(out: W, pixels: &[u32]) -> io::Result<()> {
+    let (width, height) = framebuffer::resolution(pixels);
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let rgb: Vec<u8> = pixels
+        .iter()
+        .flat_map(|pixel| {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            [r, g, b]
+        })
+        .collect();
+    encoder
+        .write_header()?
+        .write_image_data(&rgb)
+        .map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::framebuffer::{HEIGHT, WIDTH};
+
+    #[test]
+    fn test_png_round_trip() {
+        let mut pixels = [0; WIDTH * HEIGHT];
+        pixels[WIDTH + 2] = 0x00FF_AA00;
+        let mut png = Vec::new();
+        encode(&mut png, &pixels).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut rgb = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgb).unwrap();
+        assert_eq!((info.width, info.height), (WIDTH as u32, HEIGHT as u32));
+        let at = (WIDTH + 2) * 3;
+        assert_eq!(rgb[at..at + 3], [0xFF, 0xAA, 0x00]);
+        assert_eq!(rgb[..3], [0, 0, 0]);
+    }
+}
@@ -0,0 +1,270 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+
+use crate::chip8::state::Chip8State;
+
+/// When a script is called.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Callback {
+    /// `on_frame()`, after each 60th of a second of instructions.
+    Frame,
+    /// `on_instruction(opcode)`, after each instruction.
+    Instruction(u16),
+}
+
+/// A Rhai script run alongside the program, e.g. a trainer or a bot. Its
+/// `on_frame` and `on_instruction` functions see the machine as `this`, and
+/// can read and change its registers and memory:
+///
+/// ```text
+/// fn on_frame() {
+///     this.set_v(3, 9);                 // infinite lives
+///     this["frames"] = (this["frames"] ?? 0) + 1;
+/// }
+/// ```
+///
+/// `this.pc`, `this.i`, `this.dt` and `this.st` are properties, `this.v(x)`,
+/// `this.set_v(x, value)`, `this.peek(addr)` and `this.poke(addr, value)`
+/// methods, and `this["name"]` keeps a value from one call to the next.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    vars: Map,
+    on_frame: bool,
+    on_instruction: bool,
+}
+
+/// What a script's functions see as `this`.
+#[derive(Clone)]
+struct Machine {
+    state: Chip8State,
+    vars: Map,
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// `value` as a `T`, or an error if it doesn't fit one.
+fn fit<T: TryFrom<i64>>(value: i64, what: &str) -> ScriptResult<T> {
+    T::try_from(value).map_err(|_| format!("{} is out of range for {}", value, what).into())
+}
+
+impl Machine {
+    fn v(&mut self, x: i64) -> ScriptResult<i64> {
+        let x: usize = fit(x, "a V register")?;
+        match self.state.vx.get(x) {
+            Some(&v) => Ok(v.into()),
+            None => Err(format!("there is no V{:X}", x).into()),
+        }
+    }
+
+    fn set_v(&mut self, x: i64, value: i64) -> ScriptResult<()> {
+        let x: usize = fit(x, "a V register")?;
+        let value = fit(value, "a byte")?;
+        match self.state.vx.get_mut(x) {
+            Some(v) => *v = value,
+            None => return Err(format!("there is no V{:X}", x).into()),
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self, addr: i64) -> ScriptResult<&mut u8> {
+        let len = self.state.ram.len();
+        match usize::try_from(addr) {
+            Ok(addr) if addr < len => Ok(&mut self.state.ram[addr]),
+            _ => Err(format!("0x{:X} is past the end of RAM at 0x{:X}", addr, len).into()),
+        }
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register_type_with_name::<Machine>("Chip8")
+        .register_get_set(
+            "pc",
+            |m: &mut Machine| m.state.pc as i64,
+            |m: &mut Machine, pc: i64| -> ScriptResult<()> {
+                m.state.pc = fit::<u16>(pc, "PC")?.into();
+                Ok(())
+            },
+        )
+        .register_get_set(
+            "i",
+            |m: &mut Machine| i64::from(m.state.i),
+            |m: &mut Machine, i: i64| -> ScriptResult<()> {
+                m.state.i = fit(i, "I")?;
+                Ok(())
+            },
+        )
+        .register_get_set(
+            "dt",
+            |m: &mut Machine| i64::from(m.state.dt),
+            |m: &mut Machine, dt: i64| -> ScriptResult<()> {
+                m.state.dt = fit(dt, "DT")?;
+                Ok(())
+            },
+        )
+        .register_get_set(
+            "st",
+            |m: &mut Machine| i64::from(m.state.st),
+            |m: &mut Machine, st: i64| -> ScriptResult<()> {
+                m.state.st = fit(st, "ST")?;
+                Ok(())
+            },
+        )
+        .register_fn("v", Machine::v)
+        .register_fn("set_v", Machine::set_v)
+        .register_fn("peek", |m: &mut Machine, addr: i64| -> ScriptResult<i64> {
+            m.byte(addr).map(|byte| i64::from(*byte))
+        })
+        .register_fn(
+            "poke",
+            |m: &mut Machine, addr: i64, value: i64| -> ScriptResult<()> {
+                let value = fit(value, "a byte")?;
+                *m.byte(addr)? = value;
+                Ok(())
+            },
+        )
+        .register_indexer_get(|m: &mut Machine, name: &str| {
+            m.vars.get(name).cloned().unwrap_or(Dynamic::UNIT)
+        })
+        .register_indexer_set(|m: &mut Machine, name: &str, value: Dynamic| {
+            m.vars.insert(name.into(), value);
+        });
+    engine
+}
+
+impl Script {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Script::compile(&fs::read_to_string(path)?)
+    }
+
+    /// Compile `source` and run its top level once. Its functions can't see
+    /// the variables it sets; they keep values in `this["name"]` instead.
+    pub fn compile(source: &str) -> io::Result<Self> {
+        let engine = engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let (on_frame, on_instruction) = (defines("on_frame", 0), defines("on_instruction", 1));
+        Ok(Script {
+            engine,
+            ast,
+            scope,
+            vars: Map::new(),
+            on_frame,
+            on_instruction,
+        })
+    }
+
+    /// Whether the script has a function for `callback`.
+    pub fn handles(&self, callback: Callback) -> bool {
+        match callback {
+            Callback::Frame => self.on_frame,
+            Callback::Instruction(_) => self.on_instruction,
+        }
+    }
+
+    /// Call the script's function for `callback`, if it has one, letting it
+    /// change `state`.
+    pub fn call(&mut self, callback: Callback, state: &mut Chip8State) -> io::Result<()> {
+        match callback {
+            _ if !self.handles(callback) => Ok(()),
+            Callback::Frame => self.call_fn("on_frame", state, ()),
+            Callback::Instruction(opcode) => {
+                self.call_fn("on_instruction", state, (i64::from(opcode),))
+            }
+        }
+    }
+
+    fn call_fn(
+        &mut self,
+        name: &str,
+        state: &mut Chip8State,
+        args: impl FuncArgs,
+    ) -> io::Result<()> {
+        let mut this = Dynamic::from(Machine {
+            state: state.clone(),
+            vars: mem::take(&mut self.vars),
+        });
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .rewind_scope(false)
+            .bind_this_ptr(&mut this);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        );
+        let machine = this
+            .try_cast::<Machine>()
+            .ok_or_else(|| io::Error::other(format!("{} replaced `this`", name)))?;
+        self.vars = machine.vars;
+        if let Err(e) = result {
+            return Err(io::Error::other(e.to_string()));
+        }
+        *state = machine.state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::CHIP8;
+
+    #[test]
+    fn test_script() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x70, 0xFF, // ADD V0, 0xFF
+                0x12, 0x00, // JP 0x200
+            ])
+            .unwrap();
+        let script = Script::compile(
+            r#"
+            fn on_frame() {
+                this.set_v(1, 9);
+                this.poke(0x300, this.v(1) + 1);
+                this["frames"] = (this["frames"] ?? 0) + 1;
+                this.i = this["frames"];
+            }
+            fn on_instruction(opcode) {
+                if opcode == 0x70FF { this.set_v(2, this.v(2) + 1); }
+            }
+            "#,
+        )
+        .unwrap();
+        chip8.set_script(script);
+        chip8.run_frames(3).unwrap();
+
+        let state = chip8.state();
+        assert_eq!(state.vx[1], 9);
+        assert_eq!(state.ram[0x300], 10);
+        assert_eq!(state.i, 3);
+        assert_eq!(state.vx[2], 15, "ADD ran 15 times in 30 instructions");
+
+        assert!(Script::compile("fn on_frame( {").is_err());
+        let mut state = chip8.state();
+        let mut script = Script::compile("fn on_frame() { this.poke(0x1000, 1); }").unwrap();
+        assert!(script.call(Callback::Frame, &mut state).is_err());
+        assert_eq!(state, chip8.state());
+    }
+}
@@ -0,0 +1,257 @@
+use std::cell::{Cell, RefCell};
+
+use minifb::Key;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use sdl2::EventPump;
+
+use crate::chip8::dpi;
+use crate::chip8::framebuffer::{resolution, FrameBuffer};
+use crate::chip8::frontend::{Frontend, KeypadState};
+use crate::chip8::keymap::{self, Keymap};
+use crate::chip8::tone::Tone;
+
+const DEFAULT_TITLE: &str = "rust-chip-8";
+
+/// A frontend drawing the screen in an SDL2 window, reading the keypad from
+/// its keyboard through a `Keymap`, and playing the buzzer through SDL's
+/// audio. Unlike the minifb window it can go fullscreen and wait for vsync,
+/// but it runs on the thread that made it, so the caller paces the frames.
+pub struct SdlWindow {
+    canvas: RefCell<WindowCanvas>,
+    events: RefCell<EventPump>,
+    buzzer: Option<AudioDevice<Buzzer>>,
+    keymap: Keymap,
+    down: RefCell<Vec<Key>>,
+    open: Cell<bool>,
+    title: RefCell<String>,
+    lit: Vec<bool>,
+    colors: (u32, u32),
+    // Dropped last, after everything made from it.
+    _sdl: sdl2::Sdl,
+}
+
+impl SdlWindow {
+    /// Open a window `scale` screen pixels to a CHIP-8 pixel (by default as
+    /// many as the minifb window would use), or covering the desktop when
+    /// `fullscreen`. Presenting a frame waits for the display's refresh when
+    /// `vsync`. The buzzer stays silent if there is no audio device.
+    pub fn new(scale: Option<usize>, fullscreen: bool, vsync: bool) -> Result<Self, String> {
+        let sdl = sdl2::init()?;
+        let scale = scale.unwrap_or_else(|| dpi::window_scale(dpi::scale_factor())) as u32;
+        let mut window = sdl.video()?.window(DEFAULT_TITLE, 64 * scale, 32 * scale);
+        window.position_centered().resizable();
+        if fullscreen {
+            window.fullscreen_desktop();
+        }
+        let mut canvas = window.build().map_err(|e| e.to_string())?.into_canvas();
+        if vsync {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas.build().map_err(|e| e.to_string())?;
+        let events = sdl.event_pump()?;
+
+        let spec = AudioSpecDesired {
+            freq: Some(44_100),
+            channels: Some(1),
+            samples: None,
+        };
+        let buzzer = sdl
+            .audio()
+            .and_then(|audio| {
+                audio.open_playback(None, &spec, |spec| Buzzer {
+                    tone: Tone::default(),
+                    on: false,
+                    phase: 0.0,
+                    rate: spec.freq as f32,
+                })
+            })
+            .ok();
+        if let Some(buzzer) = &buzzer {
+            buzzer.resume();
+        }
+
+        Ok(SdlWindow {
+            canvas: RefCell::new(canvas),
+            events: RefCell::new(events),
+            buzzer,
+            keymap: Keymap::default(),
+            down: RefCell::new(Vec::new()),
+            open: Cell::new(true),
+            title: RefCell::new(DEFAULT_TITLE.to_string()),
+            lit: Vec::new(),
+            colors: (0, 0),
+            _sdl: sdl,
+        })
+    }
+
+    /// Read the keypad from the keys `keymap` gives.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Sound the buzzer as `tone`.
+    pub fn set_tone(&mut self, tone: Tone) {
+        if let Some(buzzer) = &mut self.buzzer {
+            buzzer.lock().tone = tone;
+        }
+    }
+
+    /// Take in the events since the last poll.
+    fn poll(&self) {
+        let mut down = self.down.borrow_mut();
+        for event in self.events.borrow_mut().poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::ESCAPE),
+                    ..
+                } => self.open.set(false),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(key) = minifb_key(keycode) {
+                        down.push(key);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = minifb_key(keycode) {
+                        down.retain(|&k| k != key);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn show_title(&self, status: &str) {
+        let title = self.title.borrow();
+        let title = if status.is_empty() {
+            title.clone()
+        } else {
+            format!("{title} - {status}")
+        };
+        let _ = self.canvas.borrow_mut().window_mut().set_title(&title);
+    }
+}
+
+impl Frontend for SdlWindow {
+    fn draw(&mut self, fb: &FrameBuffer) {
+        self.lit.clear();
+        self.lit.extend_from_slice(fb.lit());
+        self.colors = fb.colors();
+    }
+
+    /// Draw the screen as it ended the frame, scaled to fit the window.
+    fn present(&mut self) {
+        let (width, height) = resolution(&self.lit);
+        let (fg, bg) = self.colors;
+        let rects: Vec<Rect> = (self.lit.iter().enumerate())
+            .filter(|&(_, &lit)| lit)
+            .map(|(i, _)| Rect::new((i % width) as i32, (i / width) as i32, 1, 1))
+            .collect();
+        let canvas = self.canvas.get_mut();
+        let _ = canvas.set_logical_size(width as u32, height as u32);
+        canvas.set_draw_color(rgb(bg));
+        canvas.clear();
+        canvas.set_draw_color(rgb(fg));
+        let _ = canvas.fill_rects(&rects);
+        canvas.present();
+    }
+
+    fn keys(&self) -> KeypadState {
+        self.poll();
+        self.down
+            .borrow()
+            .iter()
+            .filter_map(|&key| self.keymap.keypad_key(key))
+            .fold(KeypadState::default(), KeypadState::with)
+    }
+
+    fn beep(&mut self, on: bool) {
+        if let Some(buzzer) = &mut self.buzzer {
+            buzzer.lock().on = on;
+        }
+    }
+
+    fn set_title(&self, title: &str) {
+        *self.title.borrow_mut() = title.to_string();
+        self.show_title("");
+    }
+
+    fn set_status(&self, status: &str) {
+        self.show_title(status);
+    }
+
+    /// Whether Escape has not been pressed nor the window closed yet.
+    fn is_open(&self) -> bool {
+        self.poll();
+        self.open.get()
+    }
+}
+
+/// Plays the buzzer's tone while it is on.
+struct Buzzer {
+    tone: Tone,
+    on: bool,
+    /// How far through a cycle of the wave the next sample is, from 0 up
+    /// to 1.
+    phase: f32,
+    /// Samples a second.
+    rate: f32,
+}
+
+impl AudioCallback for Buzzer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out {
+            if self.on {
+                *sample = self.tone.sample(self.phase);
+                self.phase = (self.phase + self.tone.hz / self.rate) % 1.0;
+            } else {
+                *sample = 0.0;
+                self.phase = 0.0;
+            }
+        }
+    }
+}
+
+fn rgb(color: u32) -> Color {
+    Color::RGB((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
+
+/// The minifb key a `Keymap` knows as `keycode`: the key typing the same
+/// character, or the same number pad key.
+fn minifb_key(keycode: Keycode) -> Option<Key> {
+    let key = match keycode {
+        Keycode::KP_0 => Key::NumPad0,
+        Keycode::KP_1 => Key::NumPad1,
+        Keycode::KP_2 => Key::NumPad2,
+        Keycode::KP_3 => Key::NumPad3,
+        Keycode::KP_4 => Key::NumPad4,
+        Keycode::KP_5 => Key::NumPad5,
+        Keycode::KP_6 => Key::NumPad6,
+        Keycode::KP_7 => Key::NumPad7,
+        Keycode::KP_8 => Key::NumPad8,
+        Keycode::KP_9 => Key::NumPad9,
+        Keycode::KP_DIVIDE => Key::NumPadSlash,
+        Keycode::KP_MULTIPLY => Key::NumPadAsterisk,
+        Keycode::KP_MINUS => Key::NumPadMinus,
+        Keycode::KP_PLUS => Key::NumPadPlus,
+        Keycode::KP_ENTER => Key::NumPadEnter,
+        Keycode::KP_PERIOD => Key::NumPadDot,
+        // Keys that type a character have it as their keycode.
+        _ => return char::from_u32(keycode.into_i32() as u32).and_then(keymap::key_for),
+    };
+    Some(key)
+}
@@ -0,0 +1,152 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{fence, AtomicU32, Ordering};
+
+use memmap2::MmapMut;
+
+use crate::chip8::display::{self, HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+
+pub const MAGIC: &[u8; 4] = b"C8FB";
+pub const HEADER_LEN: usize = 16;
+
+/// The framebuffer exported as a named, file-backed shared-memory region, so
+/// capture tools can map it instead of grabbing the window.
+///
+/// Layout, all integers little endian:
+///
+/// | offset | field                                              |
+/// |--------|----------------------------------------------------|
+/// | 0      | magic `C8FB`                                       |
+/// | 4      | `u32` width                                        |
+/// | 8      | `u32` height                                       |
+/// | 12     | `u32` sequence, odd while a frame is being written |
+/// | 16     | `width * height` `u32` pixels, `0x00RRGGBB`        |
+///
+/// The region has room for the SUPER-CHIP's 128x64 screen; width and height
+/// say how much of it the current frame uses.
+///
+/// The sequence makes a seqlock: a reader loads it with acquire ordering,
+/// copies the frame, issues an acquire fence and loads it again, and keeps
+/// the copy only if both loads gave the same even number.
+pub struct SharedFramebuffer {
+    map: MmapMut,
+    path: PathBuf,
+    sequence: u32,
+}
+
+impl SharedFramebuffer {
+    /// Create the region `name` under `/dev/shm` where available, or the
+    /// system temp directory otherwise. The name must be a plain file name,
+    /// not a path that could lead out of that directory.
+    pub fn create(name: &str) -> io::Result<Self> {
+        let mut components = Path::new(name).components();
+        let plain = matches!(components.next(), Some(Component::Normal(part)) if part == name)
+            && components.next().is_none()
+            && !name.contains(['/', '\\']);
+        if !plain {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{name}` is not a plain file name"),
+            ));
+        }
+        let dir = Path::new("/dev/shm");
+        let dir = if dir.is_dir() {
+            dir.to_path_buf()
+        } else {
+            env::temp_dir()
+        };
+        SharedFramebuffer::create_at(dir.join(name))
+    }
+
+    pub fn create_at(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((HEADER_LEN + HIRES_WIDTH * HIRES_HEIGHT * 4) as u64)?;
+
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[0..4].copy_from_slice(MAGIC);
+        map[4..8].copy_from_slice(&(WIDTH as u32).to_le_bytes());
+        map[8..12].copy_from_slice(&(HEIGHT as u32).to_le_bytes());
+
+        Ok(SharedFramebuffer {
+            map,
+            path,
+            sequence: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn publish(&mut self, buffer: &[u32]) {
+        self.write_sequence(self.sequence.wrapping_add(1));
+        let (width, height) = display::resolution(buffer);
+        self.map[4..8].copy_from_slice(&(width as u32).to_le_bytes());
+        self.map[8..12].copy_from_slice(&(height as u32).to_le_bytes());
+        for (dst, pixel) in self.map[HEADER_LEN..].chunks_exact_mut(4).zip(buffer) {
+            dst.copy_from_slice(&(pixel & 0x00FF_FFFF).to_le_bytes());
+        }
+        self.write_sequence(self.sequence.wrapping_add(1));
+    }
+
+    fn write_sequence(&mut self, sequence: u32) {
+        self.sequence = sequence;
+        // SAFETY: the map is page aligned, so offset 12 is aligned for a u32,
+        // and it outlives the reference.
+        let shared = unsafe { &*self.map.as_ptr().add(12).cast::<AtomicU32>() };
+        if sequence % 2 == 1 {
+            // Readers that see any of the frame being written see it odd.
+            shared.store(sequence.to_le(), Ordering::Relaxed);
+            fence(Ordering::Release);
+        } else {
+            // Readers that see it even see the whole frame before it.
+            shared.store(sequence.to_le(), Ordering::Release);
+        }
+    }
+}
+
+impl Drop for SharedFramebuffer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_layout() {
+        let path = env::temp_dir().join(format!("chip8-shm-test-{}", std::process::id()));
+        let mut shm = SharedFramebuffer::create_at(path.clone()).unwrap();
+
+        let mut buffer = [0; WIDTH * HEIGHT];
+        buffer[1] = u32::MAX;
+        shm.publish(&buffer);
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], MAGIC);
+        assert_eq!(bytes[4..8], (WIDTH as u32).to_le_bytes());
+        assert_eq!(bytes[8..12], (HEIGHT as u32).to_le_bytes());
+        assert_eq!(bytes[12..16], 2u32.to_le_bytes());
+        assert_eq!(bytes[HEADER_LEN + 4..HEADER_LEN + 8], [0xFF, 0xFF, 0xFF, 0]);
+
+        drop(shm);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_create_rejects_paths() {
+        for name in ["", ".", "..", "../escape", "a/b", "/tmp/x", "a\\b"] {
+            let e = SharedFramebuffer::create(name).err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{name}");
+        }
+    }
+}
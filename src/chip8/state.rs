@@ -0,0 +1,281 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chip8::framebuffer::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+use crate::chip8::opcodes::Instruction;
+
+/// A copy of everything the program can see of the machine at one instant:
+/// RAM, the stack, the registers and timers, and the screen. Save states are
+/// written as its JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chip8State {
+    pub ram: Vec<u8>,
+    /// Return addresses, innermost call last.
+    pub stack: Vec<u16>,
+    pub pc: usize,
+    pub sp: u8,
+    pub i: u16,
+    pub vx: [u8; 16],
+    pub dt: u8,
+    pub st: u8,
+    /// Which pixels are lit, row by row, at 64x32 or 128x64.
+    #[serde(alias = "lit")]
+    pub display: Vec<bool>,
+}
+
+impl Chip8State {
+    /// Read a state written by `write`, checking it fits a CHIP8 with `ram`
+    /// bytes of RAM.
+    pub fn read(path: &Path, ram: usize) -> io::Result<Self> {
+        let state: Chip8State = serde_json::from_slice(&fs::read(path)?)?;
+        let screens = [WIDTH * HEIGHT, HIRES_WIDTH * HIRES_HEIGHT];
+        if state.ram.len() != ram || !screens.contains(&state.display.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a state saved by this interpreter",
+            ));
+        }
+        Ok(state)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// What changed from `self` to `other`, one line per register, timer
+    /// and RAM byte, then the stack and screen as a whole. Empty if nothing
+    /// did.
+    pub fn diff(&self, other: &Chip8State) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut change = |name: String, before: String, after: String| {
+            if before != after {
+                changes.push(format!("{}: {} -> {}", name, before, after));
+            }
+        };
+        change(
+            "PC".into(),
+            format!("0x{:03X}", self.pc),
+            format!("0x{:03X}", other.pc),
+        );
+        change(
+            "I".into(),
+            format!("0x{:03X}", self.i),
+            format!("0x{:03X}", other.i),
+        );
+        for (x, (before, after)) in self.vx.iter().zip(&other.vx).enumerate() {
+            change(
+                format!("V{:X}", x),
+                format!("0x{:02X}", before),
+                format!("0x{:02X}", after),
+            );
+        }
+        change("SP".into(), self.sp.to_string(), other.sp.to_string());
+        change("DT".into(), self.dt.to_string(), other.dt.to_string());
+        change("ST".into(), self.st.to_string(), other.st.to_string());
+        for (addr, (before, after)) in self.ram.iter().zip(&other.ram).enumerate() {
+            change(
+                format!("RAM 0x{:03X}", addr),
+                format!("0x{:02X}", before),
+                format!("0x{:02X}", after),
+            );
+        }
+        change(
+            "RAM size".into(),
+            self.ram.len().to_string(),
+            other.ram.len().to_string(),
+        );
+        change(
+            "stack".into(),
+            format!("{:03X?}", self.stack),
+            format!("{:03X?}", other.stack),
+        );
+        if self.display != other.display {
+            let flipped = (self.display.iter().zip(&other.display))
+                .filter(|(before, after)| before != after)
+                .count();
+            changes.push(if self.display.len() == other.display.len() {
+                format!("display: {} pixels changed", flipped)
+            } else {
+                "display: resolution changed".to_string()
+            });
+        }
+        changes
+    }
+}
+
+/// Called with the machine's state and an instruction, before or after it
+/// runs.
+pub type Hook = Box<dyn FnMut(&Chip8State, &Instruction)>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{CHIP8, DEFAULT_RAM_SIZE};
+    use std::cell::RefCell;
+    use std::env;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_save_and_load_state() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x05, // LD V0, 5
+                0xF0, 0x29, // LD F, V0
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x70, 0x01, // ADD V0, 1
+                0x12, 0x06, // JP 0x206
+            ])
+            .unwrap();
+        chip8.run_frames(1).unwrap();
+        let state = chip8.state();
+        let path = env::temp_dir().join(format!("chip8-test-{}.state", std::process::id()));
+        state.write(&path).unwrap();
+
+        chip8.run_frames(1).unwrap();
+        assert_ne!(chip8.state(), state);
+        chip8.load_state(&Chip8State::read(&path, DEFAULT_RAM_SIZE).unwrap());
+        let _ = fs::remove_file(&path);
+        assert_eq!(chip8.state(), state);
+        assert!(chip8.is_pixel_lit(5, 5));
+        assert!(Chip8State::read(&path, DEFAULT_RAM_SIZE).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x05, // LD V0, 5
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x33, // LD B, V0
+                0xD0, 0x03, // DRW V0, V0, 3
+            ])
+            .unwrap();
+        let before = chip8.state();
+        assert!(before.diff(&before).is_empty());
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(
+            before.diff(&chip8.state()),
+            [
+                "PC: 0x200 -> 0x208",
+                "I: 0x000 -> 0x300",
+                "V0: 0x00 -> 0x05",
+                "RAM 0x302: 0x00 -> 0x05",
+                "display: 2 pixels changed",
+            ]
+        );
+    }
+
+    /// Regression check against the committed state the opcode test ROM
+    /// ends in.
+    #[test]
+    fn test_matches_golden_state() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(include_bytes!("../../roms/test_opcode.ch8"))
+            .unwrap();
+        chip8.run_frames(60).unwrap();
+
+        let golden: Chip8State =
+            serde_json::from_str(include_str!("../../tests/states/test_opcode.json")).unwrap();
+        let changes = golden.diff(&chip8.state());
+        assert!(changes.is_empty(), "{}", changes.join("\n"));
+    }
+
+    #[test]
+    fn test_rpl_flags_persist() {
+        let path = env::temp_dir().join(format!("chip8-test-{}.flags", std::process::id()));
+        let mut chip8 = CHIP8::headless();
+        chip8.set_flags_path(path.clone());
+        chip8
+            .load_bytes(&[
+                0x60, 0x2A, // LD V0, 0x2A
+                0x61, 0x07, // LD V1, 7
+                0xF1, 0x75, // LD R, V1
+            ])
+            .unwrap();
+        chip8.run_frames(1).unwrap();
+
+        let mut chip8 = CHIP8::headless();
+        chip8.set_flags_path(path.clone());
+        chip8
+            .load_bytes(&[
+                0xF1, 0x85, // LD V1, R
+            ])
+            .unwrap();
+        chip8.step().unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(chip8.state().vx[..3], [0x2A, 7, 0]);
+    }
+
+    #[test]
+    fn test_seeded_runs_repeat() {
+        let rom = [
+            0xC0, 0xFF, // RND V0, 0xFF
+            0xC1, 0xFF, // RND V1, 0xFF
+            0xC2, 0xFF, // RND V2, 0xFF
+        ];
+        let run = |seed| {
+            let mut chip8 = CHIP8::headless();
+            chip8.set_seed(seed);
+            chip8.load_bytes(&rom).unwrap();
+            chip8.run_frames(1).unwrap();
+            chip8.state().vx
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+
+        let mut chip8 = CHIP8::headless();
+        chip8.load_bytes(&rom).unwrap();
+        chip8.run_frames(1).unwrap();
+        let first = chip8.state().vx;
+        chip8.reset();
+        chip8.run_frames(1).unwrap();
+        assert_eq!(chip8.state().vx, first);
+    }
+
+    #[test]
+    fn test_hooks() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x05, // LD V0, 5
+                0x70, 0x01, // ADD V0, 1
+            ])
+            .unwrap();
+        for post in [false, true] {
+            let seen = seen.clone();
+            let hook = move |state: &Chip8State, instr: &Instruction| {
+                seen.borrow_mut()
+                    .push((post, state.pc, state.vx[0], instr.to_string()));
+            };
+            if post {
+                chip8.set_post_hook(hook);
+            } else {
+                chip8.set_hook(hook);
+            }
+        }
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+
+        let seen = seen.borrow();
+        let expected = [
+            (false, 0x200, 0, "LD V0, 0x05"),
+            (true, 0x202, 5, "LD V0, 0x05"),
+            (false, 0x202, 5, "ADD V0, 0x01"),
+            (true, 0x204, 6, "ADD V0, 0x01"),
+        ];
+        assert_eq!(seen.len(), expected.len());
+        for (seen, expected) in seen.iter().zip(expected) {
+            assert_eq!((seen.0, seen.1, seen.2, seen.3.as_str()), expected);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Counters shared between the CPU and the window thread.
+#[derive(Debug, Default)]
+pub struct Stats {
+    instructions: AtomicU64,
+    frames: AtomicU64,
+    draw_calls: AtomicU64,
+    collisions: AtomicU64,
+    key_waits: AtomicU64,
+    dropped_frames: AtomicU64,
+    /// The name of the ROM being run, for labelling them.
+    rom: Mutex<String>,
+}
+
+impl Stats {
+    pub fn count_instruction(&self) {
+        self.instructions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_frame(&self) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A sprite drawn with DRW, and whether it erased any pixels.
+    pub fn count_draw(&self, collision: bool) {
+        self.draw_calls.fetch_add(1, Ordering::Relaxed);
+        if collision {
+            self.collisions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// An `LD Vx, K` that got its key.
+    pub fn count_key_wait(&self) {
+        self.key_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Frames the window's frame loop skipped, having fallen too far behind
+    /// to catch up.
+    pub fn count_dropped_frames(&self, frames: u64) {
+        self.dropped_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    pub fn set_rom(&self, name: &str) {
+        *self.rom.lock().unwrap() = name.to_string();
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions.load(Ordering::Relaxed)
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn draw_calls(&self) -> u64 {
+        self.draw_calls.load(Ordering::Relaxed)
+    }
+
+    pub fn collisions(&self) -> u64 {
+        self.collisions.load(Ordering::Relaxed)
+    }
+
+    pub fn key_waits(&self) -> u64 {
+        self.key_waits.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn rom(&self) -> String {
+        self.rom.lock().unwrap().clone()
+    }
+}
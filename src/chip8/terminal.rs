@@ -0,0 +1,201 @@
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use crate::chip8::framebuffer::FrameBuffer;
+use crate::chip8::frontend::{Frontend, KeypadState};
+
+/// Terminals only report key presses, so a key counts as held this long
+/// after the last time it was typed, bridging the gaps of key repeat.
+const HOLD: Duration = Duration::from_millis(150);
+
+const ESC: u8 = 0x1B;
+const CTRL_C: u8 = 0x03;
+
+/// A frontend drawing the screen in the terminal with half block characters,
+/// two CHIP-8 rows to a line, and reading the keypad from stdin. The
+/// terminal is put in raw mode until this is dropped.
+pub struct Terminal {
+    original: libc::termios,
+    pressed: RefCell<[Option<Instant>; 16]>,
+    open: Cell<bool>,
+    beeping: bool,
+}
+
+impl Terminal {
+    pub fn new() -> io::Result<Self> {
+        let mut termios = MaybeUninit::uninit();
+        // SAFETY: tcgetattr fills in `termios` when it succeeds.
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a valid termios from tcgetattr.
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Hide the cursor and clear the screen.
+        print!("\x1b[?25l\x1b[2J");
+        io::stdout().flush()?;
+        Ok(Terminal {
+            original,
+            pressed: RefCell::new([None; 16]),
+            open: Cell::new(true),
+            beeping: false,
+        })
+    }
+
+    /// Take in whatever was typed since the last poll.
+    fn poll(&self) {
+        let mut buf = [0; 64];
+        loop {
+            // SAFETY: reads at most `buf.len()` bytes into `buf`.
+            let read =
+                unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+            if read <= 0 {
+                return;
+            }
+            let now = Instant::now();
+            for &byte in &buf[..read as usize] {
+                match byte {
+                    ESC | CTRL_C => self.open.set(false),
+                    _ => {
+                        if let Some(key) = keypad_key(byte) {
+                            self.pressed.borrow_mut()[key as usize] = Some(now);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Frontend for Terminal {
+    fn draw(&mut self, fb: &FrameBuffer) {
+        let mut out = io::stdout().lock();
+        let _ = out
+            .write_all(render(fb).as_bytes())
+            .and_then(|()| out.flush());
+    }
+
+    fn keys(&self) -> KeypadState {
+        self.poll();
+        let now = Instant::now();
+        self.pressed
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, at)| at.is_some_and(|at| now - at < HOLD))
+            .fold(KeypadState::default(), |keys, (key, _)| {
+                keys.with(key as u8)
+            })
+    }
+
+    /// Ring the terminal bell as the buzzer starts.
+    fn beep(&mut self, on: bool) {
+        if on && !self.beeping {
+            print!("\x07");
+            let _ = io::stdout().flush();
+        }
+        self.beeping = on;
+    }
+
+    fn set_title(&self, title: &str) {
+        print!("\x1b]0;{}\x07", title);
+        let _ = io::stdout().flush();
+    }
+
+    /// Whether Escape or Ctrl-C has not been pressed yet.
+    fn is_open(&self) -> bool {
+        self.poll();
+        self.open.get()
+    }
+}
+
+impl Drop for Terminal {
+    /// Show the cursor again below the picture and leave raw mode.
+    fn drop(&mut self) {
+        println!("\x1b[?25h\x1b[0m");
+        let _ = io::stdout().flush();
+        // SAFETY: `original` is the termios tcgetattr gave us.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// The keypad key typed as `byte`, on the same layout as the window.
+fn keypad_key(byte: u8) -> Option<u8> {
+    let key = match byte.to_ascii_lowercase() {
+        b'1' => 0x1,
+        b'2' => 0x2,
+        b'3' => 0x3,
+        b'4' => 0xC,
+        b'q' => 0x4,
+        b'w' => 0x5,
+        b'e' => 0x6,
+        b'r' => 0xD,
+        b'a' => 0x7,
+        b's' => 0x8,
+        b'd' => 0x9,
+        b'f' => 0xE,
+        b'z' => 0xA,
+        b'x' => 0x0,
+        b'c' => 0xB,
+        b'v' => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The screen as text from the top left corner of the terminal, each
+/// character showing a pixel and the one below it.
+fn render(fb: &FrameBuffer) -> String {
+    let (width, height) = fb.resolution();
+    let mut text = String::from("\x1b[H");
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            text.push(match (fb.is_lit(x, y), fb.is_lit(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push_str("\r\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::framebuffer::WIDTH;
+
+    #[test]
+    fn test_render() {
+        let mut fb = FrameBuffer::default();
+        // Two rows of a sprite: .X and XX
+        fb.set_pixels(0, 0, &[0x40, 0xC0]);
+        fb.set_pixels(4, 1, &[0x80]);
+        let text = render(&fb);
+        let first = text.trim_start_matches("\x1b[H").lines().next().unwrap();
+        assert!(first.starts_with("▄█  ▄ "));
+        assert_eq!(first.trim_end_matches('\r').chars().count(), WIDTH);
+        assert_eq!(text.matches("\r\n").count(), 16);
+    }
+
+    #[test]
+    fn test_keypad_layout() {
+        assert_eq!(keypad_key(b'x'), Some(0x0));
+        assert_eq!(keypad_key(b'V'), Some(0xF));
+        assert_eq!(keypad_key(b'p'), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+/// The shape of the buzzer's wave.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    /// The classic buzzer, bright and harsh.
+    #[default]
+    Square,
+    /// Softer, with weaker overtones.
+    Triangle,
+    /// A pure tone.
+    Sine,
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Waveform::Square => write!(f, "square"),
+            Waveform::Triangle => write!(f, "triangle"),
+            Waveform::Sine => write!(f, "sine"),
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!(
+                "expected `square`, `triangle` or `sine`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// How the buzzer sounds: a 440Hz square wave at a tenth of full volume
+/// by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    /// The pitch, in cycles a second.
+    pub hz: f32,
+    /// The loudness, from 0 for silence to 1 for full scale.
+    pub volume: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone {
+            waveform: Waveform::Square,
+            hz: 440.0,
+            volume: 0.1,
+        }
+    }
+}
+
+impl Tone {
+    /// The wave `phase` of the way through a cycle, from 0 up to 1. Every
+    /// waveform starts a cycle at 0 or on its way up, so the buzzer starts
+    /// without a click.
+    pub fn sample(&self, phase: f32) -> f32 {
+        let value = match self.waveform {
+            Waveform::Square if phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 1.0 - 4.0 * ((phase + 0.25) % 1.0 - 0.5).abs(),
+            Waveform::Sine => (TAU * phase).sin(),
+        };
+        value * self.volume
+    }
+}
+
+/// The pitch register's value at power on, at which a pattern plays at
+/// 4000 bits a second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// An XO-CHIP audio pattern: 128 bits played one after another, most
+/// significant bit of the first byte first, over and over while the sound
+/// timer runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pattern {
+    pub bits: [u8; 16],
+    /// The pitch register, setting the rate the bits play at.
+    pub pitch: u8,
+}
+
+impl Pattern {
+    /// Bits played a second: 4000 at the default pitch, doubling every 48
+    /// steps up.
+    pub fn rate(&self) -> f32 {
+        4000.0 * 2f32.powf((f32::from(self.pitch) - 64.0) / 48.0)
+    }
+
+    /// The bit played `phase` of the way through the pattern, from 0 up to
+    /// 1, as a sample of a wave at `volume`.
+    pub fn sample(&self, phase: f32, volume: f32) -> f32 {
+        let bit = ((phase * 128.0) as usize).min(127);
+        if self.bits[bit / 8] & 0x80 >> (bit % 8) != 0 {
+            volume
+        } else {
+            -volume
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::frontend::NullFrontend;
+    use crate::chip8::registers::Registers;
+    use crate::chip8::CHIP8;
+
+    #[test]
+    fn test_waveforms() {
+        let tone = |waveform| Tone {
+            waveform,
+            hz: 440.0,
+            volume: 0.5,
+        };
+        let samples = |waveform| {
+            let tone = tone(waveform);
+            [0.0, 0.25, 0.5, 0.75].map(|phase| tone.sample(phase))
+        };
+        assert_eq!(samples(Waveform::Square), [0.5, 0.5, -0.5, -0.5]);
+        assert_eq!(samples(Waveform::Triangle), [0.0, 0.5, 0.0, -0.5]);
+        let sine = samples(Waveform::Sine);
+        assert!((sine[1] - 0.5).abs() < 1e-6 && (sine[3] + 0.5).abs() < 1e-6);
+        assert_eq!("sine".parse(), Ok(Waveform::Sine));
+        assert!("saw".parse::<Waveform>().is_err());
+    }
+
+    #[test]
+    fn test_pattern() {
+        let mut bits = [0; 16];
+        bits[0] = 0b0100_0000;
+        bits[15] = 0b0000_0001;
+        let pattern = Pattern {
+            bits,
+            pitch: DEFAULT_PITCH,
+        };
+        assert_eq!(pattern.rate(), 4000.0);
+        let at = |bit: f32| pattern.sample(bit / 128.0, 0.5);
+        assert_eq!([at(0.0), at(1.0), at(2.0)], [-0.5, 0.5, -0.5]);
+        assert_eq!([at(126.0), at(127.0)], [-0.5, 0.5]);
+        let octave_up = Pattern { bits, pitch: 112 };
+        assert_eq!(octave_up.rate(), 8000.0);
+    }
+
+    #[test]
+    fn test_load_pattern() {
+        let mut chip8 = CHIP8::with_frontend(Registers::new(), NullFrontend);
+        let mut rom = vec![
+            0xA2, 0x08, // LD I, 0x208
+            0xF0, 0x02, // AUDIO
+            0x60, 0x70, // LD V0, 0x70
+            0xF0, 0x3A, // LD PITCH, V0
+        ];
+        rom.extend(0..16);
+        chip8.load_bytes(&rom).unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.audio_pattern(), None);
+        chip8.step().unwrap();
+        let bits: Vec<u8> = (0..16).collect();
+        assert_eq!(chip8.audio_pattern().map(|p| p.bits.to_vec()), Some(bits));
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.audio_pattern().map(|p| p.pitch), Some(0x70));
+        chip8.reset();
+        assert_eq!(chip8.audio_pattern(), None);
+    }
+}
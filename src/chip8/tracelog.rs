@@ -0,0 +1,136 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::chip8::error::Chip8Error;
+use crate::chip8::memory::Memory;
+use crate::chip8::opcodes::{self, Instruction};
+use crate::chip8::registers::Registers;
+
+/// The registers an instruction can change, apart from PC, and memory.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    v: [u8; 16],
+    i: u16,
+    sp: u8,
+    dt: u8,
+    st: u8,
+    ram: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn of(reg: &Registers, ram: Vec<u8>) -> Self {
+        Snapshot {
+            v: reg.Vx,
+            i: reg.I,
+            sp: reg.SP,
+            dt: reg.get_dt(),
+            st: reg.get_st(),
+            ram,
+        }
+    }
+
+    /// `V0=01 I=0x2A0 ... [0x300]=0102` for everything that differs from
+    /// `before`, with each run of bytes written to memory at its address.
+    pub fn delta(&self, before: &Snapshot) -> String {
+        let mut out = String::new();
+        for (x, (now, was)) in self.v.iter().zip(&before.v).enumerate() {
+            if now != was {
+                let _ = write!(out, " V{:X}={:02X}", x, now);
+            }
+        }
+        if self.i != before.i {
+            let _ = write!(out, " I=0x{:03X}", self.i);
+        }
+        if self.sp != before.sp {
+            let _ = write!(out, " SP={}", self.sp);
+        }
+        if self.dt != before.dt {
+            let _ = write!(out, " DT={:02X}", self.dt);
+        }
+        if self.st != before.st {
+            let _ = write!(out, " ST={:02X}", self.st);
+        }
+        let mut run: Option<usize> = None;
+        for (addr, (now, was)) in self.ram.iter().zip(&before.ram).enumerate() {
+            match (now != was, run) {
+                (true, None) => {
+                    let _ = write!(out, " [0x{:03X}]={:02X}", addr, now);
+                    run = Some(addr);
+                }
+                (true, Some(_)) => {
+                    let _ = write!(out, "{:02X}", now);
+                }
+                (false, _) => run = None,
+            }
+        }
+        out
+    }
+}
+
+/// `0x200  6001  LD V0, 0x01     V0=01`: where `instr` was, its opcode, and
+/// the registers it changed from `before` to `after`.
+pub fn line(
+    pc: usize,
+    opcode: u16,
+    instr: &Instruction,
+    after: &Snapshot,
+    before: &Snapshot,
+) -> String {
+    let line = format!(
+        "0x{:03X}  {:04X}  {:<16}{}",
+        pc,
+        opcode,
+        instr.to_string(),
+        after.delta(before)
+    );
+    line.trim_end().to_string()
+}
+
+/// Writes a `line` for every instruction a CHIP8 runs.
+pub(crate) struct Tracer {
+    out: Box<dyn Write>,
+    before: Snapshot,
+}
+
+impl Tracer {
+    /// A tracer for a CHIP8 whose registers and memory are `reg` and `ram`
+    /// now.
+    pub fn new(out: Box<dyn Write>, reg: &Registers, ram: &Memory) -> Self {
+        Tracer {
+            out,
+            before: Snapshot::of(reg, ram.to_vec()),
+        }
+    }
+
+    /// Log the instruction `opcode` at `pc` that left the registers and
+    /// memory as `reg` and `ram`. Opcodes run by extensions are logged
+    /// without a mnemonic.
+    pub fn executed(
+        &mut self,
+        pc: usize,
+        opcode: u16,
+        reg: &Registers,
+        ram: &Memory,
+    ) -> io::Result<()> {
+        let after = Snapshot::of(reg, ram.to_vec());
+        let before = std::mem::replace(&mut self.before, after.clone());
+        match opcodes::decode(opcode) {
+            Ok(instr) => writeln!(self.out, "{}", line(pc, opcode, &instr, &after, &before)),
+            Err(_) => writeln!(
+                self.out,
+                "0x{:03X}  {:04X}  ???{}",
+                pc,
+                opcode,
+                after.delta(&before)
+            ),
+        }
+    }
+
+    /// Log why the instruction `opcode` could not run.
+    pub fn failed(&mut self, opcode: Option<u16>, e: &Chip8Error) -> io::Result<()> {
+        match opcode {
+            Some(opcode) => writeln!(self.out, "0x{:03X}  {:04X}  {}", e.at(), opcode, e),
+            None => writeln!(self.out, "0x{:03X}  {}", e.at(), e),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// The buffer passed between the two ends, and whether it was published
+/// since the reader last took it.
+type Middle<T> = Arc<Mutex<(T, bool)>>;
+
+/// The writing end of a triple buffer. It fills a back buffer of its own and
+/// publishes it by swapping it for the one in the middle, so it never waits
+/// on the reader for longer than the swap.
+pub struct Publisher<T> {
+    back: T,
+    middle: Middle<T>,
+}
+
+/// The reading end of a triple buffer. It swaps the newest published buffer
+/// for its own front buffer, so it always has a whole one to read while the
+/// next is written.
+pub struct Reader<T> {
+    front: T,
+    middle: Middle<T>,
+}
+
+/// A triple buffer whose three buffers start out as `initial`.
+pub fn triple_buffer<T: Clone>(initial: T) -> (Publisher<T>, Reader<T>) {
+    let middle = Arc::new(Mutex::new((initial.clone(), false)));
+    let publisher = Publisher {
+        back: initial.clone(),
+        middle: middle.clone(),
+    };
+    let reader = Reader {
+        front: initial,
+        middle,
+    };
+    (publisher, reader)
+}
+
+impl<T> Publisher<T> {
+    /// The buffer to write the next one into. It holds an older buffer, not
+    /// necessarily the last one published.
+    pub fn back(&mut self) -> &mut T {
+        &mut self.back
+    }
+
+    /// Hand the back buffer to the reader, replacing any it has not taken.
+    pub fn publish(&mut self) {
+        let mut middle = self.middle.lock().unwrap();
+        mem::swap(&mut middle.0, &mut self.back);
+        middle.1 = true;
+    }
+}
+
+impl<T> Reader<T> {
+    /// The newest buffer published, or the last one read if nothing was
+    /// published since.
+    pub fn latest(&mut self) -> &T {
+        let mut middle = self.middle.lock().unwrap();
+        if middle.1 {
+            mem::swap(&mut middle.0, &mut self.front);
+            middle.1 = false;
+        }
+        &self.front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triple_buffer() {
+        let (mut publisher, mut reader) = triple_buffer(0);
+        assert_eq!(*reader.latest(), 0);
+
+        *publisher.back() = 1;
+        publisher.publish();
+        *publisher.back() = 2;
+        publisher.publish();
+        // Only the newest is read, however often.
+        assert_eq!(*reader.latest(), 2);
+        assert_eq!(*reader.latest(), 2);
+
+        *publisher.back() = 3;
+        assert_eq!(*reader.latest(), 2);
+        publisher.publish();
+        assert_eq!(*reader.latest(), 3);
+    }
+}
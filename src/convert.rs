@@ -0,0 +1,201 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use argh::FromArgs;
+
+use crate::chip8::quirks::{self, Quirks};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "convert")]
+/// Export a ROM as a Rust module or C array for embedding
+pub struct ConvertArgs {
+    #[argh(positional)]
+    /// filename of the Chip-8 cartridge binary
+    pub filename: String,
+
+    #[argh(option, default = "Format::Rust")]
+    /// output format: `rust` (default) or `c`
+    format: Format,
+
+    #[argh(option)]
+    /// identifier for the array, derived from the filename by default
+    name: Option<String>,
+
+    #[argh(option, short = 'o')]
+    /// write to this file instead of stdout
+    output: Option<String>,
+
+    #[argh(option)]
+    /// the quirks to play it with, as for playing, written as a constant per
+    /// quirk after the array
+    quirks: Option<Quirks>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Rust,
+    C,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(Format::Rust),
+            "c" => Ok(Format::C),
+            _ => Err(format!("unknown format `{s}`, expected `rust` or `c`")),
+        }
+    }
+}
+
+impl ConvertArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let rom = fs::read(&self.filename)?;
+        let source_name = Path::new(&self.filename)
+            .file_name()
+            .map_or(self.filename.clone(), |f| f.to_string_lossy().into_owned());
+        let stem = Path::new(&source_name)
+            .file_stem()
+            .map_or(source_name.clone(), |s| s.to_string_lossy().into_owned());
+        let name = identifier(self.name.as_deref().unwrap_or(&stem));
+
+        let source = match self.format {
+            Format::Rust => to_rust(&name.to_uppercase(), &source_name, &rom, self.quirks),
+            Format::C => to_c(&name.to_lowercase(), &source_name, &rom, self.quirks),
+        };
+
+        match &self.output {
+            Some(output) => fs::write(output, source),
+            None => {
+                print!("{source}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Turn `name` into a valid Rust and C identifier.
+fn identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+fn hex_lines(rom: &[u8], indent: &str) -> String {
+    let mut out = String::new();
+    for line in rom.chunks(16) {
+        let bytes: Vec<String> = line.iter().map(|b| format!("0x{b:02X}")).collect();
+        let _ = writeln!(out, "{indent}{},", bytes.join(", "));
+    }
+    out
+}
+
+/// Every quirk by the name `--quirks` takes it, as an identifier, and
+/// whether `quirks` turns it on.
+fn quirk_flags(quirks: Quirks) -> impl Iterator<Item = (String, bool)> {
+    let on = quirks.names();
+    quirks::NAMES
+        .iter()
+        .map(move |name| (identifier(name), on.contains(name)))
+}
+
+pub fn to_rust(name: &str, source_name: &str, rom: &[u8], quirks: Option<Quirks>) -> String {
+    let mut out = format!(
+        "//! Generated by `rust-chip-8 convert` from `{source_name}`.\n\
+         \n\
+         pub const {name}: [u8; {len}] = [\n{bytes}];\n",
+        len = rom.len(),
+        bytes = hex_lines(rom, "    "),
+    );
+    if let Some(quirks) = quirks {
+        out.push('\n');
+        for (quirk, on) in quirk_flags(quirks) {
+            let _ = writeln!(
+                out,
+                "pub const {name}_{}: bool = {on};",
+                quirk.to_uppercase()
+            );
+        }
+    }
+    out
+}
+
+pub fn to_c(name: &str, source_name: &str, rom: &[u8], quirks: Option<Quirks>) -> String {
+    let mut out = format!(
+        "/* Generated by `rust-chip-8 convert` from `{source_name}`. */\n\
+         \n\
+         const unsigned char {name}[] = {{\n{bytes}}};\n\
+         const unsigned int {name}_len = {len};\n",
+        len = rom.len(),
+        bytes = hex_lines(rom, "    "),
+    );
+    if let Some(quirks) = quirks {
+        out.push('\n');
+        for (quirk, on) in quirk_flags(quirks) {
+            let _ = writeln!(out, "const int {name}_{quirk} = {};", u8::from(on));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!(identifier("pong"), "pong");
+        assert_eq!(identifier("8ceattourny_d1"), "_8ceattourny_d1");
+        assert_eq!(identifier("space-invaders v2"), "space_invaders_v2");
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            to_rust("PONG", "pong.ch8", &[0x00, 0xE0], None),
+            "//! Generated by `rust-chip-8 convert` from `pong.ch8`.\n\
+             \n\
+             pub const PONG: [u8; 2] = [\n    0x00, 0xE0,\n];\n"
+        );
+        assert_eq!(
+            to_c("pong", "pong.ch8", &[0x00, 0xE0], None),
+            "/* Generated by `rust-chip-8 convert` from `pong.ch8`. */\n\
+             \n\
+             const unsigned char pong[] = {\n    0x00, 0xE0,\n};\n\
+             const unsigned int pong_len = 2;\n"
+        );
+    }
+
+    #[test]
+    fn test_quirk_constants() {
+        let quirks = "wrap,increment-i".parse().unwrap();
+        let rust = to_rust("PONG", "pong.ch8", &[0x00, 0xE0], Some(quirks));
+        assert!(rust.ends_with(
+            "];\n\
+             \n\
+             pub const PONG_WRAP: bool = true;\n\
+             pub const PONG_SHIFT_VY: bool = false;\n\
+             pub const PONG_INCREMENT_I: bool = true;\n\
+             pub const PONG_ADD_I_CARRY: bool = false;\n\
+             pub const PONG_MASK_I: bool = false;\n"
+        ));
+        let c = to_c("pong", "pong.ch8", &[0x00, 0xE0], Some(quirks));
+        assert!(c.ends_with(
+            "const unsigned int pong_len = 2;\n\
+             \n\
+             const int pong_wrap = 1;\n\
+             const int pong_shift_vy = 0;\n\
+             const int pong_increment_i = 1;\n\
+             const int pong_add_i_carry = 0;\n\
+             const int pong_mask_i = 0;\n"
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::{self, BufRead, Write};
+
+/// ROMs from `roms/` built into the binary, so there is something to play
+/// without hunting for ROM files.
+pub const DEMOS: &[(&str, &[u8])] = &[
+    ("pong", include_bytes!("../roms/pong.ch8")),
+    ("breakout", include_bytes!("../roms/breakout.ch8")),
+    ("logo", include_bytes!("../roms/logo.ch8")),
+    ("keypad", include_bytes!("../roms/keypad.ch8")),
+    ("test_opcode", include_bytes!("../roms/test_opcode.ch8")),
+    (
+        "delay_timer_test",
+        include_bytes!("../roms/delay_timer_test.ch8"),
+    ),
+    (
+        "random_number_test",
+        include_bytes!("../roms/random_number_test.ch8"),
+    ),
+];
+
+pub fn find(name: &str) -> Option<(&'static str, &'static [u8])> {
+    DEMOS.iter().copied().find(|(demo, _)| *demo == name)
+}
+
+/// List the demos on stdout and let the user pick one by number or name.
+pub fn pick() -> Option<(&'static str, &'static [u8])> {
+    println!("No ROM given, pick one of the built-in demos:");
+    for (i, (name, _)) in DEMOS.iter().enumerate() {
+        println!("  {}) {name}", i + 1);
+    }
+
+    loop {
+        print!("> ");
+        io::stdout().flush().ok()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        let choice = line.trim();
+        let demo = match choice.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| DEMOS.get(i).copied()),
+            Err(_) => find(choice),
+        };
+        match demo {
+            Some(demo) => return Some(demo),
+            None => println!("No demo `{choice}`"),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::fs;
+use std::io;
+
+use argh::FromArgs;
+
+use crate::chip8::disasm;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "disasm")]
+/// Disassemble a ROM, following jumps and calls to tell code from data
+pub struct DisasmArgs {
+    #[argh(positional)]
+    /// filename of the Chip-8 cartridge binary
+    pub filename: String,
+
+    #[argh(option, short = 'o')]
+    /// write to this file instead of stdout
+    output: Option<String>,
+}
+
+impl DisasmArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let rom = fs::read(&self.filename)?;
+        let listing = format!("{}\n", disasm::disassemble(&rom));
+        match &self.output {
+            Some(output) => fs::write(output, listing),
+            None => {
+                print!("{listing}");
+                Ok(())
+            }
+        }
+    }
+}
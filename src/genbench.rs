@@ -0,0 +1,86 @@
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use argh::FromArgs;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "genbench")]
+/// Write a synthetic ROM that stresses one part of the interpreter
+pub struct GenbenchArgs {
+    #[argh(positional)]
+    /// what to stress: `alu` (arithmetic loop), `draw` (DRW storm) or `bcd` (BCD and register loads)
+    kind: Kind,
+
+    #[argh(option, short = 'o')]
+    /// where to write the ROM
+    pub output: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Alu,
+    Draw,
+    Bcd,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alu" => Ok(Kind::Alu),
+            "draw" => Ok(Kind::Draw),
+            "bcd" => Ok(Kind::Bcd),
+            _ => Err(format!(
+                "unknown benchmark `{s}`, expected `alu`, `draw` or `bcd`"
+            )),
+        }
+    }
+}
+
+impl GenbenchArgs {
+    pub fn run(&self) -> io::Result<()> {
+        fs::write(&self.output, rom(self.kind))
+    }
+}
+
+/// The benchmark programs loop forever, so they run for as many cycles as
+/// the benchmark asks for.
+pub fn rom(kind: Kind) -> Vec<u8> {
+    let program: &[u16] = match kind {
+        Kind::Alu => &[
+            0x6001, // 0x200 LD V0, 0x01
+            0x6102, // 0x202 LD V1, 0x02
+            0x8014, // 0x204 ADD V0, V1
+            0x8013, // 0x206 XOR V0, V1
+            0x8015, // 0x208 SUB V0, V1
+            0x8011, // 0x20A OR V0, V1
+            0x8012, // 0x20C AND V0, V1
+            0x8106, // 0x20E SHR V1, V0
+            0x810E, // 0x210 SHL V1, V0
+            0x7101, // 0x212 ADD V1, 0x01
+            0x1204, // 0x214 JP 0x204
+        ],
+        Kind::Draw => &[
+            0x00E0, // 0x200 CLS
+            0xA000, // 0x202 LD I, 0x000 (the "0" font sprite)
+            0x6000, // 0x204 LD V0, 0x00
+            0x6100, // 0x206 LD V1, 0x00
+            0xD015, // 0x208 DRW V0, V1, 5
+            0x7005, // 0x20A ADD V0, 0x05
+            0x7103, // 0x20C ADD V1, 0x03
+            0x1208, // 0x20E JP 0x208
+        ],
+        Kind::Bcd => &[
+            0xA300, // 0x200 LD I, 0x300
+            0x6000, // 0x202 LD V0, 0x00
+            0xF033, // 0x204 LD B, V0
+            0xF265, // 0x206 LD V2, [I]
+            0xF255, // 0x208 LD [I], V2
+            0x7001, // 0x20A ADD V0, 0x01
+            0x1204, // 0x20C JP 0x204
+        ],
+    };
+    program.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
@@ -0,0 +1,39 @@
+//! The CHIP-8 interpreter, for embedding without the emulator's window.
+//!
+//! [`chip8::CHIP8::headless`] builds a machine with no window: load a ROM,
+//! drive it with `run_frames`, `step` and `tick_timers`, and read the screen
+//! back with `is_pixel_lit` or `screenshot`. To show the screen, read keys
+//! and sound the buzzer yourself, implement [`chip8::frontend::Frontend`] and
+//! pass it to `CHIP8::with_frontend`, then call `step_frame` 60 times a
+//! second. `set_hook` and `set_post_hook` watch every instruction as it
+//! runs.
+//!
+//! ```
+//! use rust_chip_8::chip8::CHIP8;
+//!
+//! let mut chip8 = CHIP8::headless();
+//! chip8.load_bytes(&[0x12, 0x00]).unwrap(); // JP 0x200
+//! chip8.run_frames(60).unwrap();
+//! assert_eq!(chip8.frames(), 60);
+//! ```
+//!
+//! For snapshot tests, `screen_hash` hashes the pixels lit, whatever the
+//! colors, so a test can check the screen a ROM shows after so many frames
+//! against the hash of a known good run, as the `test` command prints it:
+//!
+//! ```
+//! use rust_chip_8::chip8::CHIP8;
+//!
+//! let mut chip8 = CHIP8::headless();
+//! chip8.load_bytes(&[
+//!     0xF0, 0x29, // LD F, V0
+//!     0xD0, 0x05, // DRW V0, V0, 5
+//!     0x12, 0x04, // JP 0x204
+//! ]).unwrap();
+//! chip8.run_frames(300).unwrap();
+//! assert_eq!(chip8.screen_hash(), 0x7b2588e3d7cec2b5);
+//! ```
+
+pub mod chip8;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
@@ -1,22 +1,1221 @@
-mod chip8;
+mod archive;
+mod bench;
+mod convert;
+#[cfg(feature = "demo-roms")]
+mod demos;
+mod disasm;
+mod genbench;
+mod metrics;
+#[cfg(feature = "discord")]
+mod presence;
+mod quirks;
+mod romconfig;
+mod romtest;
+#[cfg(feature = "gamepad")]
+mod rumble;
+mod soak;
+mod trace;
 
 use argh::FromArgs;
-use chip8::CHIP8;
+use bench::BenchArgs;
+use chip8::display::{SoundIndicator, MAX_SCALE};
+use chip8::download;
+use chip8::extension::OpcodePattern;
+use chip8::font::FontStyle;
+use chip8::frontend::Frontend;
+#[cfg(feature = "gamepad")]
+use chip8::gamepad::Gamepad;
+use chip8::gamepad::PadMap;
+use chip8::keymap::{Keymap, Layout};
+use chip8::movie::Movie;
+use chip8::octo::{Cartridge, Metadata};
+use chip8::palette::{Color, Palette};
+use chip8::quirks::Quirks;
+#[cfg(any(all(feature = "tui", unix), feature = "sdl2"))]
+use chip8::registers::Registers;
+use chip8::romdb;
+#[cfg(feature = "sdl2")]
+use chip8::sdl::SdlWindow;
+#[cfg(all(feature = "tui", unix))]
+use chip8::terminal::Terminal;
+#[cfg(any(feature = "audio", feature = "sdl2"))]
+use chip8::tone::Tone;
+use chip8::tone::Waveform;
+use chip8::{
+    Chip8Error, OnHalt, OnUnknown, PcOverflow, StackLimit, CHIP8, DEFAULT_RAM_SIZE, MAX_RAM_SIZE,
+};
+use convert::ConvertArgs;
+use disasm::DisasmArgs;
+use genbench::GenbenchArgs;
+use quirks::QuirksTestArgs;
+use romconfig::RomConfig;
+use romtest::TestArgs;
+use rust_chip_8::chip8;
+use soak::SoakArgs;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+#[cfg(any(all(feature = "tui", unix), feature = "sdl2"))]
+use std::thread;
+#[cfg(any(all(feature = "tui", unix), feature = "sdl2"))]
+use std::time::{Duration, Instant};
+use trace::TraceArgs;
 
 #[derive(FromArgs)]
 /// Chip-8 Emulator
 struct Args {
     #[argh(positional)]
-    /// filename of the Chip-8 cartridge binary
-    filename: String,
+    /// filename of the Chip-8 cartridge binary, a .zip holding one, or `-` to read it from stdin
+    filename: Option<String>,
+
+    #[argh(switch)]
+    /// pace the frames and timers off the audio output instead of the clock
+    sync_to_audio: bool,
+
+    #[argh(option)]
+    /// the buzzer's wave: square (default), triangle or sine
+    waveform: Option<Waveform>,
+
+    #[argh(option)]
+    /// the buzzer's pitch in Hz, from 20 to 20000 (default 440)
+    pitch: Option<u32>,
+
+    #[argh(option)]
+    /// the buzzer's volume in percent of full scale (default 10)
+    volume: Option<u8>,
+
+    #[argh(option)]
+    /// instructions per second, e.g. 500, 700 or 1000 (default 600, or the
+    /// speed the ROM database knows the ROM to need)
+    cpu_hz: Option<u32>,
+
+    #[argh(option)]
+    /// how many nested calls are allowed: a depth such as 12 or 16 (default), or `unlimited`
+    stack_limit: Option<StackLimit>,
+
+    #[argh(option, default = "DEFAULT_RAM_SIZE")]
+    /// bytes of RAM, from 4096 (default) up to XO-CHIP's 65536
+    ram_size: usize,
+
+    #[argh(option, default = "OnUnknown::default()")]
+    /// what to do on an unknown opcode: halt (default) with an error, skip it,
+    /// or warn the first time at each address and skip it
+    on_unknown: OnUnknown,
+
+    #[argh(option, default = "OnHalt::default()")]
+    /// what to do once the program jumps to itself: spin (default) there,
+    /// exit, print the screen and exit, or show an overlay in the window
+    on_halt: OnHalt,
+
+    #[argh(option, default = "PcOverflow::default()")]
+    /// what to do when PC runs off the end of RAM: stop (default) and report
+    /// where, error, or wrap around to the start
+    pc_overflow: PcOverflow,
+
+    #[argh(option)]
+    /// interpreter quirks to emulate, comma-separated: wrap, shift-vy,
+    /// increment-i, add-i-carry, mask-i, or none (default)
+    quirks: Option<Quirks>,
+
+    #[argh(option, default = "FontStyle::default()")]
+    /// hex digit glyphs: vip, chip48 (default), schip, dream6800 or eti660
+    font_style: FontStyle,
+
+    #[argh(option)]
+    /// a built-in palette: classic, phosphor, amber, lcd, paper or octo
+    palette: Option<Palette>,
+
+    #[argh(option)]
+    /// the color of lit pixels, e.g. `00FF00`, overriding the palette
+    fg: Option<Color>,
+
+    #[argh(option)]
+    /// the color of unlit pixels, e.g. `001100`, overriding the palette
+    bg: Option<Color>,
+
+    #[argh(option)]
+    /// the 16 keys standing in for the keypad, row by row (default
+    /// `1234qwerasdfzxcv`)
+    keymap: Option<Keymap>,
+
+    #[argh(option)]
+    /// put the keypad on the same keys of a `qwerty` (default), `azerty` or
+    /// `dvorak` keyboard, or on the `numpad`
+    layout: Option<Layout>,
+
+    #[argh(option)]
+    /// the keypad keys controller buttons stand for, e.g. `up=2,south=5`
+    /// (default `up=2,down=8,left=4,right=6,south=5`)
+    gamepad: Option<PadMap>,
+
+    #[argh(switch)]
+    /// print characters (0FX0) and hex bytes (0FX1) sent by the ROM to stdout
+    debug_console: bool,
+
+    #[argh(switch)]
+    /// start paused in the debugger, taking step, registers and stack
+    /// commands from the terminal
+    debug: bool,
+
+    #[argh(option)]
+    /// skip opcodes matching this pattern (e.g. `FX00`) that the interpreter
+    /// does not implement, instead of aborting; may be repeated
+    ignore_opcode: Vec<OpcodePattern>,
+
+    #[argh(option)]
+    /// export the framebuffer as the named shared-memory region for capture tools
+    shm: Option<String>,
+
+    #[argh(option)]
+    /// log a hash of the display for every frame to this file
+    frame_hashes: Option<String>,
+
+    #[argh(option)]
+    /// serve Prometheus metrics on this local port
+    metrics_port: Option<u16>,
+
+    #[argh(option)]
+    /// publish the running game to Discord Rich Presence under this application id
+    discord_client_id: Option<String>,
+
+    #[argh(option)]
+    /// play one of the built-in demo ROMs (requires the `demo-roms` feature)
+    demo: Option<String>,
+
+    #[argh(option, default = "Backend::default()")]
+    /// what draws the window: minifb (default), or sdl2 to go fullscreen and
+    /// wait for vsync (requires the `sdl2` feature)
+    backend: Backend,
+
+    #[argh(switch)]
+    /// cover the whole desktop, with --backend sdl2
+    fullscreen: bool,
+
+    #[argh(switch)]
+    /// show frames as soon as they are drawn rather than waiting for the
+    /// display's refresh, with --backend sdl2
+    no_vsync: bool,
+
+    #[argh(switch)]
+    /// draw the screen in the terminal and read keys from it instead of
+    /// opening a window (requires the `tui` feature)
+    tui: bool,
+
+    #[argh(switch)]
+    /// run without a window, a 60th of --cpu-hz instructions and one timer
+    /// tick per frame
+    headless: bool,
+
+    #[argh(option)]
+    /// exit after this many frames
+    frames: Option<u64>,
+
+    #[argh(option)]
+    /// save the screen as a PNG to this path on exit
+    screenshot: Option<String>,
+
+    #[argh(option, default = "0")]
+    /// let pixels that go out fade over this many frames, to hide flicker
+    fade: u8,
+
+    #[argh(option)]
+    /// screen pixels per CHIP-8 pixel, from 1 to 32 (default 16, or more on
+    /// HiDPI desktops); + and - change it while playing
+    scale: Option<usize>,
+
+    #[argh(switch)]
+    /// show the frames and instructions a second in the corner of the window
+    show_speed: bool,
+
+    #[argh(option)]
+    /// show when the buzzer sounds, with an `icon` in the corner or a
+    /// `border` around the screen
+    sound_indicator: Option<SoundIndicator>,
+
+    #[argh(switch)]
+    /// pause the program and its timers while the window is in the
+    /// background
+    pause_unfocused: bool,
+
+    #[argh(switch)]
+    /// print the average and worst time from a key press to the program
+    /// seeing it on exit
+    input_latency: bool,
+
+    #[argh(switch)]
+    /// print frame interval and presentation time percentiles on exit
+    frame_times: bool,
+
+    #[argh(switch)]
+    /// print instructions, frames, draw calls, collisions and key waits on exit
+    stats: bool,
+
+    #[argh(switch)]
+    /// print the most run addresses and opcodes on exit
+    profile: bool,
+
+    #[argh(switch)]
+    /// rumble connected controllers while the sound timer is running
+    rumble: bool,
+
+    #[argh(option)]
+    /// record the keypad to this movie file, for replaying the run exactly
+    record: Option<String>,
+
+    #[argh(option)]
+    /// draw random numbers from this seed, so runs can be repeated
+    seed: Option<u64>,
+
+    #[argh(option)]
+    /// play back a movie file recorded with --record
+    replay: Option<String>,
+
+    #[argh(option)]
+    /// record the screen to this animated GIF until exit
+    gif: Option<String>,
+
+    #[argh(option)]
+    /// write every instruction run, with the registers it changed, to this file
+    trace: Option<String>,
+
+    #[argh(option)]
+    /// run this Rhai script every frame and instruction, letting it read and
+    /// change registers and memory (requires the `scripting` feature)
+    script: Option<String>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Bench(BenchArgs),
+    Convert(ConvertArgs),
+    Disasm(DisasmArgs),
+    Genbench(GenbenchArgs),
+    Trace(TraceArgs),
+    QuirksTest(QuirksTestArgs),
+    Soak(SoakArgs),
+    Test(TestArgs),
+}
+
+/// Where the ROM to play comes from.
+enum Rom<'a> {
+    File(&'a str),
+    #[allow(dead_code)]
+    Demo(&'static str, &'static [u8]),
+}
+
+impl Rom<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Rom::File(filename) => filename,
+            Rom::Demo(name, _) => name,
+        }
+    }
+
+    /// The ROM's file, unless it came from stdin, a URL or the demos.
+    fn path(&self) -> Option<&Path> {
+        match *self {
+            Rom::File(filename) if filename != "-" && !download::is_url(filename) => {
+                Some(Path::new(filename))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What draws the window.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Backend {
+    #[default]
+    Minifb,
+    Sdl2,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minifb" => Ok(Backend::Minifb),
+            "sdl2" => Ok(Backend::Sdl2),
+            _ => Err(format!("expected `minifb` or `sdl2`, got `{}`", s)),
+        }
+    }
+}
+
+#[cfg(feature = "demo-roms")]
+fn default_rom(demo: Option<&str>) -> Option<Rom<'static>> {
+    let demo = match demo {
+        Some(name) => demos::find(name).or_else(|| {
+            let names: Vec<_> = demos::DEMOS.iter().map(|(name, _)| *name).collect();
+            eprintln!("No demo `{name}`, expected one of: {}", names.join(", "));
+            None
+        }),
+        None => demos::pick(),
+    };
+    demo.map(|(name, rom)| Rom::Demo(name, rom))
+}
+
+#[cfg(not(feature = "demo-roms"))]
+fn default_rom(demo: Option<&str>) -> Option<Rom<'static>> {
+    match demo {
+        Some(_) => eprintln!("--demo requires building with the `demo-roms` feature"),
+        None => eprintln!("Missing ROM filename, see --help for usage"),
+    }
+    None
+}
+
+#[cfg(feature = "audio")]
+fn new_chip8(sync_to_audio: bool) -> CHIP8 {
+    if sync_to_audio {
+        match CHIP8::synced_to_audio() {
+            Ok(chip8) => return chip8,
+            Err(e) => {
+                eprintln!("Could not sync to audio, letting the CPU loop tick the timers: {e}")
+            }
+        }
+    }
+    CHIP8::new()
+}
+
+#[cfg(not(feature = "audio"))]
+fn new_chip8(sync_to_audio: bool) -> CHIP8 {
+    if sync_to_audio {
+        eprintln!("--sync-to-audio requires building with the `audio` feature");
+    }
+    CHIP8::new()
+}
+
+/// The buzzer's sound as asked with `--waveform`, `--pitch` and `--volume`.
+#[cfg(any(feature = "audio", feature = "sdl2"))]
+fn tone(args: &Args) -> Tone {
+    let default = Tone::default();
+    Tone {
+        waveform: args.waveform.unwrap_or(default.waveform),
+        hz: args.pitch.map_or(default.hz, |hz| hz as f32),
+        volume: args
+            .volume
+            .map_or(default.volume, |volume| f32::from(volume) / 100.0),
+    }
+}
+
+#[cfg(feature = "audio")]
+fn set_tone(chip8: &CHIP8, args: &Args) {
+    chip8.set_tone(tone(args));
+}
+
+#[cfg(not(feature = "audio"))]
+fn set_tone(_chip8: &CHIP8, args: &Args) {
+    if args.waveform.is_some() || args.pitch.is_some() || args.volume.is_some() {
+        eprintln!("--waveform, --pitch and --volume require building with the `audio` feature");
+    }
+}
+
+/// `argh::from_env`, except that a lone `-` is moved past a `--` so argh
+/// takes it as the filename rather than an unknown option.
+fn args_from_env() -> Args {
+    let mut strings: Vec<String> = env::args().collect();
+    if let Some(i) = strings.iter().position(|s| s == "-") {
+        if !strings.iter().any(|s| s == "--") {
+            strings.remove(i);
+            strings.extend(["--".to_string(), "-".to_string()]);
+        }
+    }
+
+    let cmd = Path::new(&strings[0])
+        .file_name()
+        .map_or(strings[0].as_str(), |name| {
+            name.to_str().unwrap_or("rust-chip-8")
+        });
+    let strs: Vec<&str> = strings.iter().map(String::as_str).collect();
+    Args::from_args(&[cmd], &strs[1..]).unwrap_or_else(|early_exit| {
+        process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    early_exit.output, cmd
+                );
+                1
+            }
+        })
+    })
 }
 
 fn main() {
-    let filename = argh::from_env::<Args>().filename;
-    let mut chip8 = CHIP8::new();
+    let args = args_from_env();
+
+    match &args.command {
+        Some(Command::Bench(bench)) => {
+            if let Err(e) = bench.run() {
+                eprintln!("Could not bench `{}`: {e}", bench.filename);
+                process::exit(1);
+            }
+        }
+        Some(Command::Convert(convert)) => {
+            if let Err(e) = convert.run() {
+                eprintln!("Could not convert `{}`: {e}", convert.filename);
+                process::exit(1);
+            }
+        }
+        Some(Command::Disasm(disasm)) => {
+            if let Err(e) = disasm.run() {
+                eprintln!("Could not disassemble `{}`: {e}", disasm.filename);
+                process::exit(1);
+            }
+        }
+        Some(Command::Genbench(genbench)) => {
+            if let Err(e) = genbench.run() {
+                eprintln!("Could not write `{}`: {e}", genbench.output);
+                process::exit(1);
+            }
+        }
+        Some(Command::Trace(trace)) => {
+            if let Err(e) = trace.run() {
+                eprintln!("Could not trace `{}`: {e}", trace.filename);
+                process::exit(1);
+            }
+        }
+        Some(Command::QuirksTest(quirks_test)) => {
+            if let Err(e) = quirks_test.run() {
+                eprintln!("Could not run the quirks test: {e}");
+                process::exit(1);
+            }
+        }
+        Some(Command::Soak(soak)) => {
+            if let Err(e) = soak.run() {
+                eprintln!("Could not soak `{}`: {e}", soak.dir);
+                process::exit(1);
+            }
+        }
+        Some(Command::Test(test)) => {
+            if let Err(e) = test.run() {
+                eprintln!("Test of `{}` failed: {e}", test.filename);
+                process::exit(1);
+            }
+        }
+        None => {
+            let rom = match (&args.filename, &args.demo) {
+                (Some(_), Some(_)) => {
+                    eprintln!("Give either a ROM file or --demo, not both");
+                    process::exit(1);
+                }
+                (Some(filename), None) => Some(Rom::File(filename)),
+                (None, demo) => default_rom(demo.as_deref()),
+            };
+            if args.cpu_hz == Some(0) {
+                eprintln!("--cpu-hz must be at least 1");
+                process::exit(1);
+            }
+            if args
+                .scale
+                .is_some_and(|scale| !(1..=MAX_SCALE).contains(&scale))
+            {
+                eprintln!("--scale must be from 1 to {MAX_SCALE}");
+                process::exit(1);
+            }
+            if args.pitch.is_some_and(|hz| !(20..=20_000).contains(&hz)) {
+                eprintln!("--pitch must be from 20 to 20000");
+                process::exit(1);
+            }
+            if args.volume.is_some_and(|volume| volume > 100) {
+                eprintln!("--volume must be from 0 to 100");
+                process::exit(1);
+            }
+            if !(DEFAULT_RAM_SIZE..=MAX_RAM_SIZE).contains(&args.ram_size) {
+                eprintln!("--ram-size must be from {DEFAULT_RAM_SIZE} to {MAX_RAM_SIZE}");
+                process::exit(1);
+            }
+            match rom {
+                Some(rom) if args.tui => play_in_terminal(&args, rom),
+                Some(rom) if args.backend == Backend::Sdl2 => play_with_sdl(&args, rom),
+                Some(rom) => play(&args, rom),
+                None => process::exit(1),
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tui", unix))]
+fn play_in_terminal(args: &Args, rom: Rom) {
+    for flag in window_flags(args) {
+        eprintln!("{flag} needs a window, ignoring it with --tui");
+    }
+    let terminal = match Terminal::new() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Could not draw in the terminal: {e}");
+            process::exit(1);
+        }
+    };
+    let mut chip8 = CHIP8::with_frontend(Registers::new(), terminal);
+    if configure(&mut chip8, args, &rom, &overrides(args)).is_none() {
+        return;
+    }
+    let result = run_paced(&mut chip8, args.frames);
+    finish(chip8, args, result, Vec::new());
+}
+
+/// Run `frames` frames, or until the frontend is closed, at 60 a second.
+#[cfg(any(all(feature = "tui", unix), feature = "sdl2"))]
+fn run_paced<F: Frontend>(chip8: &mut CHIP8<F>, frames: Option<u64>) -> Result<(), Chip8Error> {
+    let frame = Duration::from_nanos(16_666_667);
+    let mut next = Instant::now();
+    for _ in 0..frames.unwrap_or(u64::MAX) {
+        if !chip8.frontend().is_open() || chip8.is_done() {
+            break;
+        }
+        chip8.step_frame()?;
+        next += frame;
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+    Ok(())
+}
+
+#[cfg(not(all(feature = "tui", unix)))]
+fn play_in_terminal(_args: &Args, _rom: Rom) {
+    eprintln!("--tui requires building with the `tui` feature on Unix");
+    process::exit(1);
+}
+
+#[cfg(feature = "sdl2")]
+fn play_with_sdl(args: &Args, rom: Rom) {
+    for flag in minifb_flags(args) {
+        eprintln!("{flag} needs the minifb window, ignoring it with --backend sdl2");
+    }
+    let mut window = match SdlWindow::new(args.scale, args.fullscreen, !args.no_vsync) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Could not open an SDL2 window: {e}");
+            process::exit(1);
+        }
+    };
+    window.set_tone(tone(args));
+    let mut chip8 = CHIP8::with_frontend(Registers::new(), window);
+    let overrides = overrides(args);
+    let config = match configure(&mut chip8, args, &rom, &overrides) {
+        Some(config) => config,
+        None => return,
+    };
+    let keymap = overrides.keymap.or(config.keymap).unwrap_or_default();
+    chip8.frontend_mut().set_keymap(keymap);
+    apply_colors(&mut chip8, args);
+    let result = run_paced(&mut chip8, args.frames);
+    finish(chip8, args, result, Vec::new());
+}
+
+#[cfg(not(feature = "sdl2"))]
+fn play_with_sdl(_args: &Args, _rom: Rom) {
+    eprintln!("--backend sdl2 requires building with the `sdl2` feature");
+    process::exit(1);
+}
+
+/// The flags given that only a window, minifb's or SDL2's, acts on.
+#[cfg_attr(not(all(feature = "tui", unix)), allow(dead_code))]
+fn window_flags(args: &Args) -> Vec<&'static str> {
+    let mut flags: Vec<_> = [
+        ("--backend", args.backend != Backend::Minifb),
+        ("--fullscreen", args.fullscreen),
+        ("--no-vsync", args.no_vsync),
+        ("--waveform", args.waveform.is_some()),
+        ("--pitch", args.pitch.is_some()),
+        ("--volume", args.volume.is_some()),
+        ("--palette", args.palette.is_some()),
+        ("--fg", args.fg.is_some()),
+        ("--bg", args.bg.is_some()),
+        ("--keymap", args.keymap.is_some()),
+        ("--layout", args.layout.is_some()),
+        ("--scale", args.scale.is_some()),
+    ]
+    .iter()
+    .filter_map(|&(flag, given)| given.then_some(flag))
+    .collect();
+    flags.extend(minifb_flags(args));
+    flags
+}
+
+/// The flags given that only the minifb window acts on.
+#[cfg_attr(
+    not(any(all(feature = "tui", unix), feature = "sdl2")),
+    allow(dead_code)
+)]
+fn minifb_flags(args: &Args) -> Vec<&'static str> {
+    [
+        ("--sync-to-audio", args.sync_to_audio),
+        ("--debug", args.debug),
+        ("--shm", args.shm.is_some()),
+        ("--discord-client-id", args.discord_client_id.is_some()),
+        ("--headless", args.headless),
+        ("--fade", args.fade != 0),
+        ("--show-speed", args.show_speed),
+        ("--sound-indicator", args.sound_indicator.is_some()),
+        ("--pause-unfocused", args.pause_unfocused),
+        ("--input-latency", args.input_latency),
+        ("--frame-times", args.frame_times),
+        ("--rumble", args.rumble),
+    ]
+    .iter()
+    .filter_map(|&(flag, given)| given.then_some(flag))
+    .collect()
+}
 
-    match chip8.load(&filename) {
-        Ok(_) => chip8.run(),
-        Err(e) => eprintln!("Could not open file `{filename}`: {e}"),
+fn play(args: &Args, rom: Rom) {
+    if args.fullscreen {
+        eprintln!("--fullscreen needs --backend sdl2, ignoring it");
+    }
+    if args.no_vsync {
+        eprintln!("--no-vsync needs --backend sdl2, ignoring it");
+    }
+    let movie = args.record.is_some() || args.replay.is_some();
+    if movie && args.sync_to_audio {
+        eprintln!("Ignoring --sync-to-audio, as it would make the movie unrepeatable");
+    }
+    let mut chip8 = if args.headless {
+        CHIP8::headless()
+    } else {
+        new_chip8(args.sync_to_audio && !movie)
+    };
+    if let Some(scale) = args.scale {
+        chip8.set_scale(scale);
+    }
+    set_tone(&chip8, args);
+
+    if let Some(name) = &args.shm {
+        match chip8.export_framebuffer(name) {
+            Ok(path) => eprintln!("Exporting framebuffer to {}", path.display()),
+            Err(e) => eprintln!("Could not create shared framebuffer `{name}`: {e}"),
+        }
+    }
+
+    #[cfg(feature = "discord")]
+    let mut presence = args.discord_client_id.as_deref().and_then(|id| {
+        presence::Presence::connect(id)
+            .map_err(|e| eprintln!("Could not connect to Discord: {e}"))
+            .ok()
+    });
+    #[cfg(not(feature = "discord"))]
+    if args.discord_client_id.is_some() {
+        eprintln!("--discord-client-id requires building with the `discord` feature");
+    }
+
+    #[cfg(feature = "gamepad")]
+    let _rumble = args
+        .rumble
+        .then(|| {
+            rumble::Rumble::start(chip8.sound_timer())
+                .map_err(|e| eprintln!("Could not open controllers for rumble: {e}"))
+                .ok()
+        })
+        .flatten();
+    #[cfg(not(feature = "gamepad"))]
+    if args.rumble {
+        eprintln!("--rumble requires building with the `gamepad` feature");
+    }
+
+    let overrides = overrides(args);
+    let config = match configure(&mut chip8, args, &rom, &overrides) {
+        Some(config) => config,
+        None => return,
+    };
+    apply_window_config(&mut chip8, &rom, &overrides, &config);
+    #[cfg(feature = "discord")]
+    show_presence(&mut presence, &chip8, &rom);
+    chip8.set_rom_opener(move |chip8, filename| {
+        let rom = Rom::File(filename);
+        let opened = open_rom(chip8, &rom, &overrides)
+            .map(|config| apply_window_config(chip8, &rom, &overrides, &config))
+            .is_some();
+        #[cfg(feature = "discord")]
+        if opened {
+            show_presence(&mut presence, chip8, &rom);
+        }
+        opened
+    });
+    apply_colors(&mut chip8, args);
+    chip8.set_fade(args.fade);
+    if args.show_speed {
+        chip8.show_speed(true);
+    }
+    chip8.show_sound(args.sound_indicator);
+    chip8.set_pause_unfocused(args.pause_unfocused);
+    if args.debug {
+        if args.headless {
+            eprintln!("--debug needs a window, ignoring it with --headless");
+        } else {
+            chip8.set_debugging(true);
+        }
+    }
+    let result = match args.frames {
+        Some(frames) => chip8.run_frames(frames),
+        None => chip8.run(),
+    };
+    let mut timings = Vec::new();
+    if args.input_latency {
+        timings.push(chip8.input_latency().to_string());
+    }
+    if args.frame_times {
+        timings.push(chip8.frame_times().to_string());
+    }
+    finish(chip8, args, result, timings);
+}
+
+/// Set up `chip8` from the flags that apply whatever it draws on, and open
+/// `rom` with `overrides`, returning the ROM's settings. Reports why and
+/// returns `None` when the ROM can't be loaded.
+fn configure<F: Frontend>(
+    chip8: &mut CHIP8<F>,
+    args: &Args,
+    rom: &Rom,
+    overrides: &RomConfig,
+) -> Option<RomConfig> {
+    chip8.set_font_style(args.font_style);
+    chip8.set_ram_size(args.ram_size);
+    if args.debug_console {
+        chip8::console::install(chip8, io::stdout());
+    }
+    for &pattern in &args.ignore_opcode {
+        chip8.register_opcode(pattern, |_, _| {});
+    }
+
+    if let Some(path) = &args.frame_hashes {
+        match File::create(path) {
+            Ok(log) => chip8.log_frame_hashes(log),
+            Err(e) => eprintln!("Could not create frame hash log `{path}`: {e}"),
+        }
+    }
+
+    if let Some(port) = args.metrics_port {
+        if let Err(e) = metrics::serve(port, chip8.stats()) {
+            eprintln!("Could not serve metrics on port {port}: {e}");
+        }
+    }
+
+    let config = open_rom(chip8, rom, overrides)?;
+    chip8.set_pc_overflow(args.pc_overflow);
+    chip8.set_on_unknown(args.on_unknown);
+    chip8.set_on_halt(args.on_halt);
+    start_gamepad(chip8, args, config.gamepad);
+    start_movie(chip8, args);
+    start_gif(chip8, args);
+    start_trace(chip8, args);
+    start_script(chip8, args);
+    if args.profile {
+        chip8.start_profile();
+    }
+    Some(config)
+}
+
+/// Save the movie and GIF, then report how the run ended and what was asked
+/// for on exit, with `timings` after the screenshot. `chip8` is dropped
+/// before anything is printed, taking the terminal out of raw mode.
+fn finish<F: Frontend>(
+    mut chip8: CHIP8<F>,
+    args: &Args,
+    result: Result<(), Chip8Error>,
+    timings: Vec<String>,
+) {
+    save_movie(&chip8, args);
+    save_gif(&mut chip8, args);
+    if let Err(e) = result {
+        let trace = chip8.stack_trace();
+        // Finish writing the trace and GIF.
+        drop(chip8);
+        eprintln!("{e}\n{trace}");
+        process::exit(1);
+    }
+    let halted = chip8.has_halted().then(|| chip8.stack_trace());
+    let stuck = stuck_report(&chip8);
+    let screenshot = args
+        .screenshot
+        .as_ref()
+        .map(|path| (path, chip8.screenshot(Path::new(path))));
+    let stats = args.stats.then(|| {
+        format!(
+            "Instructions: {}\nFrames:       {}\nDraw calls:   {}\nCollisions:   {}\nKey waits:    {}",
+            chip8.instructions(),
+            chip8.frames(),
+            chip8.draw_calls(),
+            chip8.collisions(),
+            chip8.key_waits(),
+        )
+    });
+    let profile = chip8.profile().map(ToString::to_string);
+    drop(chip8);
+
+    if let Some(trace) = halted {
+        report_halt(&trace);
+    }
+    if let Some(report) = stuck {
+        report_stuck(report);
+    }
+    match screenshot {
+        Some((path, Ok(()))) => eprintln!("Saved screenshot to {path}"),
+        Some((path, Err(e))) => eprintln!("Could not save screenshot `{path}`: {e}"),
+        None => {}
+    }
+    for timing in timings {
+        println!("{timing}");
+    }
+    if let Some(stats) = stats {
+        println!("{stats}");
+    }
+    if let Some(profile) = profile {
+        println!("{profile}");
+    }
+}
+
+/// Tell the user why the program ended, with the `trace` of where.
+fn report_halt(trace: &str) {
+    eprintln!("The program ended: PC ran off the end of RAM\n{trace}");
+}
+
+/// Where the program jumps to itself, if that ended the run, and the screen
+/// it ended on when `--on-halt print` asks for it.
+fn stuck_report<F: Frontend>(chip8: &CHIP8<F>) -> Option<(usize, Option<String>)> {
+    if chip8.has_halted() || !chip8.is_done() {
+        return None;
+    }
+    let screen = (chip8.on_halt() == OnHalt::Print).then(|| chip8.screen_text());
+    Some((chip8.registers().PC, screen))
+}
+
+fn report_stuck((pc, screen): (usize, Option<String>)) {
+    eprintln!("The program ended: it jumps to itself at 0x{pc:03X}");
+    if let Some(screen) = screen {
+        println!("{screen}");
+    }
+}
+
+/// Load `rom` and apply its metadata, reporting why when it can't be loaded.
+/// Metadata found with the ROM goes over the settings the ROM database has
+/// for it.
+fn load_rom<F: Frontend>(chip8: &mut CHIP8<F>, rom: &Rom) -> bool {
+    let (found, name) = match read_rom(chip8, rom) {
+        Some(loaded) => loaded,
+        None => return false,
+    };
+    match (found, romdb::lookup(chip8.rom())) {
+        (Some(found), known) => {
+            // The database fills in whatever the ROM's own metadata leaves out.
+            if let Some(known) = known {
+                chip8.apply_metadata(&known);
+            }
+            apply_metadata(chip8, &found);
+        }
+        (None, Some(known)) => apply_metadata(chip8, &known),
+        (None, None) => chip8.apply_metadata(&Metadata {
+            title: Some(name),
+            ..Metadata::default()
+        }),
+    }
+    true
+}
+
+/// Load `rom`, returning the metadata found with it and a name to title the
+/// window with if there is none, or `None` after reporting why it can't be
+/// loaded.
+fn read_rom<F: Frontend>(chip8: &mut CHIP8<F>, rom: &Rom) -> Option<(Option<Metadata>, String)> {
+    let filename = match *rom {
+        Rom::File(filename) => filename,
+        Rom::Demo(_, bytes) => {
+            if let Err(e) = chip8.load_bytes(bytes) {
+                eprintln!("Could not load the demo `{}`: {e}", rom.name());
+                return None;
+            }
+            return Some((None, rom.name().to_string()));
+        }
+    };
+
+    if filename == "-" || download::is_url(filename) {
+        if let Err(e) = chip8.load(filename) {
+            match filename {
+                "-" => eprintln!("Could not read ROM from stdin: {e}"),
+                url => eprintln!("Could not download `{url}`: {e}"),
+            }
+            return None;
+        }
+        let name = match filename {
+            "-" => "stdin",
+            url => download::rom_name(url),
+        };
+        return Some((None, name.to_string()));
+    }
+
+    if archive::is_zip(filename) {
+        let extracted = match archive::extract(filename) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                eprintln!("Could not extract a ROM from `{filename}`: {e}");
+                return None;
+            }
+        };
+        if let Err(e) = chip8.load_bytes(&extracted.rom) {
+            eprintln!("Could not load the ROM in `{filename}`: {e}");
+            return None;
+        }
+        return Some((extracted.metadata, extracted.name));
+    }
+
+    if Cartridge::is_cartridge(Path::new(filename)) {
+        let cart = fs::read(filename).and_then(|gif| Cartridge::read(&gif));
+        let (cart, rom) = match cart.and_then(|cart| cart.assemble().map(|rom| (cart, rom))) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Could not load the cartridge `{filename}`: {e}");
+                return None;
+            }
+        };
+        if let Err(e) = chip8.load_bytes(&rom) {
+            eprintln!("Could not load the cartridge `{filename}`: {e}");
+            return None;
+        }
+        let name = Path::new(filename).file_stem().unwrap_or_default();
+        let name = name.to_string_lossy().into_owned();
+        return Some((Some(cart.metadata(&name)), name));
+    }
+
+    if let Err(e) = chip8.load(filename) {
+        eprintln!("Could not open file `{filename}`: {e}");
+        return None;
+    }
+
+    let meta = match Metadata::for_rom(Path::new(filename)) {
+        Some(Ok(meta)) => Some(meta),
+        Some(Err(e)) => {
+            eprintln!("Ignoring metadata for `{filename}`: {e}");
+            None
+        }
+        None => None,
+    };
+    let name = Path::new(filename).file_name().unwrap_or_default();
+    Some((meta, name.to_string_lossy().into_owned()))
+}
+
+/// The settings the command line gives for every ROM, which go over those
+/// in the ROM's own `.toml` file.
+fn overrides(args: &Args) -> RomConfig {
+    RomConfig {
+        cpu_hz: args.cpu_hz,
+        keymap: args.keymap.or_else(|| args.layout.map(Layout::keymap)),
+        gamepad: None,
+        stack_limit: args.stack_limit,
+        quirks: args.quirks,
+    }
+}
+
+/// Load `rom` as `load_rom` does and apply the settings in its `.toml` file,
+/// with `overrides` over them, returning those settings. The ROM's flags
+/// file is the one next to it.
+fn open_rom<F: Frontend>(
+    chip8: &mut CHIP8<F>,
+    rom: &Rom,
+    overrides: &RomConfig,
+) -> Option<RomConfig> {
+    if !load_rom(chip8, rom) {
+        return None;
+    }
+    chip8.stats().set_rom(rom.name());
+    let config = rom_config(rom);
+    if let Some(hz) = overrides.cpu_hz.or(config.cpu_hz) {
+        chip8.set_cpu_hz(hz);
+    }
+    chip8.set_stack_limit(
+        overrides
+            .stack_limit
+            .or(config.stack_limit)
+            .unwrap_or_default(),
+    );
+    if let Some(quirks) = overrides.quirks.or(config.quirks) {
+        chip8.set_quirks(quirks);
+    }
+    if let Some(path) = rom.path() {
+        chip8.set_flags_path(path.with_extension("flags"));
+    }
+    Some(config)
+}
+
+/// Apply the keymap from `overrides` or the ROM's `config`, and use the save
+/// state and keymap files next to `rom`, which only the window has keys for.
+fn apply_window_config(chip8: &mut CHIP8, rom: &Rom, overrides: &RomConfig, config: &RomConfig) {
+    chip8.set_keymap(overrides.keymap.or(config.keymap).unwrap_or_default());
+    if let Some(path) = rom.path() {
+        chip8.set_state_path(path.with_extension("state"));
+        chip8.set_keymap_path(romconfig::path_for(path));
+    }
+}
+
+/// Show the game loaded from `rom` on Discord, by its name in the ROM
+/// database, or by its file's when the database doesn't know it.
+#[cfg(feature = "discord")]
+fn show_presence(presence: &mut Option<presence::Presence>, chip8: &CHIP8, rom: &Rom) {
+    let presence = match presence {
+        Some(presence) => presence,
+        None => return,
+    };
+    let title = romdb::lookup(chip8.rom())
+        .and_then(|meta| meta.display_name())
+        .unwrap_or_else(|| match *rom {
+            Rom::File("-") => "stdin".to_string(),
+            Rom::File(url) if download::is_url(url) => download::rom_name(url).to_string(),
+            Rom::File(filename) => Path::new(filename)
+                .file_stem()
+                .map_or(filename.into(), |stem| stem.to_string_lossy())
+                .into_owned(),
+            Rom::Demo(name, _) => name.to_string(),
+        });
+    if let Err(e) = presence.play(&title) {
+        eprintln!("Could not update Discord: {e}");
+    }
+}
+
+/// The settings in the `.toml` file next to `rom`, if it is a ROM file that
+/// has one.
+fn rom_config(rom: &Rom) -> RomConfig {
+    let path = match rom.path() {
+        Some(path) => path,
+        None => return RomConfig::default(),
+    };
+    match RomConfig::for_rom(path) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            let path = romconfig::path_for(path);
+            eprintln!("Ignoring `{}`: {e}", path.display());
+            RomConfig::default()
+        }
+        None => RomConfig::default(),
+    }
+}
+
+/// Apply `--palette`, `--fg` and `--bg`, over any colors from the ROM's
+/// metadata.
+fn apply_colors<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    let (fg, bg) = match args.palette {
+        Some(palette) => (palette.fg, palette.bg),
+        None => chip8.colors(),
+    };
+    let fg = args.fg.map_or(fg, |Color(fg)| fg);
+    let bg = args.bg.map_or(bg, |Color(bg)| bg);
+    if (fg, bg) != chip8.colors() {
+        chip8.set_colors(fg, bg);
+    }
+}
+
+/// Read the keypad from controllers as well, through the mapping given on
+/// the command line, for the ROM, or the default one.
+#[cfg(feature = "gamepad")]
+fn start_gamepad<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args, map: Option<PadMap>) {
+    match Gamepad::start(args.gamepad.or(map).unwrap_or_default()) {
+        Ok(gamepad) => chip8.set_gamepad(gamepad),
+        Err(e) => eprintln!("Could not open controllers: {e}"),
+    }
+}
+
+#[cfg(not(feature = "gamepad"))]
+fn start_gamepad<F: Frontend>(_chip8: &mut CHIP8<F>, args: &Args, _map: Option<PadMap>) {
+    if args.gamepad.is_some() {
+        eprintln!("--gamepad requires building with the `gamepad` feature");
+    }
+}
+
+/// Seed the random numbers, then start recording or replaying a movie, as
+/// asked. A replayed movie brings its own seed.
+fn start_movie<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    if let Some(seed) = args.seed {
+        chip8.set_seed(seed);
+    }
+    match (&args.record, &args.replay) {
+        (Some(_), Some(_)) => {
+            eprintln!("--record and --replay cannot be used together");
+            process::exit(1);
+        }
+        (Some(_), None) => chip8.record(),
+        (None, Some(path)) => match Movie::read(Path::new(path)) {
+            Ok(movie) => chip8.replay(movie),
+            Err(e) => {
+                eprintln!("Could not read movie `{path}`: {e}");
+                process::exit(1);
+            }
+        },
+        (None, None) => {}
+    }
+}
+
+fn save_movie<F: Frontend>(chip8: &CHIP8<F>, args: &Args) {
+    if let (Some(path), Some(movie)) = (&args.record, chip8.recording()) {
+        match movie.write(Path::new(path)) {
+            Ok(()) => eprintln!("Saved {} frames of input to {path}", movie.frames),
+            Err(e) => eprintln!("Could not save movie `{path}`: {e}"),
+        }
+    }
+}
+
+fn start_gif<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    if let Some(path) = &args.gif {
+        if let Err(e) = chip8.start_gif(Path::new(path)) {
+            eprintln!("Could not create GIF `{path}`: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn start_trace<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    if let Some(path) = &args.trace {
+        match File::create(path) {
+            Ok(file) => chip8.set_trace(BufWriter::new(file)),
+            Err(e) => {
+                eprintln!("Could not create trace `{path}`: {e}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn start_script<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    if let Some(path) = &args.script {
+        match chip8::script::Script::load(Path::new(path)) {
+            Ok(script) => chip8.set_script(script),
+            Err(e) => {
+                eprintln!("Could not load script `{path}`: {e}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn start_script<F: Frontend>(_chip8: &mut CHIP8<F>, args: &Args) {
+    if args.script.is_some() {
+        eprintln!("--script requires building with the `scripting` feature");
+        process::exit(1);
+    }
+}
+
+/// Finish the GIF from --gif, unless F10 already did.
+fn save_gif<F: Frontend>(chip8: &mut CHIP8<F>, args: &Args) {
+    if let (Some(path), true) = (&args.gif, chip8.is_recording_gif()) {
+        match chip8.stop_gif() {
+            Ok(()) => eprintln!("Saved GIF to {path}"),
+            Err(e) => eprintln!("Could not save GIF `{path}`: {e}"),
+        }
+    }
+}
+
+fn apply_metadata<F: Frontend>(chip8: &mut CHIP8<F>, meta: &Metadata) {
+    if let Some(name) = meta.display_name() {
+        println!("{name}");
+    }
+    if let Some(desc) = &meta.desc {
+        println!("{desc}");
     }
+    chip8.apply_metadata(meta);
 }
@@ -0,0 +1,144 @@
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chip8::stats::Stats;
+
+/// How long a scraper gets to send its request and take the response before
+/// it is dropped, so a stalled one can't hold up the next.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve Prometheus metrics about the running emulator on
+/// `127.0.0.1:port/metrics`, from a background thread. The ROM is labelled
+/// with whatever `stats` was last told is running.
+pub fn serve(port: u16, stats: Arc<Stats>) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let started = Instant::now();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let body = render(&stats, started, Instant::now());
+            let _ = respond(stream, &body);
+        }
+    });
+
+    Ok(())
+}
+
+/// The metrics in the Prometheus text format. Rates are left to the
+/// counters and `rate()`.
+fn render(stats: &Stats, started: Instant, now: Instant) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        let _ = writeln!(out, "{name}{value}");
+    };
+
+    metric(
+        "chip8_rom_info",
+        "gauge",
+        "The ROM being run.",
+        format!(
+            "{{rom=\"{}\"}} 1",
+            stats.rom().replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+    );
+    metric(
+        "chip8_uptime_seconds",
+        "gauge",
+        "Seconds since the emulator started.",
+        format!(" {}", now.duration_since(started).as_secs_f64()),
+    );
+    metric(
+        "chip8_instructions_total",
+        "counter",
+        "Instructions executed.",
+        format!(" {}", stats.instructions()),
+    );
+    metric(
+        "chip8_frames_total",
+        "counter",
+        "Frames presented.",
+        format!(" {}", stats.frames()),
+    );
+    metric(
+        "chip8_dropped_frames_total",
+        "counter",
+        "Frames skipped for falling too far behind to catch up.",
+        format!(" {}", stats.dropped_frames()),
+    );
+    metric(
+        "chip8_draw_calls_total",
+        "counter",
+        "Sprites drawn.",
+        format!(" {}", stats.draw_calls()),
+    );
+    metric(
+        "chip8_collisions_total",
+        "counter",
+        "Sprites drawn that erased a pixel.",
+        format!(" {}", stats.collisions()),
+    );
+    metric(
+        "chip8_key_waits_total",
+        "counter",
+        "Waits for a key press that completed.",
+        format!(" {}", stats.key_waits()),
+    );
+    out
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    // The request itself does not matter, every path gets the metrics.
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let stats = Stats::default();
+        for _ in 0..500 {
+            stats.count_instruction();
+        }
+        stats.count_frame();
+        stats.count_draw(true);
+        stats.count_draw(false);
+        stats.count_dropped_frames(3);
+        stats.set_rom("pong \"v2\"");
+
+        let started = Instant::now();
+        let now = started + Duration::from_secs(2);
+        let body = render(&stats, started, now);
+
+        assert!(body.contains("chip8_rom_info{rom=\"pong \\\"v2\\\"\"} 1\n"));
+        assert!(body.contains("chip8_uptime_seconds 2\n"));
+        assert!(body.contains("chip8_instructions_total 500\n"));
+        assert!(!body.contains("per_second"));
+        assert!(body.contains("# TYPE chip8_frames_total counter\n"));
+        assert!(body.contains("chip8_dropped_frames_total 3\n"));
+        assert!(body.contains("chip8_draw_calls_total 2\n"));
+        assert!(body.contains("chip8_collisions_total 1\n"));
+    }
+}
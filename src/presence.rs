@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use discord_rich_presence::activity::{Activity, Timestamps};
+use discord_rich_presence::{DiscordIpc, DiscordIpcClient};
+
+/// Discord Rich Presence showing the running game and how long it has been
+/// played. The activity is cleared when this is dropped.
+pub struct Presence {
+    client: DiscordIpcClient,
+}
+
+impl Presence {
+    /// Connect to Discord as the application `client_id`, showing nothing
+    /// until `play` is called.
+    pub fn connect(client_id: &str) -> Result<Self, Box<dyn Error>> {
+        let mut client = DiscordIpcClient::new(client_id);
+        client.connect()?;
+        Ok(Presence { client })
+    }
+
+    /// Show `title` as the game being played, timed from now.
+    pub fn play(&mut self, title: &str) -> Result<(), Box<dyn Error>> {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        self.client.set_activity(
+            Activity::new()
+                .details(title)
+                .state("Playing on CHIP-8")
+                .timestamps(Timestamps::new().start(started)),
+        )?;
+        Ok(())
+    }
+}
+
+impl Drop for Presence {
+    fn drop(&mut self) {
+        let _ = self.client.clear_activity();
+        let _ = self.client.close();
+    }
+}
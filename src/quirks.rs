@@ -0,0 +1,224 @@
+use std::io;
+
+use argh::FromArgs;
+
+use crate::chip8::quirks::Quirks;
+use crate::chip8::CHIP8;
+
+/// Every probe halts well within this many instructions.
+const PROBE_STEPS: usize = 32;
+
+/// Where Timendus' test suite looks for the platform to test, instead of
+/// asking with a menu.
+const PLATFORM_ADDR: usize = 0x1FF;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "quirks-test")]
+/// Run quirk-detection ROMs without a window and report which quirks this
+/// build exhibits
+pub struct QuirksTestArgs {
+    #[argh(option, default = "Quirks::default()")]
+    /// the quirks to turn on first, as for playing
+    quirks: Quirks,
+
+    #[argh(option)]
+    /// the path to Timendus' `5-quirks.ch8`, to run and show the results of
+    /// as well, display wait included
+    rom: Option<String>,
+
+    #[argh(option, default = "1")]
+    /// the platform it checks the quirks against: 1 for CHIP-8 (default), 2
+    /// for SUPER-CHIP, 3 for XO-CHIP, as its menu numbers them
+    platform: u8,
+
+    #[argh(option, default = "600")]
+    /// how many frames to run it for, enough for its display wait test
+    frames: u64,
+}
+
+/// A tiny ROM that lights the top-left pixel when the interpreter behaves as
+/// `lit` describes, and leaves the screen blank when it behaves as `unlit`.
+pub struct Probe {
+    pub name: &'static str,
+    pub lit: &'static str,
+    pub unlit: &'static str,
+    program: &'static [u16],
+}
+
+/// The quirks from Timendus' CHIP-8 test suite that can be observed with a
+/// single pixel. Display wait is left out, as drawing here never waits for
+/// the vertical blank.
+pub const PROBES: &[Probe] = &[
+    Probe {
+        name: "vF reset",
+        lit: "AND, OR and XOR reset VF to 0",
+        unlit: "AND, OR and XOR leave VF alone",
+        program: &[
+            0x6F05, // 0x200 LD VF, 0x05
+            0x8011, // 0x202 OR V0, V1
+            0x3F00, // 0x204 SE VF, 0x00
+            0x120C, // 0x206 JP 0x20C
+            0xA20E, // 0x208 LD I, 0x20E
+            0xDEE1, // 0x20A DRW VE, VE, 1
+            0x120C, // 0x20C JP 0x20C
+            0x8000, // 0x20E sprite
+        ],
+    },
+    Probe {
+        name: "memory",
+        lit: "FX55 and FX65 advance I past the registers",
+        unlit: "FX55 and FX65 leave I unchanged",
+        program: &[
+            0x60AA, // 0x200 LD V0, 0xAA
+            0xA300, // 0x202 LD I, 0x300
+            0xF055, // 0x204 LD [I], V0
+            0xF065, // 0x206 LD V0, [I]
+            0x3000, // 0x208 SE V0, 0x00
+            0x1210, // 0x20A JP 0x210
+            0xA212, // 0x20C LD I, 0x212
+            0xDEE1, // 0x20E DRW VE, VE, 1
+            0x1210, // 0x210 JP 0x210
+            0x8000, // 0x212 sprite
+        ],
+    },
+    Probe {
+        name: "shifting",
+        lit: "8XY6 and 8XYE shift VX in place, ignoring VY",
+        unlit: "8XY6 and 8XYE shift VY into VX",
+        program: &[
+            0x6004, // 0x200 LD V0, 0x04
+            0x6110, // 0x202 LD V1, 0x10
+            0x8016, // 0x204 SHR V0, V1
+            0x3002, // 0x206 SE V0, 0x02
+            0x120E, // 0x208 JP 0x20E
+            0xA210, // 0x20A LD I, 0x210
+            0xDEE1, // 0x20C DRW VE, VE, 1
+            0x120E, // 0x20E JP 0x20E
+            0x8000, // 0x210 sprite
+        ],
+    },
+    Probe {
+        name: "jumping",
+        lit: "BXNN jumps to XNN + VX",
+        unlit: "BNNN jumps to NNN + V0",
+        program: &[
+            0x6000, // 0x200 LD V0, 0x00
+            0x6202, // 0x202 LD V2, 0x02
+            0xB206, // 0x204 JP V0, 0x206 (or JP V2, 0x206)
+            0x120C, // 0x206 JP 0x20C
+            0xA20E, // 0x208 LD I, 0x20E
+            0xDEE1, // 0x20A DRW VE, VE, 1
+            0x120C, // 0x20C JP 0x20C
+            0x8000, // 0x20E sprite
+        ],
+    },
+    Probe {
+        name: "clipping",
+        lit: "sprites wrap around the screen edges",
+        unlit: "sprites are clipped at the screen edges",
+        program: &[
+            0x6E3F, // 0x200 LD VE, 0x3F
+            0xA208, // 0x202 LD I, 0x208
+            0xDED1, // 0x204 DRW VE, VD, 1
+            0x1206, // 0x206 JP 0x206
+            0xC000, // 0x208 sprite, its second pixel is past the right edge
+        ],
+    },
+];
+
+impl QuirksTestArgs {
+    pub fn run(&self) -> io::Result<()> {
+        for probe in PROBES {
+            let behaviour = if probe.detect(self.quirks) {
+                probe.lit
+            } else {
+                probe.unlit
+            };
+            println!("{:<10}{}", probe.name, behaviour);
+        }
+
+        if let Some(rom) = &self.rom {
+            let mut chip8 = CHIP8::headless();
+            chip8.set_quirks(self.quirks);
+            chip8.load(rom)?;
+            chip8.poke(PLATFORM_ADDR, &[self.platform]);
+            chip8
+                .run_frames(self.frames)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            println!("\n{} ends on:\n", rom);
+            println!("{}", result_lines(&chip8.screen_text()));
+        }
+        Ok(())
+    }
+}
+
+/// The rows of a screen from `screen_text` that have something on them,
+/// lit pixels as blocks, so the result lines of a test ROM read as text.
+fn result_lines(screen: &str) -> String {
+    let rows: Vec<String> = (screen.lines())
+        .filter(|row| row.contains('#'))
+        .map(|row| {
+            row.replace('#', "█")
+                .replace('.', " ")
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    rows.join("\n")
+}
+
+impl Probe {
+    /// Run the probe with `quirks` and read its result off the screen.
+    pub fn detect(&self, quirks: Quirks) -> bool {
+        let rom: Vec<u8> = self
+            .program
+            .iter()
+            .flat_map(|op| op.to_be_bytes())
+            .collect();
+        let mut chip8 = CHIP8::headless();
+        chip8.set_quirks(quirks);
+        chip8.load_bytes(&rom).expect("the probes fit in RAM");
+        for _ in 0..PROBE_STEPS {
+            if chip8.step().is_err() {
+                break;
+            }
+        }
+        chip8.is_pixel_lit(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(name: &str) -> bool {
+        detect_with(name, "none")
+    }
+
+    fn detect_with(name: &str, quirks: &str) -> bool {
+        let probe = PROBES.iter().find(|p| p.name == name).unwrap();
+        probe.detect(quirks.parse().unwrap())
+    }
+
+    #[test]
+    fn test_current_quirks() {
+        assert!(!detect("vF reset"));
+        assert!(!detect("memory"));
+        assert!(detect("shifting"));
+        assert!(!detect("jumping"));
+        assert!(!detect("clipping"));
+    }
+
+    #[test]
+    fn test_result_lines() {
+        let screen = "....\n.##.\n....\n#..#";
+        assert_eq!(result_lines(screen), " ██\n█  █");
+    }
+
+    #[test]
+    fn test_quirks_turned_on() {
+        assert!(detect_with("clipping", "wrap"));
+        assert!(!detect_with("shifting", "shift-vy"));
+        assert!(detect_with("memory", "increment-i"));
+    }
+}
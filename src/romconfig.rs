@@ -0,0 +1,125 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::chip8::gamepad::PadMap;
+use crate::chip8::keymap::Keymap;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::StackLimit;
+
+/// Settings for one ROM, read from a TOML file next to it named after the
+/// whole file, e.g. `game.ch8.toml`. Each one set goes over the default and
+/// the ROM database, and gives way to the command line.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RomConfig {
+    pub cpu_hz: Option<u32>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub keymap: Option<Keymap>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub gamepad: Option<PadMap>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub stack_limit: Option<StackLimit>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub quirks: Option<Quirks>,
+}
+
+impl RomConfig {
+    pub fn from_toml(toml: &str) -> io::Result<Self> {
+        let config: RomConfig =
+            toml::from_str(toml).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if config.cpu_hz == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cpu-hz must be at least 1",
+            ));
+        }
+        Ok(config)
+    }
+
+    /// The settings stored next to `rom`, if there are any.
+    pub fn for_rom(rom: &Path) -> Option<io::Result<Self>> {
+        let path = path_for(rom);
+        if !path.is_file() {
+            return None;
+        }
+        Some(fs::read_to_string(&path).and_then(|toml| RomConfig::from_toml(&toml)))
+    }
+}
+
+/// Where the settings for `rom` are kept.
+pub fn path_for(rom: &Path) -> PathBuf {
+    let mut path = rom.as_os_str().to_owned();
+    path.push(".toml");
+    PathBuf::from(path)
+}
+
+/// A string or number, as written for an option on the command line.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Arg {
+    Number(u64),
+    Text(String),
+}
+
+/// An option parsed the way the command line parses it.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = match Arg::deserialize(deserializer)? {
+        Arg::Number(n) => n.to_string(),
+        Arg::Text(s) => s,
+    };
+    s.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = RomConfig::from_toml(
+            r#"
+            cpu-hz = 1000
+            keymap = "1234qwerasdfzxcv"
+            gamepad = "up=1,down=4"
+            stack-limit = "unlimited"
+            quirks = "wrap"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            RomConfig {
+                cpu_hz: Some(1000),
+                keymap: Some(Keymap::default()),
+                gamepad: "up=1,down=4".parse().ok(),
+                stack_limit: Some(StackLimit::Unlimited),
+                quirks: "wrap".parse().ok(),
+            }
+        );
+
+        assert_eq!(
+            RomConfig::from_toml("stack-limit = 12")
+                .unwrap()
+                .stack_limit,
+            Some(StackLimit::Depth(12))
+        );
+        assert_eq!(RomConfig::from_toml("").unwrap(), RomConfig::default());
+        assert!(RomConfig::from_toml("cpu-hz = 0").is_err());
+        assert!(RomConfig::from_toml("keymap = \"qwerty\"").is_err());
+        assert!(RomConfig::from_toml("speed = 1000").is_err());
+        assert_eq!(
+            path_for(Path::new("roms/pong.ch8")),
+            Path::new("roms/pong.ch8.toml")
+        );
+    }
+}
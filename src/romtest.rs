@@ -0,0 +1,137 @@
+use std::io;
+
+use argh::FromArgs;
+
+use crate::chip8::{Chip8Error, CHIP8};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "test")]
+/// Run a test ROM without a window and report the screen it ends on
+pub struct TestArgs {
+    #[argh(positional)]
+    /// the ROM to run, e.g. one of Timendus' CHIP-8 test suite
+    pub filename: String,
+
+    #[argh(option, default = "300")]
+    /// how many frames to run it for
+    frames: u64,
+
+    #[argh(option)]
+    /// instructions per second (default 600, or the speed the ROM database
+    /// knows the ROM to need)
+    cpu_hz: Option<u32>,
+
+    #[argh(option, default = "0")]
+    /// the seed of the random numbers RND draws (default 0)
+    seed: u64,
+
+    #[argh(option, from_str_fn(parse_hash))]
+    /// the screen hash a passing run ends on, as printed by an earlier run;
+    /// the test fails if the final screen differs
+    expect: Option<u64>,
+}
+
+fn parse_hash(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("expected a hash like `d80ac658736bb725`, got `{}`", s))
+}
+
+/// How a test run ended.
+#[derive(Debug, PartialEq)]
+struct Report {
+    /// Frames run before the end or the error.
+    frames: u64,
+    /// The last frame that changed the screen, when the result was drawn.
+    settled: u64,
+    hash: u64,
+    error: Option<Chip8Error>,
+}
+
+impl TestArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let mut chip8 = CHIP8::headless();
+        chip8.load(&self.filename)?;
+        if let Some(hz) = self.cpu_hz {
+            chip8.set_cpu_hz(hz);
+        }
+        chip8.set_seed(self.seed);
+        let report = run_test(&mut chip8, self.frames);
+
+        println!("Frames:  {}", report.frames);
+        println!("Settled: frame {}", report.settled);
+        println!("Hash:    {:016x}", report.hash);
+        if let Some(e) = &report.error {
+            println!("Error:   {}", e);
+            return Err(io::Error::other(e.to_string()));
+        }
+        match self.expect {
+            Some(expected) if expected != report.hash => {
+                println!("Result:  fail");
+                Err(io::Error::other(format!(
+                    "the screen hash {:016x} is not the expected {:016x}",
+                    report.hash, expected
+                )))
+            }
+            Some(_) => {
+                println!("Result:  pass");
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run `chip8` for `frames` frames, stopping early if it fails or PC leaves
+/// RAM.
+fn run_test(chip8: &mut CHIP8, frames: u64) -> Report {
+    let mut hash = chip8.screen_hash();
+    let mut settled = 0;
+    let mut error = None;
+    for frame in 1..=frames {
+        if chip8.has_halted() {
+            break;
+        }
+        if let Err(e) = chip8.step_frame() {
+            error = Some(e);
+            break;
+        }
+        if chip8.screen_hash() != hash {
+            hash = chip8.screen_hash();
+            settled = frame;
+        }
+    }
+    Report {
+        frames: chip8.frames(),
+        settled,
+        hash,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_test() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0xF0, 0x29, // LD F, V0
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x12, 0x04, // JP 0x204
+            ])
+            .unwrap();
+        let report = run_test(&mut chip8, 30);
+        assert_eq!(report.frames, 30);
+        assert_eq!(report.settled, 1);
+        assert_ne!(report.hash, CHIP8::headless().screen_hash());
+        assert_eq!(report.error, None);
+
+        let mut chip8 = CHIP8::headless();
+        chip8.load_bytes(&[0x00, 0xEE]).unwrap(); // RET
+        let report = run_test(&mut chip8, 30);
+        assert_eq!(report.frames, 0);
+        assert_eq!(report.error, Some(Chip8Error::StackUnderflow { at: 0x200 }));
+    }
+}
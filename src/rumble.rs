@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Repeat, Replay, Ticks};
+use gilrs::{GamepadId, Gilrs};
+
+/// How often the sound timer and the connected controllers are checked.
+const POLL: Duration = Duration::from_millis(16);
+/// Strength of the rumble, out of `u16::MAX`.
+const MAGNITUDE: u16 = 0x6000;
+
+/// Rumbles every connected controller that supports force feedback while
+/// the sound timer is above zero. Rumbling stops when this is dropped.
+pub struct Rumble {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Rumble {
+    pub fn start(st: Arc<AtomicU8>) -> Result<Self, String> {
+        let running = Arc::new(AtomicBool::new(true));
+        let (ready, started) = mpsc::channel();
+        let thread = {
+            let running = running.clone();
+            thread::spawn(move || {
+                // Gilrs can't move between threads, so it lives on this one.
+                let mut gilrs = match Gilrs::new() {
+                    Ok(gilrs) => gilrs,
+                    Err(e) => return ready.send(Err(e.to_string())).unwrap_or(()),
+                };
+                let _ = ready.send(Ok(()));
+                poll(&mut gilrs, &st, &running);
+            })
+        };
+        started.recv().map_err(|e| e.to_string())?.map(|()| Rumble {
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Rumble {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn poll(gilrs: &mut Gilrs, st: &AtomicU8, running: &AtomicBool) {
+    let mut effect: Option<Effect> = None;
+    while running.load(Ordering::Relaxed) {
+        // Handling events keeps the list of connected controllers current.
+        while gilrs.next_event().is_some() {}
+
+        let buzzing = st.load(Ordering::Relaxed) > 0;
+        if buzzing && effect.is_none() {
+            effect = rumble(gilrs)
+                .map_err(|e| eprintln!("Could not rumble controllers: {e}"))
+                .ok()
+                .flatten();
+        } else if !buzzing {
+            if let Some(effect) = effect.take() {
+                let _ = effect.stop();
+            }
+        }
+        thread::sleep(POLL);
+    }
+}
+
+/// Start a rumble on every controller that can, until it is stopped.
+fn rumble(gilrs: &mut Gilrs) -> Result<Option<Effect>, gilrs::ff::Error> {
+    let ids: Vec<GamepadId> = gilrs
+        .gamepads()
+        .filter(|(_, gamepad)| gamepad.is_ff_supported())
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(None);
+    }
+    let effect = EffectBuilder::new()
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Strong {
+                magnitude: MAGNITUDE,
+            },
+            scheduling: Replay {
+                play_for: Ticks::from_ms(POLL.as_millis() as u32 * 4),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .gamepads(&ids)
+        .repeat(Repeat::Infinitely)
+        .finish(gilrs)?;
+    effect.play()?;
+    Ok(Some(effect))
+}
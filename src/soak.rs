@@ -0,0 +1,131 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use argh::FromArgs;
+
+use crate::archive;
+use crate::chip8::CHIP8;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "soak")]
+/// Run every ROM in a directory without a window and report which ones crash
+pub struct SoakArgs {
+    #[argh(positional)]
+    /// the directory holding the ROMs
+    pub dir: String,
+
+    #[argh(option, default = "600")]
+    /// how many frames to run each ROM for
+    frames: u64,
+}
+
+/// How a ROM fared.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// Ran for every frame.
+    Ran,
+    /// PC left RAM after this many frames.
+    Halted(u64),
+    /// An instruction failed, as bad ROMs do.
+    Failed(String),
+    /// Execution aborted with this message.
+    Panicked(String),
+    /// The file could not be read.
+    Unreadable(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Ran => write!(f, "ok"),
+            Outcome::Halted(frames) => write!(f, "halted after {} frames", frames),
+            Outcome::Failed(e) => write!(f, "failed: {}", e),
+            Outcome::Panicked(message) => write!(f, "panicked: {}", message),
+            Outcome::Unreadable(e) => write!(f, "unreadable: {}", e),
+        }
+    }
+}
+
+impl SoakArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let mut roms: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                let name = path.to_string_lossy();
+                path.is_file() && archive::is_rom_name(&name) && !archive::is_zip(&name)
+            })
+            .collect();
+        roms.sort();
+
+        let mut failed = 0;
+        for path in &roms {
+            let outcome = match fs::read(path) {
+                Ok(rom) => soak(&rom, self.frames),
+                Err(e) => Outcome::Unreadable(e.to_string()),
+            };
+            if matches!(
+                outcome,
+                Outcome::Failed(_) | Outcome::Panicked(_) | Outcome::Unreadable(_)
+            ) {
+                failed += 1;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            println!("{:<32}{}", name, outcome);
+        }
+
+        println!(
+            "{} ROMs, {} ran, {} failed",
+            roms.len(),
+            roms.len() - failed,
+            failed
+        );
+        if failed > 0 {
+            return Err(io::Error::other(format!(
+                "{} of {} ROMs failed",
+                failed,
+                roms.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Run `rom` headless for `frames` frames.
+fn soak(rom: &[u8], frames: u64) -> Outcome {
+    let mut chip8 = CHIP8::headless();
+    if let Err(e) = chip8.load_bytes(rom) {
+        return Outcome::Unreadable(e.to_string());
+    }
+    match panic::catch_unwind(AssertUnwindSafe(|| chip8.run_frames(frames))) {
+        Ok(Ok(())) if chip8.frames() < frames => Outcome::Halted(chip8.frames()),
+        Ok(Ok(())) => Outcome::Ran,
+        Ok(Err(e)) => Outcome::Failed(e.to_string()),
+        Err(panic) => Outcome::Panicked(
+            panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown error".to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soak() {
+        // JP 0x200
+        assert_eq!(soak(&[0x12, 0x00], 60), Outcome::Ran);
+        // JP 0xFFF, past the end of RAM
+        assert_eq!(soak(&[0x1F, 0xFF], 60), Outcome::Halted(0));
+        assert_eq!(
+            soak(&[0xE0, 0x00], 60),
+            Outcome::Failed("unknown opcode E000 at 0x200".to_string())
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+
+use argh::FromArgs;
+
+use crate::chip8::opcodes;
+use crate::chip8::tracelog::{self, Snapshot};
+use crate::chip8::{CHIP8, STEPS_PER_FRAME};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "trace")]
+/// Run a ROM without a window, printing what every instruction changed
+pub struct TraceArgs {
+    #[argh(positional)]
+    /// the ROM to trace
+    pub filename: String,
+
+    #[argh(option, default = "1000")]
+    /// how many instructions to run
+    steps: usize,
+
+    #[argh(option)]
+    /// compare against a reference trace in the same format instead of
+    /// printing, and report where the two first differ
+    diff: Option<String>,
+}
+
+impl TraceArgs {
+    pub fn run(&self) -> io::Result<()> {
+        let mut chip8 = CHIP8::headless();
+        chip8.load(&self.filename)?;
+
+        let reference = match &self.diff {
+            Some(path) => fs::read_to_string(path)?,
+            None => {
+                let stdout = io::stdout();
+                return trace(&mut chip8, self.steps, &mut stdout.lock());
+            }
+        };
+
+        let mut actual = Vec::new();
+        trace(&mut chip8, self.steps, &mut actual)?;
+        match diff(&reference, &String::from_utf8_lossy(&actual)) {
+            Some(divergence) => {
+                println!("{}", divergence);
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "diverges from the reference trace at instruction {}",
+                        divergence.step
+                    ),
+                ))
+            }
+            None => {
+                println!("Matches the reference trace");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run up to `steps` instructions, writing one line per instruction. Stops
+/// early when PC runs off the end of RAM, hits an unknown opcode or an
+/// instruction fails.
+pub fn trace(chip8: &mut CHIP8, steps: usize, out: &mut impl Write) -> io::Result<()> {
+    let mut before = Snapshot::of(chip8.registers(), chip8.memory());
+    for step in 0..steps {
+        let pc = chip8.registers().PC;
+        let opcode = match chip8.current_opcode() {
+            Some(opcode) => opcode,
+            None => {
+                writeln!(out, "0x{:03X}  PC ran off the end of RAM", pc)?;
+                break;
+            }
+        };
+        let instr = match opcodes::decode(opcode) {
+            Ok(instr) => instr,
+            Err(e) => {
+                writeln!(out, "0x{:03X}  {:04X}  {}", pc, opcode, e)?;
+                break;
+            }
+        };
+
+        if let Err(e) = chip8.step() {
+            writeln!(out, "0x{:03X}  {:04X}  {}", pc, opcode, e)?;
+            break;
+        }
+        if (step + 1) % STEPS_PER_FRAME == 0 {
+            chip8.tick_timers();
+        }
+
+        let after = Snapshot::of(chip8.registers(), chip8.memory());
+        writeln!(
+            out,
+            "{}",
+            tracelog::line(pc, opcode, &instr, &after, &before)
+        )?;
+        before = after;
+    }
+    Ok(())
+}
+
+/// Where a trace first differs from a reference trace.
+pub struct Divergence {
+    /// Index of the first differing instruction.
+    pub step: usize,
+    pub expected: String,
+    pub actual: String,
+    /// `PC`, `opcode` or the registers whose values differ.
+    pub fields: Vec<String>,
+}
+
+impl Divergence {
+    /// The frame the divergent instruction ran in, counting one frame per
+    /// timer tick.
+    pub fn frame(&self) -> usize {
+        self.step / STEPS_PER_FRAME
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "First divergence at instruction {} (frame {}) in {}",
+            self.step,
+            self.frame(),
+            self.fields.join(", ")
+        )?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        write!(f, "  actual:   {}", self.actual)
+    }
+}
+
+/// Compare two traces line by line. Steps past the end of `reference` are not
+/// compared, so a short reference checks a prefix of a longer run.
+pub fn diff(reference: &str, actual: &str) -> Option<Divergence> {
+    let mut actual_lines = actual.lines();
+    for (step, expected) in reference.lines().enumerate() {
+        let actual = actual_lines.next().unwrap_or("<end of trace>");
+        if expected != actual {
+            return Some(Divergence {
+                step,
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+                fields: differing_fields(expected, actual),
+            });
+        }
+    }
+    None
+}
+
+/// The named fields of a trace line: PC, opcode and the `X=..` deltas.
+fn fields(line: &str) -> Vec<(String, String)> {
+    let mut tokens = line.split_whitespace();
+    let mut fields = Vec::new();
+    for name in ["PC", "opcode"] {
+        if let Some(token) = tokens.next() {
+            fields.push((name.to_string(), token.to_string()));
+        }
+    }
+    for token in tokens {
+        if let Some((name, value)) = token.split_once('=') {
+            fields.push((name.to_string(), value.to_string()));
+        }
+    }
+    fields
+}
+
+fn differing_fields(expected: &str, actual: &str) -> Vec<String> {
+    let expected = fields(expected);
+    let actual = fields(actual);
+    let mut differing: Vec<String> = Vec::new();
+    for (name, _) in expected.iter().chain(&actual) {
+        let value = |fields: &[(String, String)]| fields.iter().find(|(n, _)| n == name).cloned();
+        if value(&expected) != value(&actual) && !differing.contains(name) {
+            differing.push(name.clone());
+        }
+    }
+    if differing.is_empty() {
+        differing.push("instruction text".to_string());
+    }
+    differing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_prints_deltas() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x01, // LD V0, 0x01
+                0x61, 0x01, // LD V1, 0x01
+                0xA2, 0x10, // LD I, 0x210
+                0x12, 0x06, // JP 0x206
+                0xFF, 0xFF, // unknown
+            ])
+            .unwrap();
+
+        let mut out = Vec::new();
+        trace(&mut chip8, 5, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("0x200  6001  LD V0, 0x01"));
+        assert!(lines[0].ends_with(" V0=01"));
+        assert!(lines[1].ends_with(" V1=01"));
+        assert!(!lines[1].contains("V0="));
+        assert!(lines[2].ends_with(" I=0x210"));
+        assert_eq!(lines[3], "0x206  1206  JP 0x206");
+        assert_eq!(lines[4], "0x206  1206  JP 0x206");
+    }
+
+    #[test]
+    fn test_trace_prints_memory_writes() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(&[
+                0x60, 0x7B, // LD V0, 123
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x33, // LD B, V0
+            ])
+            .unwrap();
+
+        let mut out = Vec::new();
+        trace(&mut chip8, 3, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out
+            .lines()
+            .nth(2)
+            .unwrap()
+            .ends_with("LD B, V0         [0x300]=010203"));
+    }
+
+    #[test]
+    fn test_diff_reports_first_divergence() {
+        let reference = "0x200  6001  LD V0, 0x01    V0=01\n0x202  7001  ADD V0, 0x01   V0=02\n";
+        assert!(diff(reference, reference).is_none());
+
+        let actual = "0x200  6001  LD V0, 0x01    V0=01\n0x202  7001  ADD V0, 0x01   V0=03 VF=01\n";
+        let divergence = diff(reference, actual).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.frame(), 0);
+        assert_eq!(divergence.fields, ["V0", "VF"]);
+
+        let divergence = diff(reference, "0x200  6001  LD V0, 0x01    V0=01\n").unwrap();
+        assert_eq!(divergence.actual, "<end of trace>");
+    }
+
+    /// Regression check against the committed trace of the opcode test ROM.
+    #[test]
+    fn test_matches_reference_trace() {
+        let mut chip8 = CHIP8::headless();
+        chip8
+            .load_bytes(include_bytes!("../roms/test_opcode.ch8"))
+            .unwrap();
+        let mut actual = Vec::new();
+        trace(&mut chip8, 250, &mut actual).unwrap();
+
+        let reference = include_str!("../tests/traces/test_opcode.txt");
+        if let Some(divergence) = diff(reference, &String::from_utf8(actual).unwrap()) {
+            panic!("{}", divergence);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! The interpreter as a WebAssembly module for `web/chip8.js`, which draws
+//! the screen to a canvas and feeds it keyboard events. Build it with
+//! `cargo rustc --lib --release --no-default-features --target
+//! wasm32-unknown-unknown --crate-type cdylib`.
+
+use std::cell::RefCell;
+
+use crate::chip8::canvas::CanvasFrontend;
+use crate::chip8::registers::Registers;
+use crate::chip8::CHIP8;
+
+thread_local! {
+    static MACHINE: RefCell<Option<CHIP8<CanvasFrontend>>> = const { RefCell::new(None) };
+}
+
+extern "C" {
+    /// Fill `len` bytes at `ptr` with random bytes, e.g. from
+    /// `crypto.getRandomValues`.
+    fn chip8_fill_random(ptr: *mut u8, len: usize);
+}
+
+fn fill_random(buf: &mut [u8]) -> Result<(), getrandom::Error> {
+    unsafe { chip8_fill_random(buf.as_mut_ptr(), buf.len()) };
+    Ok(())
+}
+
+getrandom::register_custom_getrandom!(fill_random);
+
+fn with_machine<T: Default>(f: impl FnOnce(&mut CHIP8<CanvasFrontend>) -> T) -> T {
+    MACHINE.with(|machine| machine.borrow_mut().as_mut().map(f).unwrap_or_default())
+}
+
+/// Room for a ROM of `len` bytes, for the page to copy it into before
+/// `chip8_start`.
+#[no_mangle]
+pub extern "C" fn chip8_alloc(len: usize) -> *mut u8 {
+    let mut rom = Vec::with_capacity(len);
+    let ptr = rom.as_mut_ptr();
+    std::mem::forget(rom);
+    ptr
+}
+
+/// Start a new machine running the `len` bytes at `rom`, from `chip8_alloc`.
+///
+/// # Safety
+///
+/// `rom` must come from `chip8_alloc(len)` and is freed here. Returns 1,
+/// leaving the previous machine running, if the ROM does not fit in RAM.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_start(rom: *mut u8, len: usize) -> u32 {
+    let rom = Vec::from_raw_parts(rom, len, len);
+    let mut chip8 = CHIP8::with_frontend(Registers::new(), CanvasFrontend::default());
+    if chip8.load_bytes(&rom).is_err() {
+        return 1;
+    }
+    chip8.reset();
+    MACHINE.with(|machine| *machine.borrow_mut() = Some(chip8));
+    0
+}
+
+/// Run one 60th of a second. Returns 1 if an instruction failed, after
+/// which the machine is stopped and further frames do nothing.
+#[no_mangle]
+pub extern "C" fn chip8_frame() -> u32 {
+    let failed = with_machine(|chip8| chip8.step_frame().is_err());
+    if failed {
+        MACHINE.with(|machine| *machine.borrow_mut() = None);
+    }
+    failed as u32
+}
+
+/// Press (`down` nonzero) or release keypad key `key`.
+#[no_mangle]
+pub extern "C" fn chip8_key(key: u8, down: u32) {
+    with_machine(|chip8| chip8.frontend_mut().set_key(key, down != 0))
+}
+
+/// The screen as RGBA bytes, `chip8_width` by `chip8_height`.
+#[no_mangle]
+pub extern "C" fn chip8_rgba() -> *const u8 {
+    with_machine(|chip8| Some(chip8.frontend().rgba().as_ptr())).unwrap_or(std::ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn chip8_width() -> usize {
+    with_machine(|chip8| chip8.frontend().width())
+}
+
+#[no_mangle]
+pub extern "C" fn chip8_height() -> usize {
+    with_machine(|chip8| chip8.frontend().height())
+}
+
+/// 1 while the buzzer should sound.
+#[no_mangle]
+pub extern "C" fn chip8_beeping() -> u32 {
+    with_machine(|chip8| chip8.frontend().is_beeping() as u32)
+}
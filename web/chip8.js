@@ -0,0 +1,85 @@
+// Runs rust_chip_8.wasm, built as described in src/wasm.rs, on the canvas.
+
+const KEYPAD = {
+  Digit1: 0x1, Digit2: 0x2, Digit3: 0x3, Digit4: 0xC,
+  KeyQ: 0x4, KeyW: 0x5, KeyE: 0x6, KeyR: 0xD,
+  KeyA: 0x7, KeyS: 0x8, KeyD: 0x9, KeyF: 0xE,
+  KeyZ: 0xA, KeyX: 0x0, KeyC: 0xB, KeyV: 0xF,
+};
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+let exports = null;
+let buzzer = null;
+let running = false;
+
+function fillRandom(ptr, len) {
+  crypto.getRandomValues(new Uint8Array(exports.memory.buffer, ptr, len));
+}
+
+function setBuzzer(on) {
+  if (on && !buzzer) {
+    const audio = new AudioContext();
+    buzzer = audio.createOscillator();
+    buzzer.type = "square";
+    buzzer.frequency.value = 440;
+    const gain = audio.createGain();
+    gain.gain.value = 0.1;
+    buzzer.connect(gain).connect(audio.destination);
+    buzzer.start();
+  } else if (!on && buzzer) {
+    buzzer.stop();
+    buzzer.context.close();
+    buzzer = null;
+  }
+}
+
+function frame() {
+  if (exports.chip8_frame() !== 0) {
+    console.error("The ROM stopped at an instruction it cannot run");
+    setBuzzer(false);
+    running = false;
+    return;
+  }
+  const width = exports.chip8_width();
+  const height = exports.chip8_height();
+  if (width > 0) {
+    canvas.width = width;
+    canvas.height = height;
+    const rgba = new Uint8ClampedArray(exports.memory.buffer, exports.chip8_rgba(), width * height * 4);
+    context.putImageData(new ImageData(rgba.slice(), width, height), 0, 0);
+  }
+  setBuzzer(exports.chip8_beeping() !== 0);
+  requestAnimationFrame(frame);
+}
+
+function key(event, down) {
+  if (exports && event.code in KEYPAD) {
+    exports.chip8_key(KEYPAD[event.code], down ? 1 : 0);
+    event.preventDefault();
+  }
+}
+
+document.addEventListener("keydown", (event) => key(event, true));
+document.addEventListener("keyup", (event) => key(event, false));
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const rom = new Uint8Array(await event.target.files[0].arrayBuffer());
+  if (!exports) {
+    const { instance } = await WebAssembly.instantiateStreaming(
+      fetch("rust_chip_8.wasm"),
+      { env: { chip8_fill_random: fillRandom } },
+    );
+    exports = instance.exports;
+  }
+  const ptr = exports.chip8_alloc(rom.length);
+  new Uint8Array(exports.memory.buffer, ptr, rom.length).set(rom);
+  if (exports.chip8_start(ptr, rom.length) !== 0) {
+    console.error("The ROM is too large to fit in RAM");
+    return;
+  }
+  if (!running) {
+    running = true;
+    requestAnimationFrame(frame);
+  }
+});